@@ -7,7 +7,7 @@ use std::io::{self, Write};
 use clap::{App, AppSettings};
 
 use immeta::GenericMetadata;
-use immeta::formats::{jpeg, gif, png, webp};
+use immeta::formats::{jpeg, gif, png, webp, bmp, tiff, ico, pnm, exr, qoi, heif, svg, dds, psd, wbmp, jp2};
 
 fn main() {
     let matches = App::new("immeta image analyzer")
@@ -23,7 +23,8 @@ fn main() {
     
     let file_name = matches.value_of("FILE").unwrap();
 
-    let metadata = match immeta::load_from_file(file_name) {
+    // the extension is needed to load images without a signature, like WBMP
+    let metadata = match immeta::load_from_file_with_hint(file_name) {
         Ok(md) => md,
         Err(e) => {
             let _ = writeln!(&mut io::stderr(), "Cannot load image metadata from {}: {}", file_name, e);
@@ -36,6 +37,20 @@ fn main() {
         GenericMetadata::Gif(md) => print_gif_metadata(md),
         GenericMetadata::Png(md) => print_png_metadata(md),
         GenericMetadata::Webp(md) => print_webp_metadata(md),
+        GenericMetadata::Bmp(md) => print_bmp_metadata(md),
+        GenericMetadata::Tiff(md) => print_tiff_metadata(md),
+        GenericMetadata::Ico(md) => print_ico_metadata(md),
+        GenericMetadata::Pnm(md) => print_pnm_metadata(md),
+        GenericMetadata::Exr(md) => print_exr_metadata(md),
+        GenericMetadata::Qoi(md) => print_qoi_metadata(md),
+        GenericMetadata::Heif(md) => print_heif_metadata(md),
+        GenericMetadata::Svg(md) => print_svg_metadata(md),
+        GenericMetadata::Dds(md) => print_dds_metadata(md),
+        GenericMetadata::Psd(md) => print_psd_metadata(md),
+        GenericMetadata::Wbmp(md) => print_wbmp_metadata(md),
+        GenericMetadata::Jp2(md) => print_jp2_metadata(md),
+        md => println!("{} image:\n  Width: {}\n  Height: {}",
+                       md.format(), md.dimensions().width, md.dimensions().height),
     }
 }
 
@@ -48,11 +63,52 @@ fn print_jpeg_metadata(md: jpeg::Metadata) {
     println!("  Differential: {}", md.differential);
     println!("  Entropy coding: {}", md.entropy_coding);
     println!("  Coding process: {}", md.coding_process);
+    println!("  Components: {}", md.num_components);
+    println!("  Scans: {}", md.scan_count);
+    if let Some(interval) = md.restart_interval {
+        println!("  Restart interval: {} MCUs", interval);
+    }
+    if let Some(subsampling) = md.subsampling {
+        println!("  Chroma subsampling: {}", subsampling);
+    }
+    println!("  Quantization tables: {}", md.quantization_tables);
+    println!("  Huffman tables: {}", md.huffman_tables);
+    if let Some(transform) = md.adobe_transform {
+        println!("  Adobe color transform: {}", transform);
+    }
+    if let Some(orientation) = md.orientation() {
+        println!("  Orientation: {}", orientation);
+    }
+    if let Some(gps) = md.exif.as_ref().and_then(|e| e.gps()) {
+        println!("  GPS location: {:.6}, {:.6}", gps.latitude, gps.longitude);
+        if let Some(altitude) = gps.altitude {
+            println!("  GPS altitude: {} m", altitude);
+        }
+    }
+    if let Some(thumbnail) = md.exif.as_ref().and_then(|e| e.thumbnail()) {
+        println!("  EXIF thumbnail: {} ({} bytes)", thumbnail.dimensions, thumbnail.data.len());
+    }
+    if let Some((x, y)) = md.density.and_then(|d| d.dpi()) {
+        println!("  Resolution: {:.0}x{:.0} dpi", x, y);
+    }
+    for comment in &md.comments {
+        println!("  Comment: {}", comment);
+    }
+    if let Some(ref xmp) = md.xmp {
+        println!("  XMP packet: {} bytes", xmp.len());
+    }
+    if let Some(ref xmp) = md.extended_xmp {
+        println!("  Extended XMP packet: {} bytes", xmp.len());
+    }
 }
 
 fn print_gif_metadata(md: gif::Metadata) {
-    println!("GIF image:");
-    // TODO
+    println!("{}", md);
+    for block in &md.blocks {
+        if let gif::Block::CommentExtension(ref ext) = *block {
+            println!("  Comment: {}", ext.text);
+        }
+    }
 }
 
 fn print_png_metadata(md: png::Metadata) {
@@ -64,9 +120,176 @@ fn print_png_metadata(md: png::Metadata) {
     println!("  Compression method: {}", md.compression_method);
     println!("  Filter method: {}", md.filter_method);
     println!("  Interlace method: {}", md.interlace_method);
+    if let Some(n) = md.palette_entries {
+        println!("  Palette entries: {}", n);
+    }
+    println!("  Transparent: {}", md.is_transparent());
+    if let Some(animation) = md.animation {
+        println!("  Animation frames: {}", animation.num_frames);
+        println!("  Animation plays: {}", animation.num_plays);
+    }
+    if let Some((x, y)) = md.physical_dimensions.and_then(|pd| pd.dpi()) {
+        println!("  Resolution: {:.0}x{:.0} dpi", x, y);
+    }
+    if let Some(gamma) = md.gamma_approx() {
+        println!("  Gamma: {}", gamma);
+    }
+    if let Some(intent) = md.rendering_intent {
+        println!("  sRGB rendering intent: {}", intent);
+    }
+    if let Some(c) = md.chromaticities {
+        println!("  Chromaticities: white ({}, {}), red ({}, {}), green ({}, {}), blue ({}, {})",
+                 c.white_x, c.white_y, c.red_x, c.red_y, c.green_x, c.green_y, c.blue_x, c.blue_y);
+    }
+    if let Some(ref bits) = md.significant_bits {
+        println!("  Significant bits: {:?}", bits);
+    }
+    if let Some(profile) = md.icc_profile {
+        println!("  ICC profile: {}", profile.name);
+    }
 }
 
 fn print_webp_metadata(md: webp::Metadata) {
     println!("WEBP image:");
-    // TODO
+    println!("  Width: {}", md.dimensions().width);
+    println!("  Height: {}", md.dimensions().height);
+    println!("  Chunks:");
+    for &(id, len) in md.chunks() {
+        println!("    {}: {} bytes", id, len);
+    }
+}
+
+fn print_bmp_metadata(md: bmp::Metadata) {
+    println!("BMP image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Header version: {}", md.header_version);
+    println!("  Bit depth: {} bpp", md.bit_depth);
+    println!("  Compression: {}", md.compression);
+    println!("  Top-down: {}", md.top_down);
+}
+
+fn print_tiff_metadata(md: tiff::Metadata) {
+    println!("TIFF image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Bits per sample: {:?}", md.bits_per_sample);
+    println!("  Samples per pixel: {}", md.samples_per_pixel);
+    println!("  Photometric interpretation: {}", md.photometric_interpretation);
+    println!("  Compression: {}", md.compression);
+    if md.pages.len() > 1 {
+        println!("  Pages: {}", md.pages.len());
+        for (i, page) in md.pages.iter().enumerate().skip(1) {
+            println!("  Page {}: {}, {}, {}", i, page.dimensions, page.photometric_interpretation, page.compression);
+        }
+    }
+}
+
+fn print_ico_metadata(md: ico::Metadata) {
+    println!("{} image:", md.icon_type);
+    println!("  Images: {}", md.entries.len());
+    for (i, entry) in md.entries.iter().enumerate() {
+        println!("  Image {}:", i);
+        println!("    Width: {}", entry.dimensions.width);
+        println!("    Height: {}", entry.dimensions.height);
+        if let Some(bit_count) = entry.bit_count {
+            println!("    Bit count: {} bpp", bit_count);
+        }
+        if let Some((x, y)) = entry.hotspot {
+            println!("    Hotspot: ({}, {})", x, y);
+        }
+        println!("    Data size: {} bytes", entry.size);
+        println!("    Data offset: {}", entry.offset);
+    }
+}
+
+fn print_pnm_metadata(md: pnm::Metadata) {
+    println!("PNM image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Format: {}", md.format);
+    println!("  Binary: {}", md.binary);
+    if let Some(max_value) = md.max_value {
+        println!("  Maximum color value: {}", max_value);
+    }
+}
+
+fn print_exr_metadata(md: exr::Metadata) {
+    println!("OpenEXR image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Version: {}", md.version);
+    println!("  Tiled: {}", md.tiled);
+    println!("  Compression: {}", md.compression);
+    for channel in &md.channels {
+        println!("  Channel {}: {}", channel.name, channel.pixel_type);
+    }
+}
+
+fn print_qoi_metadata(md: qoi::Metadata) {
+    println!("QOI image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Channels: {}", md.channels);
+    println!("  Colorspace: {}", md.colorspace);
+}
+
+fn print_heif_metadata(md: heif::Metadata) {
+    println!("{} image:", if md.is_avif() { "AVIF" } else { "HEIF" });
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Major brand: {}", md.major_brand);
+    println!("  Compatible brands: {}", md.compatible_brands.join(", "));
+}
+
+fn print_svg_metadata(md: svg::Metadata) {
+    println!("SVG image:");
+    match md.dimensions {
+        Some(d) => {
+            println!("  Width: {}", d.width);
+            println!("  Height: {}", d.height);
+        }
+        None => println!("  Dimensions: relative"),
+    }
+    if let Some((x, y, w, h)) = md.view_box {
+        println!("  View box: {} {} {} {}", x, y, w, h);
+    }
+}
+
+fn print_dds_metadata(md: dds::Metadata) {
+    println!("DDS image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Format: {}", md.format);
+    if let Some(bit_count) = md.bit_count {
+        println!("  Bit count: {} bpp", bit_count);
+    }
+    if let Some(dxgi_format) = md.dxgi_format {
+        println!("  DXGI format: {}", dxgi_format);
+    }
+    println!("  Mipmaps: {}", md.mipmap_count);
+    println!("  Array size: {}", md.array_size);
+    println!("  Cube map: {}", md.is_cubemap);
+}
+
+fn print_psd_metadata(md: psd::Metadata) {
+    println!("{} image:", if md.is_psb() { "PSB" } else { "PSD" });
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Channels: {}", md.channels);
+    println!("  Depth: {} bits per channel", md.depth);
+    println!("  Color mode: {}", md.color_mode);
+}
+
+fn print_wbmp_metadata(md: wbmp::Metadata) {
+    println!("WBMP image:");
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+}
+
+fn print_jp2_metadata(md: jp2::Metadata) {
+    println!("JPEG 2000 {}:", if md.jp2_container { "image" } else { "codestream" });
+    println!("  Width: {}", md.dimensions.width);
+    println!("  Height: {}", md.dimensions.height);
+    println!("  Components: {}", md.components);
 }
@@ -0,0 +1,597 @@
+//! EXIF metadata support.
+//!
+//! EXIF metadata is stored as a TIFF document embedded into the image file, so this module
+//! uses `common::tiff` to read it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, Cursor};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use types::{Result, Dimensions, LoadOptions};
+use traits::LoadableMetadata;
+use utils::ByteOrder;
+use common::tiff::{self, TiffReader, Tag, EntryType, EntryTypeRepr, entry_types};
+use formats::jpeg;
+
+const TAG_MAKE: u16 = 0x010f;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_X_RESOLUTION: u16 = 0x011a;
+const TAG_Y_RESOLUTION: u16 = 0x011b;
+const TAG_RESOLUTION_UNIT: u16 = 0x0128;
+const TAG_EXIF_IFD_POINTER: u16 = 0x8769;
+const TAG_GPS_IFD_POINTER: u16 = 0x8825;
+const TAG_MAKER_NOTE: u16 = 0x927c;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF: u16 = 0x0005;
+const TAG_GPS_ALTITUDE: u16 = 0x0006;
+
+/// Image orientation, as defined by EXIF spec.
+///
+/// Describes the transformation which should be applied to the stored image in order
+/// to display it correctly.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Orientation {
+    /// No transformation is needed.
+    Normal,
+    /// The image is mirrored horizontally.
+    FlipHorizontal,
+    /// The image is rotated by 180 degrees.
+    Rotate180,
+    /// The image is mirrored vertically.
+    FlipVertical,
+    /// The image is mirrored along its top-left to bottom-right diagonal.
+    Transpose,
+    /// The image should be rotated by 90 degrees clockwise.
+    Rotate90,
+    /// The image is mirrored along its top-right to bottom-left diagonal.
+    Transverse,
+    /// The image should be rotated by 270 degrees clockwise.
+    Rotate270
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Orientation::Normal => "Normal",
+            Orientation::FlipHorizontal => "Flipped horizontally",
+            Orientation::Rotate180 => "Rotated by 180 degrees",
+            Orientation::FlipVertical => "Flipped vertically",
+            Orientation::Transpose => "Transposed",
+            Orientation::Rotate90 => "Rotated by 90 degrees clockwise",
+            Orientation::Transverse => "Transversed",
+            Orientation::Rotate270 => "Rotated by 270 degrees clockwise",
+        })
+    }
+}
+
+impl Orientation {
+    fn from_u16(n: u16) -> Option<Orientation> {
+        match n {
+            1 => Some(Orientation::Normal),
+            2 => Some(Orientation::FlipHorizontal),
+            3 => Some(Orientation::Rotate180),
+            4 => Some(Orientation::FlipVertical),
+            5 => Some(Orientation::Transpose),
+            6 => Some(Orientation::Rotate90),
+            7 => Some(Orientation::Transverse),
+            8 => Some(Orientation::Rotate270),
+            _ => None
+        }
+    }
+}
+
+/// Geographic location of an image, as recorded in the GPS IFD of EXIF metadata.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GpsLocation {
+    /// Latitude in decimal degrees, negative in the southern hemisphere.
+    pub latitude: f64,
+    /// Longitude in decimal degrees, negative in the western hemisphere.
+    pub longitude: f64,
+    /// Altitude in meters, negative below the sea level, if it is available.
+    pub altitude: Option<f64>
+}
+
+/// A JPEG thumbnail embedded into EXIF metadata.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Thumbnail {
+    /// Dimensions of the thumbnail.
+    pub dimensions: Dimensions,
+    /// The thumbnail itself, a complete JPEG image.
+    pub data: Vec<u8>
+}
+
+/// Contains EXIF metadata of an image.
+///
+/// Entries of each directory are keyed by their numeric tags.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Exif {
+    /// Entries of the primary image directory (IFD0).
+    pub ifd0: BTreeMap<u16, Entry>,
+    /// Entries of the EXIF sub-IFD, if it is present.
+    pub exif_ifd: Option<BTreeMap<u16, Entry>>,
+    /// Entries of the GPS sub-IFD, if it is present.
+    pub gps_ifd: Option<BTreeMap<u16, Entry>>,
+    /// Entries of the thumbnail image directory (IFD1), if it is present.
+    pub ifd1: Option<BTreeMap<u16, Entry>>,
+    /// Data of the JPEG thumbnail referenced by IFD1, if it is present.
+    ///
+    /// Use `thumbnail()` to obtain the thumbnail along with its dimensions.
+    pub thumbnail_data: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "tag_map"))]
+    tags: HashMap<Tag, EntryValue>
+}
+
+// `tags` is built from `ifd0` and `exif_ifd`, so comparing and hashing the directories
+// is sufficient; this also keeps equality reflexive, since tag values may contain NaNs
+impl PartialEq for Exif {
+    fn eq(&self, other: &Exif) -> bool {
+        self.ifd0 == other.ifd0 && self.exif_ifd == other.exif_ifd && self.gps_ifd == other.gps_ifd &&
+            self.ifd1 == other.ifd1 && self.thumbnail_data == other.thumbnail_data
+    }
+}
+
+impl Eq for Exif {}
+
+impl Hash for Exif {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ifd0.hash(state);
+        self.exif_ifd.hash(state);
+        self.gps_ifd.hash(state);
+        self.ifd1.hash(state);
+        self.thumbnail_data.hash(state);
+    }
+}
+
+impl Exif {
+    /// Loads EXIF metadata from the provided TIFF document.
+    ///
+    /// The buffer must contain a TIFF header followed by the IFDs, i.e. it should not contain
+    /// any container-specific prefixes like `Exif\0\0` of JPEG APP1 segments.
+    #[inline]
+    pub fn load(data: &[u8]) -> Result<Exif> {
+        Exif::load_with_options(data, &LoadOptions::default())
+    }
+
+    /// Loads EXIF metadata from the provided TIFF document, using the provided options.
+    ///
+    /// The allocation limit of the options applies to loading data of the entries.
+    pub fn load_with_options(data: &[u8], options: &LoadOptions) -> Result<Exif> {
+        let ifds = try!(TiffReader::with_options(Cursor::new(data), options).ifds());
+
+        let mut ifds_iter = (&ifds).into_iter();
+        let ifd0 = match ifds_iter.next() {
+            Some(ifd) => try!(read_entries(try!(ifd), ifds.byte_order())),
+            None => return Err(invalid_format!("EXIF data does not contain any IFDs"))
+        };
+
+        // broken thumbnail directory should not prevent loading the rest of the metadata
+        let ifd1 = match ifds_iter.next() {
+            Some(Ok(ifd)) => read_entries(ifd, ifds.byte_order()).ok(),
+            _ => None
+        };
+        let thumbnail_data = ifd1.as_ref().and_then(|ifd1| thumbnail_data(data, ifd1));
+
+        // likewise, broken sub-IFDs should not prevent loading IFD0, e.g. orientation
+        let exif_ifd = read_sub_ifd(&ifds, &ifd0, TAG_EXIF_IFD_POINTER, "EXIF").ok().and_then(|x| x);
+        let gps_ifd = read_sub_ifd(&ifds, &ifd0, TAG_GPS_IFD_POINTER, "GPS").ok().and_then(|x| x);
+
+        let mut tags = HashMap::new();
+        for (&tag, entry) in ifd0.iter().chain(exif_ifd.iter().flat_map(|ifd| ifd.iter())) {
+            // entries whose data is broken are not included
+            if let Ok(value) = entry.value() {
+                tags.entry(tag.into()).or_insert(value);
+            }
+        }
+
+        Ok(Exif {
+            ifd0: ifd0,
+            exif_ifd: exif_ifd,
+            gps_ifd: gps_ifd,
+            ifd1: ifd1,
+            thumbnail_data: thumbnail_data,
+            tags: tags
+        })
+    }
+
+    /// Returns typed values of all entries of IFD0 and the EXIF sub-IFD, keyed by their tags.
+    ///
+    /// The map is built when EXIF metadata is loaded, so it does not reflect changes made to
+    /// `ifd0` and `exif_ifd` afterwards. Entries which can't be decoded are omitted. GPS and
+    /// IFD1 entries are not included because their tags overlap with those of other directories.
+    #[inline]
+    pub fn tags(&self) -> &HashMap<Tag, EntryValue> {
+        &self.tags
+    }
+
+    /// Returns an entry with the given tag, looking it up in IFD0 first and in the EXIF
+    /// sub-IFD next.
+    pub fn get(&self, tag: u16) -> Option<&Entry> {
+        self.ifd0.get(&tag)
+            .or_else(|| self.exif_ifd.as_ref().and_then(|ifd| ifd.get(&tag)))
+    }
+
+    /// Returns image orientation from IFD0, if it is present and valid.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.ifd0.get(&TAG_ORIENTATION)
+            .and_then(|entry| entry.values::<entry_types::Short>())
+            .and_then(|values| values.ok())
+            .and_then(|values| values.first().cloned())
+            .and_then(Orientation::from_u16)
+    }
+
+    /// Returns horizontal and vertical resolution in dots per inch, if it is available.
+    ///
+    /// XResolution and YResolution entries of IFD0 are required. They are converted
+    /// according to ResolutionUnit, which defaults to inches; `None` is returned if the unit
+    /// is absolute (1) or unknown, or if any of the values is not a positive finite number.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        const CM_PER_INCH: f64 = 2.54;
+
+        let unit = match self.ifd0.get(&TAG_RESOLUTION_UNIT) {
+            Some(entry) => match entry.integer_value() {
+                Some(2) => 1.0,
+                Some(3) => CM_PER_INCH,
+                _ => return None
+            },
+            None => 1.0
+        };
+
+        let x = resolution_value(&self.ifd0, TAG_X_RESOLUTION);
+        let y = resolution_value(&self.ifd0, TAG_Y_RESOLUTION);
+        match (x, y) {
+            (Some(x), Some(y)) => Some((x * unit, y * unit)),
+            _ => None
+        }
+    }
+
+    /// Returns the raw data of the MakerNote entry of the EXIF sub-IFD, if it is present.
+    ///
+    /// The format of this data is specific to the camera vendor and it is not decoded;
+    /// `maker_note_vendor()` may be used to find out which vendor it comes from.
+    pub fn maker_note(&self) -> Option<&[u8]> {
+        self.exif_ifd.as_ref()
+            .and_then(|ifd| ifd.get(&TAG_MAKER_NOTE))
+            .map(|entry| entry.data())
+    }
+
+    /// Returns the name of the camera vendor whose format the MakerNote data is in, if it
+    /// can be recognized.
+    ///
+    /// Detection is best-effort and is based on the signatures which most vendors put at
+    /// the beginning of their maker notes. Canon maker notes do not have a signature, so
+    /// they are recognized by the Make entry of IFD0 instead.
+    pub fn maker_note_vendor(&self) -> Option<&'static str> {
+        const SIGNATURES: &'static [(&'static [u8], &'static str)] = &[
+            (b"Nikon\0", "Nikon"),
+            (b"OLYMPUS\0", "Olympus"),
+            (b"OLYMP\0", "Olympus"),
+            (b"OM SYSTEM\0", "OM System"),
+            (b"FUJIFILM", "Fujifilm"),
+            (b"Panasonic\0", "Panasonic"),
+            (b"SONY", "Sony"),
+            (b"PENTAX ", "Pentax"),
+            (b"AOC\0", "Pentax"),
+            (b"SIGMA\0", "Sigma"),
+            (b"LEICA", "Leica"),
+            (b"Apple iOS\0", "Apple"),
+        ];
+
+        let data = match self.maker_note() {
+            Some(data) => data,
+            None => return None
+        };
+
+        if let Some(&(_, vendor)) = SIGNATURES.iter().find(|&&(sig, _)| data.starts_with(sig)) {
+            return Some(vendor);
+        }
+
+        match self.ifd0.get(&TAG_MAKE) {
+            Some(make) if make.data().starts_with(b"Canon") => Some("Canon"),
+            _ => None
+        }
+    }
+
+    /// Returns the location where the image was taken, if it is available.
+    ///
+    /// Latitude, longitude and their reference (N/S and E/W) entries of the GPS IFD are
+    /// required; `None` is returned if any of them is missing or invalid. Altitude is
+    /// optional.
+    pub fn gps(&self) -> Option<GpsLocation> {
+        let ifd = match self.gps_ifd {
+            Some(ref ifd) => ifd,
+            None => return None
+        };
+
+        let latitude = gps_coordinate(ifd, TAG_GPS_LATITUDE_REF, TAG_GPS_LATITUDE, b'N', b'S');
+        let longitude = gps_coordinate(ifd, TAG_GPS_LONGITUDE_REF, TAG_GPS_LONGITUDE, b'E', b'W');
+
+        match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => Some(GpsLocation {
+                latitude: latitude,
+                longitude: longitude,
+                altitude: gps_altitude(ifd)
+            }),
+            _ => None
+        }
+    }
+
+    /// Returns the embedded JPEG thumbnail, if it is present and valid.
+    ///
+    /// The thumbnail is referenced by JPEGInterchangeFormat and JPEGInterchangeFormatLength
+    /// entries of IFD1; its dimensions are obtained by loading its JPEG metadata. `None` is
+    /// returned if there is no thumbnail or if it is not a valid JPEG image.
+    pub fn thumbnail(&self) -> Option<Thumbnail> {
+        let data = match self.thumbnail_data {
+            Some(ref data) => data,
+            None => return None
+        };
+        jpeg::Metadata::load_from_buf(data).ok().map(|md| Thumbnail {
+            dimensions: md.dimensions,
+            data: data.clone()
+        })
+    }
+}
+
+/// Returns the data of the thumbnail referenced by IFD1 entries, if they are present and
+/// point inside the EXIF data.
+fn thumbnail_data(data: &[u8], ifd1: &BTreeMap<u16, Entry>) -> Option<Vec<u8>> {
+    let offset = ifd1.get(&TAG_JPEG_INTERCHANGE_FORMAT).and_then(|e| e.integer_value());
+    let length = ifd1.get(&TAG_JPEG_INTERCHANGE_FORMAT_LENGTH).and_then(|e| e.integer_value());
+    match (offset, length) {
+        (Some(offset), Some(length)) => {
+            let start = offset as usize;
+            let end = start.checked_add(length as usize);
+            match end {
+                Some(end) if end <= data.len() => Some(data[start..end].to_vec()),
+                _ => None
+            }
+        }
+        _ => None
+    }
+}
+
+fn resolution_value(ifd: &BTreeMap<u16, Entry>, tag: u16) -> Option<f64> {
+    match ifd.get(&tag).and_then(|entry| entry.rational_values()) {
+        Some(Ok(values)) => match values.first() {
+            Some(&v) if v > 0.0 && v.is_finite() => Some(v),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Reads a coordinate stored as degrees, minutes and seconds, with the sign determined by
+/// the reference entry.
+fn gps_coordinate(ifd: &BTreeMap<u16, Entry>, ref_tag: u16, tag: u16, positive: u8, negative: u8) -> Option<f64> {
+    let sign = match ifd.get(&ref_tag) {
+        Some(entry) if entry.entry_type() == EntryType::Ascii => match entry.data().first() {
+            Some(&c) if c == positive => 1.0,
+            Some(&c) if c == negative => -1.0,
+            _ => return None
+        },
+        _ => return None
+    };
+
+    let values = match ifd.get(&tag).and_then(|entry| entry.rational_values()) {
+        Some(Ok(values)) => values,
+        _ => return None
+    };
+    if values.len() != 3 || values.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+
+    Some(sign * (values[0] + values[1] / 60.0 + values[2] / 3600.0))
+}
+
+fn gps_altitude(ifd: &BTreeMap<u16, Entry>) -> Option<f64> {
+    let altitude = match ifd.get(&TAG_GPS_ALTITUDE).and_then(|entry| entry.rational_values()) {
+        Some(Ok(values)) => match values.first() {
+            Some(&v) if v.is_finite() => v,
+            _ => return None
+        },
+        _ => return None
+    };
+
+    // reference value 1 means that the altitude is below the sea level
+    let below_sea_level = ifd.get(&TAG_GPS_ALTITUDE_REF)
+        .and_then(|entry| entry.values::<entry_types::Byte>())
+        .and_then(|values| values.ok())
+        .map_or(false, |values| values.first() == Some(&1));
+
+    Some(if below_sea_level { -altitude } else { altitude })
+}
+
+/// Reads entries of a sub-IFD referenced by the given pointer entry of IFD0, if it is present.
+fn read_sub_ifd<R: Read + Seek>(ifds: &tiff::LazyIfds<R>, ifd0: &BTreeMap<u16, Entry>,
+                                pointer_tag: u16, name: &str) -> Result<Option<BTreeMap<u16, Entry>>> {
+    let offset = match ifd0.get(&pointer_tag) {
+        Some(entry) => match entry.values::<entry_types::Long>() {
+            Some(offsets) => try!(offsets).first().cloned(),
+            None => return Err(invalid_format!("invalid type of {} IFD pointer: {:?}",
+                                               name, entry.entry_type()))
+        },
+        None => None
+    };
+
+    match offset {
+        Some(offset) => {
+            let ifd = try!(ifds.ifd_at(offset));
+            Ok(Some(try!(read_entries(ifd, ifds.byte_order()))))
+        }
+        None => Ok(None)
+    }
+}
+
+fn read_entries<R: Read + Seek>(ifd: tiff::Ifd<R>, byte_order: ByteOrder) -> Result<BTreeMap<u16, Entry>> {
+    let mut entries = BTreeMap::new();
+    for entry in ifd {
+        let entry = try!(entry);
+
+        // data of entries of unknown types can't be loaded because its size is unknown
+        if let EntryType::Unknown(_) = entry.entry_type() {
+            continue;
+        }
+
+        entries.insert(entry.tag(), Entry {
+            entry_type: entry.entry_type(),
+            count: entry.count(),
+            data: try!(entry.raw_bytes()),
+            byte_order: byte_order
+        });
+    }
+    Ok(entries)
+}
+
+/// Serializes a tag map as a sequence of pairs, since not all tags can be map keys in
+/// serialization formats like JSON.
+#[cfg(feature = "serde")]
+mod tag_map {
+    use std::collections::HashMap;
+
+    use serde::{Serializer, Deserializer, Deserialize};
+
+    use common::tiff::Tag;
+    use super::EntryValue;
+
+    pub fn serialize<S: Serializer>(map: &HashMap<Tag, EntryValue>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<Tag, EntryValue>, D::Error> {
+        Vec::<(Tag, EntryValue)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+/// Contains all of the items of an EXIF entry, loaded according to the entry type.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntryValue {
+    Bytes(Vec<u8>),
+    Ascii(Vec<String>),
+    Shorts(Vec<u16>),
+    Longs(Vec<u32>),
+    Rationals(Vec<(u32, u32)>),
+    SignedBytes(Vec<i8>),
+    Undefined(Vec<u8>),
+    SignedShorts(Vec<i16>),
+    SignedLongs(Vec<i32>),
+    SignedRationals(Vec<(i32, i32)>),
+    Floats(Vec<f32>),
+    Doubles(Vec<f64>),
+}
+
+/// Represents a single EXIF entry.
+///
+/// Unlike `common::tiff::Entry`, this structure holds the entry data in memory.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    entry_type: EntryType,
+    count: u32,
+    data: Vec<u8>,
+    byte_order: ByteOrder
+}
+
+impl Entry {
+    /// Returns entry type.
+    #[inline]
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    /// Returns the number of items this entry contains.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the raw entry data, in the byte order of the EXIF block.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the first item of a `Short` or `Long` entry as an unsigned integer.
+    fn integer_value(&self) -> Option<u32> {
+        match self.entry_type {
+            EntryType::Short => self.values::<entry_types::Short>()
+                .and_then(|values| values.ok())
+                .and_then(|values| values.first().map(|&v| v as u32)),
+            EntryType::Long => self.values::<entry_types::Long>()
+                .and_then(|values| values.ok())
+                .and_then(|values| values.first().cloned()),
+            _ => None
+        }
+    }
+
+    /// Returns a vector containing all of the items of a rational entry, converted to
+    /// floating point numbers.
+    ///
+    /// Both `Rational` and `SignedRational` entries are supported; for other entry types
+    /// this method returns `None`. Items with zero denominators are converted to NaN.
+    pub fn rational_values(&self) -> Option<Result<Vec<f64>>> {
+        let nan = ::std::f64::NAN;
+        match self.entry_type {
+            EntryType::Rational => self.values::<entry_types::Rational>().map(|values| values.map(|values|
+                values.into_iter().map(|v| entry_types::Rational::as_f64(v).unwrap_or(nan)).collect()
+            )),
+            EntryType::SignedRational => self.values::<entry_types::SignedRational>().map(|values| values.map(|values|
+                values.into_iter().map(|v| entry_types::SignedRational::as_f64(v).unwrap_or(nan)).collect()
+            )),
+            _ => None
+        }
+    }
+
+    /// Returns all of the items of this entry, loaded according to the entry type.
+    pub fn value(&self) -> Result<EntryValue> {
+        fn load<T: EntryTypeRepr>(entry: &Entry, wrap: fn(Vec<T::Repr>) -> EntryValue) -> Result<EntryValue> {
+            entry.values::<T>().expect("entry type mismatch").map(wrap)
+        }
+
+        match self.entry_type {
+            EntryType::Byte           => load::<entry_types::Byte>(self, EntryValue::Bytes),
+            EntryType::Ascii          => load::<entry_types::Ascii>(self, EntryValue::Ascii),
+            EntryType::Short          => load::<entry_types::Short>(self, EntryValue::Shorts),
+            EntryType::Long           => load::<entry_types::Long>(self, EntryValue::Longs),
+            EntryType::Rational       => load::<entry_types::Rational>(self, EntryValue::Rationals),
+            EntryType::SignedByte     => load::<entry_types::SignedByte>(self, EntryValue::SignedBytes),
+            EntryType::Undefined      => load::<entry_types::Undefined>(self, EntryValue::Undefined),
+            EntryType::SignedShort    => load::<entry_types::SignedShort>(self, EntryValue::SignedShorts),
+            EntryType::SignedLong     => load::<entry_types::SignedLong>(self, EntryValue::SignedLongs),
+            EntryType::SignedRational => load::<entry_types::SignedRational>(self, EntryValue::SignedRationals),
+            EntryType::Float          => load::<entry_types::Float>(self, EntryValue::Floats),
+            EntryType::Double         => load::<entry_types::Double>(self, EntryValue::Doubles),
+            EntryType::Unknown(n)     => Err(invalid_format!("unknown type of EXIF entry: {}", n)),
+        }
+    }
+
+    /// Returns a vector containing all of the items of this entry, loaded with the specified
+    /// representation type.
+    ///
+    /// This method returns `None` if the requested representation type does not correspond
+    /// to the actual type of the entry.
+    pub fn values<T: EntryTypeRepr>(&self) -> Option<Result<Vec<T::Repr>>> {
+        if self.entry_type != T::entry_type() {
+            return None;
+        }
+
+        let mut result = Vec::new();
+        match T::read_many_from(&mut &self.data[..], self.byte_order, self.count, &mut result)
+            .map_err(if_eof!("when reading EXIF entry values"))
+        {
+            Ok(_) => Some(Ok(result)),
+            Err(e) => Some(Err(e))
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod riff;
-mod tiff;  // temporarily private to allow backwards-incompatible changes
+pub mod tiff;
+pub mod exif;
@@ -8,7 +8,8 @@ use byteorder::{ReadBytesExt, LittleEndian};
 use types::Result;
 use utils::ReadExt;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ChunkId(pub [u8; 4]);
 
 impl ChunkId {
@@ -159,13 +160,20 @@ impl<'a> RiffListChunk<'a> {
 
     #[inline]
     pub fn next(&mut self) -> Option<Result<RiffChunk>> {
-        if self.cur_chunk_read < self.cur_chunk_len {
-            let to_skip = (self.cur_chunk_len - self.cur_chunk_read) as u64;
+        // chunk data is padded to an even number of bytes, but the padding byte is not
+        // included into the chunk length
+        let padding = (self.cur_chunk_len % 2) as u64;
+        let to_skip = (self.cur_chunk_len - self.cur_chunk_read) as u64 + padding;
+        if to_skip > 0 {
             match self.data.skip_exact_0(to_skip) {
                 Ok(n) if n == to_skip => {}
+                // some writers omit the padding byte after the last chunk
+                Ok(n) if n + padding == to_skip => return None,
                 Ok(_) => return Some(Err(unexpected_eof!())),
                 Err(e) => return Some(Err(e.into()))
             }
+            self.cur_chunk_read = 0;
+            self.cur_chunk_len = 0;
         }
 
         let (id, len) = match read_id_and_len(&mut self.data) {
@@ -198,7 +206,7 @@ fn read_id_and_len<R: Read>(source: &mut R) -> Result<Option<(ChunkId, u32)>> {
         _ => return Err(unexpected_eof!())
     }
 
-    let len = try!(source.read_u32::<LittleEndian>());
+    let len = try_if_eof!(source.read_u32::<LittleEndian>(), "when reading chunk length");
 
     Ok(Some((ChunkId(id), len)))
 }
@@ -274,14 +282,14 @@ mod tests {
     #[test]
     fn test_nested_chunks() {
         let data = build! {
-            b"RIFF", &n(77), b"abcd",
-            b"A   ", &n(1), b"z",
-            b"LIST", &n(56), b"wxyz",
-                b" B  ", &n(3), b"123",
+            b"RIFF", &n(80), b"abcd",
+            b"A   ", &n(1), b"z\0",
+            b"LIST", &n(58), b"wxyz",
+                b" B  ", &n(3), b"123\0",
                 b"LIST", &n(22), b"hi  ",
                     b"  C ", &n(0),
                     b"   D", &n(2), b"op",
-                b"E   ", &n(3), b"fuz"
+                b"E   ", &n(3), b"fuz\0"
         };
         let mut data: &[u8] = &data;
 
@@ -290,7 +298,7 @@ mod tests {
         let mut root = r.root().unwrap();
 
         assert_eq!(root.chunk_id(), ChunkId(*b"RIFF"));
-        assert_eq!(root.len(), 77);
+        assert_eq!(root.len(), 80);
         assert_eq!(root.chunk_type(), ChunkId(*b"abcd"));
 
         check_next_chunk(&mut root, ChunkId(*b"A   "), 1, b"z");
@@ -298,7 +306,7 @@ mod tests {
         {
             let chunk = root.next().unwrap().unwrap();
             assert_eq!(chunk.chunk_id(), ChunkId(*b"LIST"));
-            assert_eq!(chunk.len(), 56);
+            assert_eq!(chunk.len(), 58);
             assert!(chunk.can_have_subchunks());
 
             let chunk = chunk.into_list();
@@ -368,4 +376,33 @@ mod tests {
 
         assert!(root.next().is_none());
     }
+
+    #[test]
+    fn test_odd_chunk_padding() {
+        let data = build! {
+            b"RIFF", &n(39), b"abcd",
+            b"A   ", &n(3), b"123\0",
+            b"B   ", &n(5), b"45678\0",
+            b"C   ", &n(1), b"9"
+        };
+        let mut data: &[u8] = &data;
+
+        let mut r = RiffReader::new(&mut data);
+
+        let mut root = r.root().unwrap();
+
+        check_next_chunk(&mut root, ChunkId(*b"A   "), 3, b"123");
+
+        {
+            // skip the chunk contents completely
+            let chunk = root.next().unwrap().unwrap();
+            assert_eq!(chunk.chunk_id(), ChunkId(*b"B   "));
+            assert_eq!(chunk.len(), 5);
+        }
+
+        // the last padding byte is missing
+        check_next_chunk(&mut root, ChunkId(*b"C   "), 1, b"9");
+
+        assert!(root.next().is_none());
+    }
 }
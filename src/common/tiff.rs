@@ -1,8 +1,8 @@
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Cursor};
 use std::cell::{RefCell, Cell};
 use std::marker::PhantomData;
 
-use types::Result;
+use types::{Result, LoadOptions};
 use utils::{ByteOrder, ByteOrderReadExt};
 
 /// A TIFF document reader.
@@ -10,14 +10,24 @@ use utils::{ByteOrder, ByteOrderReadExt};
 /// This structure wraps a `Read` and `Seek` implementation and allows one to read a TIFF
 /// document from it.
 pub struct TiffReader<R: Read + Seek> {
-    source: R
+    source: R,
+    options: LoadOptions
 }
 
 impl<R: Read + Seek> TiffReader<R> {
     /// Wraps the provider `Read + Seek` implementation and returns a new TIFF reader.
     pub fn new(source: R) -> TiffReader<R> {
+        TiffReader::with_options(source, &LoadOptions::default())
+    }
+
+    /// Wraps the provided `Read + Seek` implementation and returns a new TIFF reader which
+    /// uses the provided options.
+    ///
+    /// The allocation limit of the options applies to loading data of IFD entries.
+    pub fn with_options(source: R, options: &LoadOptions) -> TiffReader<R> {
         TiffReader {
-            source: source
+            source: source,
+            options: *options
         }
     }
 
@@ -29,8 +39,7 @@ impl<R: Read + Seek> TiffReader<R> {
     /// Note that the returned value does not implement `IntoIterator`, but an immutable
     /// reference to it does. Therefore, it should be used like this:
     ///
-    /// TODO: make it `no_run` instead after tiff module is made public again
-    /// ```ignore
+    /// ```no_run
     /// # use std::io::Cursor;
     /// # use immeta::common::tiff::TiffReader;
     /// # let r = TiffReader::new(Cursor::new(Vec::<u8>::new()));
@@ -65,16 +74,30 @@ impl<R: Read + Seek> TiffReader<R> {
             source: RefCell::new(self.source),
             byte_order: byte_order,
             next_ifd_offset: Cell::new(next_ifd_offset as u64),
+            options: self.options,
         })
     }
 }
 
+impl<'a> TiffReader<Cursor<&'a [u8]>> {
+    /// Returns a new TIFF reader over an in-memory buffer.
+    ///
+    /// The buffer is borrowed, not copied, and jumps to IFD offsets are simple index updates,
+    /// so this is the cheapest way to parse TIFF documents which are already in memory,
+    /// e.g. EXIF blocks embedded into other image formats. The returned reader provides
+    /// exactly the same API as readers over seekable streams.
+    pub fn from_buf(buf: &'a [u8]) -> TiffReader<Cursor<&'a [u8]>> {
+        TiffReader::new(Cursor::new(buf))
+    }
+}
+
 /// An intermediate structure, a reference to which can be converted to an iterator
 /// of IFDs.
 pub struct LazyIfds<R: Read + Seek> {
     source: RefCell<R>,
     byte_order: ByteOrder,
     next_ifd_offset: Cell<u64>,
+    options: LoadOptions,
 }
 
 impl<'a, R: Read + Seek> IntoIterator for &'a LazyIfds<R> {
@@ -109,40 +132,80 @@ impl<'a, R: Read + Seek> Ifds<'a, R> {
             return Ok(None);
         }
 
-        // seek to the beginning of the next IFD
+        let (ifd, next_ifd_offset) = try!(self.0.read_ifd_at(next_ifd_offset));
+
+        // update the next IFD offset for further calls to `next()`
+        self.0.next_ifd_offset.set(next_ifd_offset);
+
+        Ok(Some(ifd))
+    }
+}
+
+impl<R: Read + Seek> LazyIfds<R> {
+    #[inline]
+    pub(crate) fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Reads an IFD located at the given offset from the beginning of the document.
+    ///
+    /// This method can be used to read IFDs which are not a part of the main IFD chain,
+    /// e.g. sub-IFDs referenced by `SubIfds` or `ExifIfdPointer` entries. It does not
+    /// affect iteration over the main IFD chain.
+    #[inline]
+    pub fn ifd_at(&self, offset: u32) -> Result<Ifd<R>> {
+        self.read_ifd_at(offset as u64).map(|(ifd, _)| ifd)
+    }
+
+    /// Reads an IFD located at the given offset.
+    ///
+    /// Returns the IFD itself and the offset of the IFD following it. Errors returned by
+    /// this method carry the offset of the IFD.
+    pub(crate) fn read_ifd_at(&self, ifd_offset: u64) -> Result<(Ifd<R>, u64)> {
+        let (ifd_size, next_ifd_offset) = try!(
+            self.read_ifd_header(ifd_offset).map_err(|e| e.at_offset(ifd_offset))
+        );
+
+        Ok((Ifd {
+            ifds: self,
+            ifd_offset: ifd_offset,
+            next_ifd_offset: next_ifd_offset,
+            current_entry: 0,
+            total_entries: ifd_size,
+        }, next_ifd_offset))
+    }
+
+    /// Reads the number of entries in an IFD located at the given offset and the offset
+    /// of the IFD following it.
+    fn read_ifd_header(&self, ifd_offset: u64) -> Result<(u16, u64)> {
+        // seek to the beginning of the IFD
         try_if_eof!(std,
-            self.0.source.borrow_mut().seek(SeekFrom::Start(next_ifd_offset as u64)),
+            self.source.borrow_mut().seek(SeekFrom::Start(ifd_offset)),
             "when seeking to the beginning of the next IFD"
         );
-        let current_ifd_offset = next_ifd_offset;
 
         // read the length of this IFD
-        let current_ifd_size = try_if_eof!(
-            self.0.source.borrow_mut().read_u16(self.0.byte_order), "when reading number of entries in an IFD"
+        let ifd_size = try_if_eof!(
+            self.source.borrow_mut().read_u16(self.byte_order), "when reading number of entries in an IFD"
         );
         // it is an error for an IFD to be empty
-        if current_ifd_size == 0 {
+        if ifd_size == 0 {
             return Err(invalid_format!("number of entries in an IFD is zero"));
         }
 
         // compute the offset of the next IFD offset and seek to it
-        let next_ifd_offset_offset = current_ifd_offset + 2 + current_ifd_size as u64 * 12;
+        let next_ifd_offset_offset = ifd_offset + 2 + ifd_size as u64 * 12;
         try_if_eof!(std,
-            self.0.source.borrow_mut().seek(SeekFrom::Start(next_ifd_offset_offset as u64)),
+            self.source.borrow_mut().seek(SeekFrom::Start(next_ifd_offset_offset)),
             "when seeking to the next IFD offset"
         );
 
-        // read and update the next IFD offset for further calls to `next()`
-        self.0.next_ifd_offset.set(try_if_eof!(
-            self.0.source.borrow_mut().read_u32(self.0.byte_order), "when reading the next IFD offset"
-        ) as u64);
+        // read the next IFD offset
+        let next_ifd_offset = try_if_eof!(
+            self.source.borrow_mut().read_u32(self.byte_order), "when reading the next IFD offset"
+        );
 
-        Ok(Some(Ifd {
-            ifds: self.0,
-            ifd_offset: current_ifd_offset,
-            current_entry: 0,
-            total_entries: current_ifd_size,
-        }))
+        Ok((ifd_size, next_ifd_offset as u64))
     }
 }
 
@@ -152,6 +215,7 @@ impl<'a, R: Read + Seek> Ifds<'a, R> {
 pub struct Ifd<'a, R: Read + Seek + 'a> {
     ifds: &'a LazyIfds<R>,
     ifd_offset: u64,
+    next_ifd_offset: u64,
     current_entry: u16,
     total_entries: u16,
 }
@@ -174,11 +238,46 @@ impl<'a, R: Read + Seek + 'a> Ifd<'a, R> {
         self.total_entries
     }
 
+    /// Returns the offset of this IFD from the beginning of the document.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.ifd_offset
+    }
+
+    /// Returns the offset of the IFD following this one, or zero if this IFD is the last
+    /// one in its chain.
+    ///
+    /// Together with `sub_ifd()`, this allows following chains of sub-IFDs.
+    #[inline]
+    pub fn next_ifd_offset(&self) -> u64 {
+        self.next_ifd_offset
+    }
+
+    /// Reads a sub-IFD located at the given offset from the beginning of the document.
+    ///
+    /// The offset is usually read from an entry of this IFD, e.g. from `SubIfds` entry
+    /// or from `ExifIfdPointer` entry in EXIF data. Reading the sub-IFD does not affect
+    /// iteration over entries of this IFD.
+    #[inline]
+    pub fn sub_ifd(&self, offset: u32) -> Result<Ifd<'a, R>> {
+        self.ifds.ifd_at(offset)
+    }
+
     fn read_entry(&mut self) -> Result<Entry<'a, R>> {
+        // the next entry starts at ifd offset + 2 + next_entry * 12
+        let entry_offset = self.ifd_offset + 2 + self.current_entry as u64 * 12;
+        let entry = try!(self.read_entry_at(entry_offset).map_err(|e| e.at_offset(entry_offset)));
+
+        self.current_entry += 1;
+
+        Ok(entry)
+    }
+
+    fn read_entry_at(&self, entry_offset: u64) -> Result<Entry<'a, R>> {
         let mut source = self.ifds.source.borrow_mut();
 
-        // seek to the beginning of the next entry (ifd offset + 2 + next_entry * 12)
-        try!(source.seek(SeekFrom::Start(self.ifd_offset + 2 + self.current_entry as u64 * 12)));
+        // seek to the beginning of the entry
+        try!(source.seek(SeekFrom::Start(entry_offset)));
 
         // read the tag
         let tag = try_if_eof!(
@@ -200,15 +299,6 @@ impl<'a, R: Read + Seek + 'a> Ifd<'a, R> {
             source.read_u32(self.ifds.byte_order), "when reading TIFF IFD entry data offset"
         );
 
-        println!("---------------------------------");
-        println!("Entry tag:                   {:04X}, {}", tag, tag);
-        println!("Entry type:                  {:04X}, {}", entry_type, entry_type);
-        println!("Entry items count:       {:08X}, {}", count, count);
-        println!("Entry data offset/value: {:08X}, {}", offset, offset);
-        println!("---------------------------------");
-
-        self.current_entry += 1;
-
         Ok(Entry {
             ifds: self.ifds,
             tag: tag,
@@ -219,8 +309,173 @@ impl<'a, R: Read + Seek + 'a> Ifd<'a, R> {
     }
 }
 
+/// Designates a TIFF IFD entry tag.
+///
+/// This enum covers the most common baseline TIFF tags and EXIF tags. Tags which are not
+/// covered are represented by the `Unknown` variant.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Tag {
+    /// A general indication of the kind of data contained in this subfile.
+    NewSubfileType,
+    /// A general indication of the kind of data contained in this subfile (deprecated).
+    SubfileType,
+    /// The number of columns in the image.
+    ImageWidth,
+    /// The number of rows in the image.
+    ImageLength,
+    /// Number of bits per component.
+    BitsPerSample,
+    /// Compression scheme used on the image data.
+    Compression,
+    /// The color space of the image data.
+    PhotometricInterpretation,
+    /// A string that describes the subject of the image.
+    ImageDescription,
+    /// The manufacturer of the scanner, video digitizer or other equipment.
+    Make,
+    /// The model name or number of the equipment.
+    Model,
+    /// For each strip, the byte offset of that strip.
+    StripOffsets,
+    /// The orientation of the image with respect to the rows and columns.
+    Orientation,
+    /// The number of components per pixel.
+    SamplesPerPixel,
+    /// The number of rows per strip.
+    RowsPerStrip,
+    /// For each strip, the number of bytes in the strip after compression.
+    StripByteCounts,
+    /// The number of pixels per resolution unit in the image width direction.
+    XResolution,
+    /// The number of pixels per resolution unit in the image length direction.
+    YResolution,
+    /// How the components of each pixel are stored.
+    PlanarConfiguration,
+    /// The unit of measurement for X and Y resolutions.
+    ResolutionUnit,
+    /// Name and version number of the software package used to create the image.
+    Software,
+    /// Date and time of image creation.
+    DateTime,
+    /// Person who created the image.
+    Artist,
+    /// A color map for palette color images.
+    ColorMap,
+    /// The tile width in pixels.
+    TileWidth,
+    /// The tile length (height) in pixels.
+    TileLength,
+    /// For each tile, the byte offset of that tile.
+    TileOffsets,
+    /// For each tile, the number of (compressed) bytes in that tile.
+    TileByteCounts,
+    /// Offsets of child IFDs.
+    SubIfds,
+    /// Description of extra components.
+    ExtraSamples,
+    /// How to interpret each data sample in a pixel.
+    SampleFormat,
+    /// The offset to the start byte of JPEG compressed thumbnail data.
+    JpegInterchangeFormat,
+    /// The number of bytes of JPEG compressed thumbnail data.
+    JpegInterchangeFormatLength,
+    /// The sampling ratio of chrominance components in relation to the luminance component.
+    YCbCrSubSampling,
+    /// The position of chrominance components in relation to the luminance component.
+    YCbCrPositioning,
+    /// Copyright notice.
+    Copyright,
+    /// Exposure time, given in seconds.
+    ExposureTime,
+    /// The F number.
+    FNumber,
+    /// Offset of the EXIF IFD.
+    ExifIfdPointer,
+    /// Offset of the GPS IFD.
+    GpsIfdPointer,
+    /// The ISO speed of the camera or input device.
+    IsoSpeedRatings,
+    /// The version of the EXIF standard.
+    ExifVersion,
+    /// Date and time when the original image data was generated.
+    DateTimeOriginal,
+    /// Date and time when the image was stored as digital data.
+    DateTimeDigitized,
+    /// The actual focal length of the lens, in mm.
+    FocalLength,
+    /// The color space information tag.
+    ColorSpace,
+    /// Valid width of the meaningful image.
+    PixelXDimension,
+    /// Valid height of the meaningful image.
+    PixelYDimension,
+    /// Offset of the Interoperability IFD.
+    InteroperabilityIfdPointer,
+    /// A tag which is not known to this library.
+    Unknown(u16),
+}
+
+impl From<u16> for Tag {
+    fn from(n: u16) -> Tag {
+        match n {
+            254   => Tag::NewSubfileType,
+            255   => Tag::SubfileType,
+            256   => Tag::ImageWidth,
+            257   => Tag::ImageLength,
+            258   => Tag::BitsPerSample,
+            259   => Tag::Compression,
+            262   => Tag::PhotometricInterpretation,
+            270   => Tag::ImageDescription,
+            271   => Tag::Make,
+            272   => Tag::Model,
+            273   => Tag::StripOffsets,
+            274   => Tag::Orientation,
+            277   => Tag::SamplesPerPixel,
+            278   => Tag::RowsPerStrip,
+            279   => Tag::StripByteCounts,
+            282   => Tag::XResolution,
+            283   => Tag::YResolution,
+            284   => Tag::PlanarConfiguration,
+            296   => Tag::ResolutionUnit,
+            305   => Tag::Software,
+            306   => Tag::DateTime,
+            315   => Tag::Artist,
+            320   => Tag::ColorMap,
+            322   => Tag::TileWidth,
+            323   => Tag::TileLength,
+            324   => Tag::TileOffsets,
+            325   => Tag::TileByteCounts,
+            330   => Tag::SubIfds,
+            338   => Tag::ExtraSamples,
+            339   => Tag::SampleFormat,
+            513   => Tag::JpegInterchangeFormat,
+            514   => Tag::JpegInterchangeFormatLength,
+            530   => Tag::YCbCrSubSampling,
+            531   => Tag::YCbCrPositioning,
+            33432 => Tag::Copyright,
+            33434 => Tag::ExposureTime,
+            33437 => Tag::FNumber,
+            34665 => Tag::ExifIfdPointer,
+            34853 => Tag::GpsIfdPointer,
+            34855 => Tag::IsoSpeedRatings,
+            36864 => Tag::ExifVersion,
+            36867 => Tag::DateTimeOriginal,
+            36868 => Tag::DateTimeDigitized,
+            37386 => Tag::FocalLength,
+            40961 => Tag::ColorSpace,
+            40962 => Tag::PixelXDimension,
+            40963 => Tag::PixelYDimension,
+            40965 => Tag::InteroperabilityIfdPointer,
+            n     => Tag::Unknown(n),
+        }
+    }
+}
+
 /// Designates TIFF IFD entry type, as defined by TIFF spec.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EntryType {
     Byte,
     Ascii,
@@ -293,6 +548,15 @@ impl<'a, R: Read + Seek + 'a> Entry<'a, R> {
         self.tag
     }
 
+    /// Returns the tag of the entry as a `Tag` value.
+    ///
+    /// Tags which are not known to this library are returned as `Tag::Unknown`; use `tag()`
+    /// to get the numeric value in all cases.
+    #[inline]
+    pub fn tag_name(&self) -> Tag {
+        self.tag.into()
+    }
+
     /// Returns entry type.
     #[inline]
     pub fn entry_type(&self) -> EntryType {
@@ -305,6 +569,43 @@ impl<'a, R: Read + Seek + 'a> Entry<'a, R> {
         self.count
     }
 
+    /// Returns the entry data as raw bytes, in the byte order of the document.
+    ///
+    /// The data is returned verbatim, which is useful for entries of `Undefined` type,
+    /// e.g. vendor-specific maker notes. Returns an error if the entry type is unknown,
+    /// because the size of the data can't be determined in this case.
+    pub fn raw_bytes(&self) -> Result<Vec<u8>> {
+        let size = match self.entry_type.size() {
+            Some(entry_type_size) => entry_type_size as u64 * self.count as u64,
+            None => return Err(invalid_format!("cannot read data of TIFF IFD entry of unknown type: {:?}",
+                                               self.entry_type))
+        };
+
+        // if the total entry data size is smaller than 4 bytes (u32 value length)
+        // the the data is embedded into the offset u32
+        if size <= 4 {
+            let mut data = [0u8; 4];
+            self.ifds.byte_order.write_u32(&mut data, self.offset);
+            Ok(data[..size as usize].to_vec())
+        // othewise the data is stored at that offset
+        } else {
+            try!(self.ifds.options.check_alloc(size, "TIFF IFD entry data")
+                .map_err(|e| e.at_offset(self.offset as u64)));
+
+            let mut source = self.ifds.source.borrow_mut();
+            try_if_eof!(std,
+                source.seek(SeekFrom::Start(self.offset as u64)),
+                "when seeking to the beginning of IFD entry data"
+            );
+
+            let mut data = Vec::new();
+            if try!((&mut *source).take(size).read_to_end(&mut data)) as u64 != size {
+                return Err(unexpected_eof!("when reading TIFF IFD entry data").at_offset(self.offset as u64));
+            }
+            Ok(data)
+        }
+    }
+
     /// Returns an iterator for elements of the specified representation type.
     ///
     /// This method returns `None` if the requested representation type does not correspond
@@ -346,6 +647,24 @@ impl<'a, R: Read + Seek + 'a> Entry<'a, R> {
         }
     }
 
+    /// Returns a vector containing all of the items of a rational entry, converted to
+    /// floating point numbers.
+    ///
+    /// Both `Rational` and `SignedRational` entries are supported; for other entry types
+    /// this method returns `None`. Items with zero denominators are converted to NaN.
+    pub fn rational_values(&self) -> Option<Result<Vec<f64>>> {
+        let nan = ::std::f64::NAN;
+        match self.entry_type {
+            EntryType::Rational => self.all_values::<entry_types::Rational>().map(|values| values.map(|values|
+                values.into_iter().map(|v| entry_types::Rational::as_f64(v).unwrap_or(nan)).collect()
+            )),
+            EntryType::SignedRational => self.all_values::<entry_types::SignedRational>().map(|values| values.map(|values|
+                values.into_iter().map(|v| entry_types::SignedRational::as_f64(v).unwrap_or(nan)).collect()
+            )),
+            _ => None
+        }
+    }
+
     /// Returns a vector containing all of the items of this entry, loaded with the specified
     /// representation type.
     ///
@@ -365,6 +684,11 @@ impl<'a, R: Read + Seek + 'a> Entry<'a, R> {
                     Some(self.values::<T>().unwrap().collect())
                 // othewise the data is stored at that offset, load it all at once
                 } else {
+                    let size = entry_type_size as u64 * self.count as u64;
+                    if let Err(e) = self.ifds.options.check_alloc(size, "TIFF IFD entry data") {
+                        return Some(Err(e.at_offset(self.offset as u64)));
+                    }
+
                     match self.ifds.source.borrow_mut().seek(SeekFrom::Start(self.offset as u64))
                         .map_err(if_eof!(std, "when seeking to the beginning of IFD entry data"))
                     {
@@ -376,6 +700,7 @@ impl<'a, R: Read + Seek + 'a> Entry<'a, R> {
                     match T::read_many_from(&mut *self.ifds.source.borrow_mut(),
                                             self.ifds.byte_order, self.count, &mut result)
                         .map_err(if_eof!("when reading TIFF IFD entry values"))
+                        .map_err(|e| e.at_offset(self.offset as u64))
                     {
                         Ok(_) => Some(Ok(result)),
                         Err(e) => Some(Err(e))
@@ -557,6 +882,26 @@ pub mod entry_types {
             |source, byte_order| source.read_f64(byte_order).map(|v| (8, v)),
             |_, _, _, _| None
     }
+
+    impl Rational {
+        /// Converts a rational value to a floating point number.
+        ///
+        /// Returns `None` if the denominator is zero.
+        #[inline]
+        pub fn as_f64((num, den): (u32, u32)) -> Option<f64> {
+            if den == 0 { None } else { Some(num as f64 / den as f64) }
+        }
+    }
+
+    impl SignedRational {
+        /// Converts a signed rational value to a floating point number.
+        ///
+        /// Returns `None` if the denominator is zero.
+        #[inline]
+        pub fn as_f64((num, den): (i32, i32)) -> Option<f64> {
+            if den == 0 { None } else { Some(num as f64 / den as f64) }
+        }
+    }
 }
 
 /// An iterator over values in an TIFF IFD entry.
@@ -642,7 +987,7 @@ mod tests {
 
     use byteorder::{self, ByteOrder, BigEndian, LittleEndian};
 
-    use super::{TiffReader, EntryType, entry_types};
+    use super::{TiffReader, EntryType, Tag, entry_types};
 
     trait Writable {
         fn write_to<W: Write + ?Sized, T: ByteOrder>(&self, target: &mut W);
@@ -707,6 +1052,14 @@ mod tests {
         }}
     }
 
+    #[test]
+    fn test_rational_as_f64() {
+        assert_eq!(entry_types::Rational::as_f64((1, 4)), Some(0.25));
+        assert_eq!(entry_types::Rational::as_f64((1, 0)), None);
+        assert_eq!(entry_types::SignedRational::as_f64((-3, 2)), Some(-1.5));
+        assert_eq!(entry_types::SignedRational::as_f64((-3, 0)), None);
+    }
+
     #[test]
     fn test_big_endian_empty() {
         let data = build! { BigEndian,
@@ -719,6 +1072,26 @@ mod tests {
         assert!(ifds_iter.next().is_none());
     }
 
+    #[test]
+    fn test_from_buf() {
+        let data = build! { LittleEndian,
+            b"II", 42u16, 8u32,
+            1u16,
+            256u16, 3u16, 1u32, 640u16, 0u16,
+            0u32
+        };
+
+        let reader = TiffReader::from_buf(&data);
+        let ifds = reader.ifds().unwrap();
+        let mut ifds_iter = (&ifds).into_iter();
+        let mut ifd = ifds_iter.next().unwrap().unwrap();
+        let entry = ifd.next().unwrap().unwrap();
+        assert_eq!(entry.tag_name(), Tag::ImageWidth);
+        assert_eq!(entry.all_values::<entry_types::Short>().unwrap().unwrap(), vec![640]);
+        assert!(ifd.next().is_none());
+        assert!(ifds_iter.next().is_none());
+    }
+
     #[test]
     fn test_little_endian_empty() {
         let data = build! { LittleEndian,
@@ -818,6 +1191,7 @@ mod tests {
                 match i {
                     0 => {
                         assert_eq!(e.tag(), 4);
+                        assert_eq!(e.tag_name(), Tag::Unknown(4));
                         assert_eq!(e.entry_type(), EntryType::Byte);
                         assert_eq!(e.count(), 4);
                         assert_eq!(
@@ -880,7 +1254,11 @@ mod tests {
                         assert_items!(
                             e.values::<entry_types::Rational>().unwrap(),
                             (22, 7), (355, 113)
-                        )
+                        );
+                        assert_eq!(
+                            e.rational_values().unwrap().unwrap(),
+                            vec![22.0 / 7.0, 355.0 / 113.0]
+                        );
                     }
                     5 => {
                         assert_eq!(e.tag(), 42);
@@ -948,6 +1326,7 @@ mod tests {
                             e.values::<entry_types::SignedRational>().unwrap(),
                             (-333, -106)
                         );
+                        assert_eq!(e.rational_values().unwrap().unwrap(), vec![333.0 / 106.0]);
                     }
                     10 => {
                         assert_eq!(e.tag(), 23);
@@ -988,6 +1367,89 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sub_ifd() {
+        let data = build! { LittleEndian,
+            b"II", 42u16, 8u32,
+
+            // @8, IFD0 has a single SubIfds entry pointing to @26
+            1u16,
+            330u16, 4u16, 1u32, 26u32,
+            0u32,
+
+            // @26, sub-IFD has a single ImageWidth entry and is followed by another one @44
+            1u16,
+            256u16, 3u16, 1u32, 640u16, 0u16,
+            44u32,
+
+            // @44, the second sub-IFD in the chain
+            1u16,
+            257u16, 3u16, 1u32, 480u16, 0u16,
+            0u32
+        };
+
+        let reader = TiffReader::from_buf(&data);
+        let ifds = reader.ifds().unwrap();
+        let mut ifds_iter = (&ifds).into_iter();
+        let mut ifd = ifds_iter.next().unwrap().unwrap();
+        assert_eq!(ifd.offset(), 8);
+        assert_eq!(ifd.next_ifd_offset(), 0);
+
+        let entry = ifd.next().unwrap().unwrap();
+        assert_eq!(entry.tag_name(), Tag::SubIfds);
+        let offset = entry.values::<entry_types::Long>().unwrap().next().unwrap().unwrap();
+
+        let mut sub_ifd = ifd.sub_ifd(offset).unwrap();
+        assert_eq!(sub_ifd.offset(), 26);
+        assert_eq!(sub_ifd.next_ifd_offset(), 44);
+        let entry = sub_ifd.next().unwrap().unwrap();
+        assert_eq!(entry.tag_name(), Tag::ImageWidth);
+        assert_eq!(entry.all_values::<entry_types::Short>().unwrap().unwrap(), vec![640]);
+        assert!(sub_ifd.next().is_none());
+
+        let mut next_sub_ifd = ifds.ifd_at(sub_ifd.next_ifd_offset() as u32).unwrap();
+        let entry = next_sub_ifd.next().unwrap().unwrap();
+        assert_eq!(entry.tag_name(), Tag::ImageLength);
+
+        // reading sub-IFDs does not affect the main IFD chain
+        assert!(ifd.next().is_none());
+        assert!(ifds_iter.next().is_none());
+
+        assert!(ifd.sub_ifd(1000).is_err());
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        let data = build! { BigEndian,
+            b"MM", 42u16, 8u32,
+
+            // @8, IFD0 has an out-of-line maker note, an embedded undefined entry
+            // and an entry of unknown type
+            3u16,
+            37500u16, 7u16, 6u32, 50u32,
+            37510u16, 7u16, 3u32, 1u8, 2u8, 3u8, 0u8,
+            1000u16, 99u16, 1u32, 0u32,
+            0u32,
+
+            // @50, maker note data
+            b"Vendor"
+        };
+
+        let reader = TiffReader::from_buf(&data);
+        let ifds = reader.ifds().unwrap();
+        let mut ifd = (&ifds).into_iter().next().unwrap().unwrap();
+
+        let entry = ifd.next().unwrap().unwrap();
+        assert_eq!(entry.entry_type(), EntryType::Undefined);
+        assert_eq!(entry.raw_bytes().unwrap(), b"Vendor".to_vec());
+
+        let entry = ifd.next().unwrap().unwrap();
+        assert_eq!(entry.raw_bytes().unwrap(), vec![1, 2, 3]);
+
+        let entry = ifd.next().unwrap().unwrap();
+        assert!(entry.raw_bytes().is_err());
+    }
+
     //#[test]
     //fn test_two_ifds() {
         //let data = build! { LittleEndian,
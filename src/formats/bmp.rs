@@ -0,0 +1,157 @@
+//! Metadata of BMP images.
+
+use std::io::BufRead;
+use std::fmt;
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::BufReadExt;
+
+/// Compression method used in an image.
+///
+/// Only compression methods which are applicable to the supported DIB headers are
+/// represented here.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Compression {
+    /// No compression, raw RGB data.
+    Rgb,
+    /// Run-length encoding for 8 bits per pixel images.
+    Rle8,
+    /// Run-length encoding for 4 bits per pixel images.
+    Rle4,
+    /// No compression, pixel format is defined by color channel bit masks.
+    Bitfields
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Compression::Rgb => "RGB",
+            Compression::Rle8 => "RLE8",
+            Compression::Rle4 => "RLE4",
+            Compression::Bitfields => "Bitfields",
+        })
+    }
+}
+
+impl Compression {
+    fn from_u32(n: u32) -> Option<Compression> {
+        match n {
+            0 => Some(Compression::Rgb),
+            1 => Some(Compression::Rle8),
+            2 => Some(Compression::Rle4),
+            3 => Some(Compression::Bitfields),
+            _ => None
+        }
+    }
+}
+
+/// Version of the DIB header used in an image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HeaderVersion {
+    /// `BITMAPINFOHEADER`, 40 bytes.
+    Info,
+    /// `BITMAPV4HEADER`, 108 bytes.
+    V4,
+    /// `BITMAPV5HEADER`, 124 bytes.
+    V5
+}
+
+impl fmt::Display for HeaderVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            HeaderVersion::Info => "BITMAPINFOHEADER",
+            HeaderVersion::V4 => "BITMAPV4HEADER",
+            HeaderVersion::V5 => "BITMAPV5HEADER",
+        })
+    }
+}
+
+impl HeaderVersion {
+    fn from_size(n: u32) -> Option<HeaderVersion> {
+        match n {
+            40 => Some(HeaderVersion::Info),
+            108 => Some(HeaderVersion::V4),
+            124 => Some(HeaderVersion::V5),
+            _ => None
+        }
+    }
+}
+
+/// Represents metadata of a BMP image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Width and height.
+    pub dimensions: Dimensions,
+    /// Version of the DIB header present in the image.
+    pub header_version: HeaderVersion,
+    /// Number of bits per pixel.
+    pub bit_depth: u16,
+    /// Compression method used in the image.
+    pub compression: Compression,
+    /// Whether the pixel rows are stored from top to bottom.
+    ///
+    /// BMP images are usually stored bottom-up; top-down images are designated by
+    /// a negative height in the DIB header.
+    pub top_down: bool
+}
+
+impl LoadableMetadata for Metadata {
+    /// File header and the smallest (OS/2 core) info header.
+    #[inline]
+    fn min_header_len() -> usize {
+        26
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut signature = [0u8; 2];
+        try!(r.read_exact(&mut signature).map_err(if_eof!(std, "when reading BMP signature")));
+
+        if &signature != b"BM" {
+            return Err(invalid_format!("invalid BMP signature: {:?}", signature));
+        }
+
+        // file size (4 bytes), two reserved fields (2 bytes each) and pixel data offset (4 bytes)
+        if try!(r.skip_exact(12)) != 12 {
+            return Err(unexpected_eof!("when reading BMP file header"));
+        }
+
+        let header_size = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DIB header size");
+        let header_version = try!(
+            HeaderVersion::from_size(header_size)
+                .ok_or(invalid_format!("unsupported DIB header size: {}", header_size))
+        );
+
+        let width = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading BMP width");
+        let height = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading BMP height");
+        if width < 0 {
+            return Err(invalid_format!("invalid BMP width: {}", width));
+        }
+
+        let planes = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading number of color planes");
+        if planes != 1 {
+            return Err(invalid_format!("invalid number of color planes: {}", planes));
+        }
+
+        let bit_depth = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading bit depth");
+        let compression = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading compression method");
+
+        Ok(Metadata {
+            // negative height designates a top-down image
+            dimensions: try!(Dimensions::non_zero((width, (height as i64).abs()))),
+            header_version: header_version,
+            bit_depth: bit_depth,
+            compression: try!(
+                Compression::from_u32(compression)
+                    .ok_or(invalid_format!("invalid compression method: {}", compression))
+            ),
+            top_down: height < 0
+        })
+    }
+}
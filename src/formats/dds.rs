@@ -0,0 +1,189 @@
+//! Metadata of DDS (DirectDraw Surface) images.
+
+use std::io::BufRead;
+use std::fmt;
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::BufReadExt;
+
+const HEADER_SIZE: u32 = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Pixel format of a DDS image.
+///
+/// Compressed formats are identified by their FourCC codes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum PixelFormat {
+    /// Uncompressed RGB, luminance or alpha data; see `Metadata::bit_count`.
+    Uncompressed,
+    /// DXT1 (BC1) block compression.
+    Dxt1,
+    /// DXT2 block compression, i.e. DXT3 with premultiplied alpha.
+    Dxt2,
+    /// DXT3 (BC2) block compression.
+    Dxt3,
+    /// DXT4 block compression, i.e. DXT5 with premultiplied alpha.
+    Dxt4,
+    /// DXT5 (BC3) block compression.
+    Dxt5,
+    /// BC4 block compression (`ATI1` or `BC4U` FourCC).
+    Bc4,
+    /// BC5 block compression (`ATI2` or `BC5U` FourCC).
+    Bc5,
+    /// The format is defined by the DXGI format of the DX10 extended header; see
+    /// `Metadata::dxgi_format`.
+    Dx10,
+    /// Some other FourCC code.
+    Other([u8; 4])
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PixelFormat::Uncompressed => "Uncompressed",
+            PixelFormat::Dxt1 => "DXT1",
+            PixelFormat::Dxt2 => "DXT2",
+            PixelFormat::Dxt3 => "DXT3",
+            PixelFormat::Dxt4 => "DXT4",
+            PixelFormat::Dxt5 => "DXT5",
+            PixelFormat::Bc4 => "BC4",
+            PixelFormat::Bc5 => "BC5",
+            PixelFormat::Dx10 => "DX10",
+            PixelFormat::Other(ref fourcc) => return write!(f, "FourCC {:?}", String::from_utf8_lossy(fourcc)),
+        })
+    }
+}
+
+impl PixelFormat {
+    fn from_fourcc(fourcc: [u8; 4]) -> PixelFormat {
+        match &fourcc {
+            b"DXT1" => PixelFormat::Dxt1,
+            b"DXT2" => PixelFormat::Dxt2,
+            b"DXT3" => PixelFormat::Dxt3,
+            b"DXT4" => PixelFormat::Dxt4,
+            b"DXT5" => PixelFormat::Dxt5,
+            b"ATI1" | b"BC4U" => PixelFormat::Bc4,
+            b"ATI2" | b"BC5U" => PixelFormat::Bc5,
+            b"DX10" => PixelFormat::Dx10,
+            _ => PixelFormat::Other(fourcc)
+        }
+    }
+}
+
+/// Represents metadata of a DDS image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Dimensions of the main image, i.e. of the largest mipmap level.
+    pub dimensions: Dimensions,
+    /// Number of mipmap levels, including the main image.
+    pub mipmap_count: u32,
+    /// Pixel format of the image.
+    pub format: PixelFormat,
+    /// Number of bits per pixel, if the image is uncompressed.
+    pub bit_count: Option<u32>,
+    /// DXGI format from the DX10 extended header, if it is present.
+    ///
+    /// This is a raw value of the `DXGI_FORMAT` enumeration, e.g. 98 for `DXGI_FORMAT_BC7_UNORM`.
+    pub dxgi_format: Option<u32>,
+    /// Number of elements in a texture array, as stored in the DX10 extended header.
+    ///
+    /// It is 1 for images without the DX10 header. For cube maps, this is the number of cubes.
+    pub array_size: u32,
+    /// Whether the image is a cube map.
+    pub is_cubemap: bool
+}
+
+impl LoadableMetadata for Metadata {
+    /// Magic number and the header.
+    #[inline]
+    fn min_header_len() -> usize {
+        128
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut magic = [0u8; 4];
+        try_if_eof!(std, r.read_exact(&mut magic), "when reading DDS magic number");
+        if &magic != b"DDS " {
+            return Err(invalid_format!("invalid DDS magic number: {:?}", magic));
+        }
+
+        let header_size = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS header size");
+        if header_size != HEADER_SIZE {
+            return Err(invalid_format!("invalid DDS header size: {}", header_size));
+        }
+
+        let flags = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS header flags");
+        let height = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS image height");
+        let width = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS image width");
+
+        // pitch or linear size (4 bytes) and depth (4 bytes)
+        if try!(r.skip_exact(8)) != 8 {
+            return Err(unexpected_eof!("when reading DDS header"));
+        }
+        let mipmap_count = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS mipmap count");
+        // reserved fields
+        if try!(r.skip_exact(11 * 4)) != 11 * 4 {
+            return Err(unexpected_eof!("when reading DDS header"));
+        }
+
+        let pf_size = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS pixel format size");
+        if pf_size != PIXEL_FORMAT_SIZE {
+            return Err(invalid_format!("invalid DDS pixel format size: {}", pf_size));
+        }
+        let pf_flags = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS pixel format flags");
+        let mut fourcc = [0u8; 4];
+        try_if_eof!(std, r.read_exact(&mut fourcc), "when reading DDS pixel format FourCC");
+        let bit_count = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS pixel format bit count");
+        // color component masks
+        if try!(r.skip_exact(4 * 4)) != 4 * 4 {
+            return Err(unexpected_eof!("when reading DDS pixel format"));
+        }
+
+        let _caps = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS caps");
+        let caps2 = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DDS caps");
+        // caps3, caps4 and a reserved field
+        if try!(r.skip_exact(3 * 4)) != 3 * 4 {
+            return Err(unexpected_eof!("when reading DDS header"));
+        }
+
+        let format = if pf_flags & DDPF_FOURCC != 0 {
+            PixelFormat::from_fourcc(fourcc)
+        } else {
+            PixelFormat::Uncompressed
+        };
+
+        let mut is_cubemap = caps2 & DDSCAPS2_CUBEMAP != 0;
+        let (dxgi_format, array_size) = if format == PixelFormat::Dx10 {
+            let dxgi_format = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DXGI format");
+            let _resource_dimension = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DX10 resource dimension");
+            let misc_flag = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DX10 flags");
+            let array_size = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading DX10 array size");
+            is_cubemap = is_cubemap || misc_flag & D3D10_RESOURCE_MISC_TEXTURECUBE != 0;
+            (Some(dxgi_format), array_size)
+        } else {
+            (None, 1)
+        };
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width, height))),
+            // the mipmap count is only valid if the corresponding flag is set
+            mipmap_count: if flags & DDSD_MIPMAPCOUNT != 0 && mipmap_count > 0 { mipmap_count } else { 1 },
+            format: format,
+            bit_count: if format == PixelFormat::Uncompressed { Some(bit_count) } else { None },
+            dxgi_format: dxgi_format,
+            array_size: array_size,
+            is_cubemap: is_cubemap
+        })
+    }
+}
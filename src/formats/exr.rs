@@ -0,0 +1,280 @@
+//! Metadata of OpenEXR images.
+
+use std::io::{Read, BufRead};
+use std::fmt;
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::BufReadExt;
+
+/// Compression method used in an image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// Run-length encoding.
+    Rle,
+    /// Zlib compression, one scan line at a time.
+    Zips,
+    /// Zlib compression, in blocks of 16 scan lines.
+    Zip,
+    /// Piz-based wavelet compression.
+    Piz,
+    /// Lossy 24-bit float compression.
+    Pxr24,
+    /// Lossy 4-by-4 pixel block compression.
+    B44,
+    /// Lossy 4-by-4 pixel block compression with flat fields compressed more.
+    B44a,
+    /// Lossy DCT-based compression, in blocks of 32 scan lines.
+    Dwaa,
+    /// Lossy DCT-based compression, in blocks of 256 scan lines.
+    Dwab
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Compression::None => "None",
+            Compression::Rle => "RLE",
+            Compression::Zips => "ZIPS",
+            Compression::Zip => "ZIP",
+            Compression::Piz => "PIZ",
+            Compression::Pxr24 => "PXR24",
+            Compression::B44 => "B44",
+            Compression::B44a => "B44A",
+            Compression::Dwaa => "DWAA",
+            Compression::Dwab => "DWAB",
+        })
+    }
+}
+
+impl Compression {
+    fn from_u8(n: u8) -> Option<Compression> {
+        match n {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Rle),
+            2 => Some(Compression::Zips),
+            3 => Some(Compression::Zip),
+            4 => Some(Compression::Piz),
+            5 => Some(Compression::Pxr24),
+            6 => Some(Compression::B44),
+            7 => Some(Compression::B44a),
+            8 => Some(Compression::Dwaa),
+            9 => Some(Compression::Dwab),
+            _ => None
+        }
+    }
+}
+
+/// Data type of channel samples.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PixelType {
+    /// 32-bit unsigned integer.
+    Uint,
+    /// 16-bit floating point number.
+    Half,
+    /// 32-bit floating point number.
+    Float
+}
+
+impl fmt::Display for PixelType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PixelType::Uint => "UINT",
+            PixelType::Half => "HALF",
+            PixelType::Float => "FLOAT",
+        })
+    }
+}
+
+impl PixelType {
+    /// Returns the size of a sample of this type in bits.
+    pub fn bits(&self) -> u8 {
+        match *self {
+            PixelType::Half => 16,
+            PixelType::Uint | PixelType::Float => 32
+        }
+    }
+
+    fn from_i32(n: i32) -> Option<PixelType> {
+        match n {
+            0 => Some(PixelType::Uint),
+            1 => Some(PixelType::Half),
+            2 => Some(PixelType::Float),
+            _ => None
+        }
+    }
+}
+
+/// Describes a single channel of an image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Channel {
+    /// Channel name, e.g. `R` or `diffuse.G`.
+    pub name: String,
+    /// Data type of the channel samples.
+    pub pixel_type: PixelType,
+    /// Whether the channel samples are perceptually linear, as a hint for lossy compression.
+    pub linear: bool,
+    /// Horizontal and vertical subsampling factors.
+    pub sampling: (i32, i32)
+}
+
+/// Represents metadata of an OpenEXR image.
+///
+/// Only the header of the first part is read for multi-part images.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions, as defined by the data window.
+    pub dimensions: Dimensions,
+    /// File format version number.
+    pub version: u8,
+    /// Whether the image is tiled rather than stored in scan lines.
+    pub tiled: bool,
+    /// Whether the image contains deep data.
+    pub deep: bool,
+    /// Whether the image consists of several parts.
+    pub multipart: bool,
+    /// Image channels, in the order they are stored in the header.
+    pub channels: Vec<Channel>,
+    /// Compression method used in the image.
+    pub compression: Compression
+}
+
+const MAGIC: u32 = 20000630;
+
+const FLAG_TILED: u32 = 0x200;
+const FLAG_DEEP: u32 = 0x800;
+const FLAG_MULTIPART: u32 = 0x1000;
+
+/// Reads a null-terminated string, which may be empty.
+fn read_string<R: ?Sized + Read>(r: &mut R, what: &str) -> Result<String> {
+    let mut s = Vec::new();
+    loop {
+        match try_if_eof!(r.read_u8(), "when reading {}", what) {
+            0 => return Ok(String::from_utf8_lossy(&s).into_owned()),
+            b => s.push(b)
+        }
+    }
+}
+
+fn read_channels<R: ?Sized + Read>(r: &mut R) -> Result<Vec<Channel>> {
+    let mut channels = Vec::new();
+    loop {
+        let name = try!(read_string(r, "channel name"));
+        // channel list is terminated by an empty name
+        if name.is_empty() {
+            return Ok(channels);
+        }
+
+        let pixel_type = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading pixel type of channel {}", name);
+        let linear = try_if_eof!(r.read_u8(), "when reading linearity of channel {}", name);
+        let mut reserved = [0u8; 3];
+        try_if_eof!(std, r.read_exact(&mut reserved), "when reading channel {}", name);
+        let x_sampling = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading sampling of channel {}", name);
+        let y_sampling = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading sampling of channel {}", name);
+
+        channels.push(Channel {
+            pixel_type: try!(
+                PixelType::from_i32(pixel_type)
+                    .ok_or(invalid_format!("invalid pixel type of channel {}: {}", name, pixel_type))
+            ),
+            name: name,
+            linear: linear != 0,
+            sampling: (x_sampling, y_sampling)
+        });
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// Magic number, version and the smallest required attributes with a single channel.
+    #[inline]
+    fn min_header_len() -> usize {
+        114
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let magic = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading OpenEXR magic number");
+        if magic != MAGIC {
+            return Err(invalid_format!("invalid OpenEXR magic number: {:#x}", magic));
+        }
+
+        let version = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading OpenEXR version");
+
+        let mut channels = None;
+        let mut compression = None;
+        let mut data_window = None;
+
+        // header consists of attributes and is terminated by an empty attribute name
+        loop {
+            let name = try!(read_string(r, "attribute name"));
+            if name.is_empty() {
+                break;
+            }
+            let attr_type = try!(read_string(r, "attribute type"));
+            let size = try_if_eof!(r.read_i32::<LittleEndian>(), "when reading size of attribute {}", name);
+            if size < 0 {
+                return Err(invalid_format!("invalid size of attribute {}: {}", name, size));
+            }
+            let size = size as u64;
+
+            // attribute values are parsed from a limited reader, so a parser can't read
+            // beyond the attribute value
+            let mut value = (&mut *r).take(size);
+            match (&*name, &*attr_type) {
+                ("channels", "chlist") => channels = Some(try!(read_channels(&mut value))),
+                ("compression", "compression") => {
+                    let n = try_if_eof!(value.read_u8(), "when reading compression method");
+                    compression = Some(try!(
+                        Compression::from_u8(n).ok_or(invalid_format!("invalid compression method: {}", n))
+                    ));
+                }
+                ("dataWindow", "box2i") => {
+                    let mut window = [0i32; 4];
+                    for w in &mut window {
+                        *w = try_if_eof!(value.read_i32::<LittleEndian>(), "when reading data window");
+                    }
+                    data_window = Some(window);
+                }
+                _ => {}
+            }
+
+            // skip the rest of the attribute value
+            let remaining = value.limit();
+            if try!(value.skip_exact(remaining)) != remaining {
+                return Err(unexpected_eof!("when reading value of attribute {}", name));
+            }
+        }
+
+        let [x_min, y_min, x_max, y_max] = try!(
+            data_window.ok_or(invalid_format!("dataWindow attribute is missing"))
+        );
+        if x_max < x_min || y_max < y_min {
+            return Err(invalid_format!("invalid data window: ({}, {}) - ({}, {})", x_min, y_min, x_max, y_max));
+        }
+
+        // the window may span the whole range of i32, which does not fit into u32
+        let width = x_max as i64 - x_min as i64 + 1;
+        let height = y_max as i64 - y_min as i64 + 1;
+        if width > u32::MAX as i64 || height > u32::MAX as i64 {
+            return Err(invalid_format!("data window is too large: ({}, {}) - ({}, {})", x_min, y_min, x_max, y_max));
+        }
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width as u32, height as u32))),
+            version: (version & 0xff) as u8,
+            tiled: version & FLAG_TILED != 0,
+            deep: version & FLAG_DEEP != 0,
+            multipart: version & FLAG_MULTIPART != 0,
+            channels: try!(channels.ok_or(invalid_format!("channels attribute is missing"))),
+            compression: try!(compression.ok_or(invalid_format!("compression attribute is missing")))
+        })
+    }
+}
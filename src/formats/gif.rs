@@ -1,22 +1,34 @@
 //! Metadata of GIF images.
 
-use std::io::BufRead;
+use std::io::{Read, BufRead, Seek};
 use std::borrow::Cow;
 use std::str;
+use std::fmt;
+use std::cmp;
 
 use byteorder::{ReadBytesExt, LittleEndian};
 
-use types::{Result, Dimensions};
+use types::{Result, Error, Dimensions, LoadOptions};
 use traits::LoadableMetadata;
-use utils::BufReadExt;
+use utils::{Skip, Streaming, Seekable};
 
 /// GIF file version number.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Version {
     V87a,
     V89a
 }
 
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Version::V87a => "87a",
+            Version::V89a => "89a",
+        })
+    }
+}
+
 impl Version {
     fn from_bytes(b: &[u8]) -> Option<Version> {
         match b {
@@ -28,7 +40,9 @@ impl Version {
 }
 
 /// Represents various kinds of blocks which can be used in a GIF image.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum Block {
     /// An image descriptor (image contents for one frame).
     ImageDescriptor(ImageDescriptor),
@@ -43,20 +57,34 @@ pub enum Block {
     CommentExtension(CommentExtension)
 }
 
-fn skip_blocks<R: ?Sized + BufRead, F>(r: &mut R, on_eof: F) -> Result<()>
+fn skip_blocks<R: ?Sized + Skip, F>(r: &mut R, on_eof: F) -> Result<()>
     where F: Fn() -> Cow<'static, str>
 {
     loop {
         let n = try_if_eof!(r.read_u8(), on_eof()) as u64;
         if n == 0 { return Ok(()); }
-        if try!(r.skip_exact(n)) != n {
+        if try!(r.skip(n)) != n {
+            return Err(unexpected_eof!(on_eof()));
+        }
+    }
+}
+
+fn read_blocks<R: ?Sized + BufRead, F>(r: &mut R, on_eof: F) -> Result<Vec<u8>>
+    where F: Fn() -> Cow<'static, str>
+{
+    let mut data = Vec::new();
+    loop {
+        let n = try_if_eof!(r.read_u8(), on_eof()) as u64;
+        if n == 0 { return Ok(data); }
+        if try!((&mut *r).take(n).read_to_end(&mut data)) as u64 != n {
             return Err(unexpected_eof!(on_eof()));
         }
     }
 }
 
 /// Contains information about a color table (global or local).
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ColorTable {
     /// Color table size, between 2 and 256.
     pub size: u16,
@@ -68,10 +96,33 @@ pub struct ColorTable {
     /// the best subset of colors; the decoder may use an initial segment of the 
     /// table to render the graphic.
     pub sorted: bool,
+    /// RGB colors of the color table.
+    ///
+    /// The colors are only loaded if `LoadOptions::load_color_tables` is set; otherwise,
+    /// this field is `None`.
+    pub colors: Option<Vec<[u8; 3]>>,
+}
+
+fn read_color_table<R: ?Sized + Skip, F>(r: &mut R, size: u16, load_colors: bool, on_eof: F)
+    -> Result<Option<Vec<[u8; 3]>>>
+    where F: Fn() -> Cow<'static, str>
+{
+    let byte_size = size as u64 * 3;
+    if load_colors {
+        let mut data = vec![0u8; byte_size as usize];
+        try_if_eof!(std, r.read_exact(&mut data), on_eof());
+        Ok(Some(data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()))
+    } else {
+        if try!(r.skip(byte_size)) != byte_size {
+            return Err(unexpected_eof!(on_eof()));
+        }
+        Ok(None)
+    }
 }
 
 /// Contains metadata about an image block, i.e. a single frame of a GIF image.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImageDescriptor {
     /// Offset of the image data from the left boundary of the logical screen.
     pub left: u16,
@@ -90,7 +141,16 @@ pub struct ImageDescriptor {
 }
 
 impl ImageDescriptor {
-    fn load<R: ?Sized + BufRead>(index: usize, r: &mut R) -> Result<ImageDescriptor> {
+    /// Returns `true` if the image extends beyond the given canvas, usually the logical screen.
+    ///
+    /// Such images are a sign of corruption or of an unusual encoder, and they must be clipped
+    /// when rendered.
+    pub fn exceeds_canvas(&self, canvas: Dimensions) -> bool {
+        self.left as u32 + self.width as u32 > canvas.width ||
+            self.top as u32 + self.height as u32 > canvas.height
+    }
+
+    fn load<R: ?Sized + Skip>(index: usize, r: &mut R, load_colors: bool) -> Result<ImageDescriptor> {
         let left = try_if_eof!(
             r.read_u16::<LittleEndian>(), 
             "when reading left offset of image block {}", index
@@ -120,12 +180,12 @@ impl ImageDescriptor {
             0
         };
 
-        if local_color_table {
-            let skip_size = local_color_table_size as u64 * 3;
-            if try!(r.skip_exact(skip_size)) != skip_size {
-                return Err(unexpected_eof!("when reading color table of image block {}", index));
-            }
-        }
+        let local_color_table_colors = if local_color_table {
+            try!(read_color_table(r, local_color_table_size, load_colors,
+                                  || format!("when reading color table of image block {}", index).into()))
+        } else {
+            None
+        };
 
         let _ = try_if_eof!(r.read_u8(), "when reading LZW minimum code size of image block {}", index);
         try!(skip_blocks(r, || format!("when reading image data of image block {}", index).into()));
@@ -139,7 +199,8 @@ impl ImageDescriptor {
             local_color_table: if local_color_table {
                 Some(ColorTable {
                     size: local_color_table_size,
-                    sorted: local_color_table_sorted
+                    sorted: local_color_table_sorted,
+                    colors: local_color_table_colors
                 })
             } else { None },
 
@@ -153,7 +214,8 @@ impl ImageDescriptor {
 /// This block usually leads an image descriptor block and contains information on how this
 /// image should be displayed. It is especially important for animated GIF images because
 /// it contains delay and disposal method flags.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GraphicControlExtension {
     /// Indicates how the graphic should be treated after it is displayed.
     ///
@@ -187,7 +249,7 @@ impl GraphicControlExtension {
         self.delay_time as u32 * 10
     }
 
-    fn load<R: ?Sized + BufRead>(index: usize, r: &mut R) -> Result<GraphicControlExtension> {
+    fn load<R: ?Sized + Skip>(index: usize, r: &mut R) -> Result<GraphicControlExtension> {
         const NAME: &'static str = "graphics control extension block";
 
         let block_size = try_if_eof!(r.read_u8(), "when reading block size of {} {}", NAME, index);
@@ -233,7 +295,8 @@ impl GraphicControlExtension {
 ///
 /// Disposal method defines how the graphic should be treated after being displayed. Descriptions
 /// of enum variants come from GIF spec.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DisposalMethod {
     /// The decoder is not required to take any action.
     None,
@@ -266,7 +329,8 @@ impl DisposalMethod {
 /// Plain text blocks can be used to render texts represented as an actual textual data as
 /// opposed to pre-rendered rasterized text. However, it seems that these blocks are not
 /// well supported by the existing software.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlainTextExtension {
     /// Column number, in pixels, of the left edge of the text grid, with respect to 
     /// the left edge of the logical screen.
@@ -290,7 +354,7 @@ pub struct PlainTextExtension {
 }
 
 impl PlainTextExtension {
-    fn load<R: ?Sized + BufRead>(index: usize, r: &mut R) -> Result<PlainTextExtension> {
+    fn load<R: ?Sized + Skip>(index: usize, r: &mut R) -> Result<PlainTextExtension> {
         const NAME: &'static str = "plain text extension block";
 
         let block_size = try_if_eof!(r.read_u8(), "when reading block size of {} {}", NAME, index);
@@ -350,11 +414,38 @@ impl PlainTextExtension {
     }
 }
 
+/// Well-known kinds of application extension blocks.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ApplicationKind {
+    /// Netscape 2.0 extension, which contains animation loop count.
+    Netscape,
+    /// AnimExts 1.0 extension, an alternative to Netscape 2.0 extension with the same contents.
+    AnimExts,
+    /// XMP data extension, which contains an XMP packet.
+    XmpData,
+    /// Any other application extension.
+    Unknown
+}
+
+impl fmt::Display for ApplicationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ApplicationKind::Netscape => "Netscape 2.0",
+            ApplicationKind::AnimExts => "AnimExts 1.0",
+            ApplicationKind::XmpData => "XMP data",
+            ApplicationKind::Unknown => "unknown",
+        })
+    }
+}
+
 /// Contains metadata for application extension block.
 ///
 /// These blocks usually contain information about the application which was used to create
 /// the image.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ApplicationExtension {
     /// Eight ASCII bytes of an application identifier.
     pub application_identifier: [u8; 8],
@@ -365,7 +456,12 @@ pub struct ApplicationExtension {
     /// > Sequence of three bytes used to authenticate the Application Identifier. 
     /// An Application program may use an algorithm to compute a binary code that uniquely
     /// identifies it as the application owning the Application Extension.
-    pub authentication_code: [u8; 3]
+    pub authentication_code: [u8; 3],
+    /// Application data, with all data sub-blocks concatenated.
+    ///
+    /// The format of this data is specific to the application, e.g. Netscape 2.0 extension
+    /// contains animation loop count, and XMP data extension contains an XMP packet.
+    pub data: Vec<u8>
 }
 
 impl ApplicationExtension {
@@ -381,6 +477,32 @@ impl ApplicationExtension {
         str::from_utf8(&self.authentication_code).ok()
     }
 
+    /// Returns the kind of this application extension.
+    ///
+    /// Both application identifier and authentication code are taken into account, so e.g.
+    /// a `NETSCAPE` block with an authentication code other than `2.0` is considered unknown.
+    pub fn kind(&self) -> ApplicationKind {
+        match (&self.application_identifier, &self.authentication_code) {
+            (b"NETSCAPE", b"2.0") => ApplicationKind::Netscape,
+            (b"ANIMEXTS", b"1.0") => ApplicationKind::AnimExts,
+            (b"XMP Data", b"XMP") => ApplicationKind::XmpData,
+            _ => ApplicationKind::Unknown
+        }
+    }
+
+    /// Returns `true` if this is a Netscape 2.0 extension block.
+    #[inline]
+    pub fn is_netscape(&self) -> bool {
+        self.kind() == ApplicationKind::Netscape
+    }
+
+    fn has_loop_count(&self) -> bool {
+        match self.kind() {
+            ApplicationKind::Netscape | ApplicationKind::AnimExts => true,
+            _ => false
+        }
+    }
+
     fn load<R: ?Sized + BufRead>(index: usize, r: &mut R) -> Result<ApplicationExtension> {
         const NAME: &'static str = "application extension block";
 
@@ -397,38 +519,72 @@ impl ApplicationExtension {
         try!(r.read_exact(&mut authentication_code)
              .map_err(if_eof!(std, "while reading authentication code in {} {}", NAME, index)));
 
-        try!(skip_blocks(r, || format!("when reading application data of {} {}", NAME, index).into()));
+        let data = try!(read_blocks(r, || format!("when reading application data of {} {}", NAME, index).into()));
 
         Ok(ApplicationExtension {
             application_identifier: application_identifier,
-            authentication_code: authentication_code
+            authentication_code: authentication_code,
+            data: data
         })
     }
 }
 
 /// Represents a comment extension block.
-///
-/// Comment block does not contain any metadata, so this struct is used for uniformity
-/// as a placeholder in the enum.
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct CommentExtension;
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommentExtension {
+    /// Comment text, with all data sub-blocks concatenated.
+    ///
+    /// GIF spec requires comments to be 7-bit ASCII, but other encodings are used in
+    /// the wild, so the text is decoded as UTF-8, with invalid sequences replaced by
+    /// U+FFFD REPLACEMENT CHARACTER.
+    pub text: String
+}
 
 impl CommentExtension {
     fn load<R: ?Sized + BufRead>(index: usize, r: &mut R) -> Result<CommentExtension> {
         const NAME: &'static str = "comments extension block";
-        try!(skip_blocks(r, || format!("when reading comment data of {} {}", NAME, index).into()));
+        let data = try!(read_blocks(r, || format!("when reading comment data of {} {}", NAME, index).into()));
 
-        Ok(CommentExtension)
+        Ok(CommentExtension {
+            text: String::from_utf8_lossy(&data).into_owned()
+        })
     }
 }
 
+/// Contains information about a single frame of a GIF image.
+///
+/// Values of this type are obtained via `Metadata::frames()` method, which combines
+/// an image descriptor block with the graphic control extension block preceding it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FrameInfo {
+    /// Frame dimensions.
+    pub dimensions: Dimensions,
+    /// Left and top position of the frame on the logical screen.
+    pub offset: (u16, u16),
+    /// Delay time of the frame in milliseconds, or zero if it is not specified.
+    pub delay_ms: u32,
+    /// Disposal method of the frame; `DisposalMethod::None` if it is not specified.
+    pub disposal_method: DisposalMethod,
+    /// Transparent color index of the frame, if it is available.
+    pub transparent_color_index: Option<u8>,
+    /// Whether the frame is interlaced.
+    pub interlace: bool
+}
+
 /// Contains metadata about the whole GIF image.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Metadata {
     /// GIF format version from the file header.
     pub version: Version,
 
     /// Logical screen dimensions of the image.
+    ///
+    /// This is the size of the canvas on which frames are drawn, which is not necessarily
+    /// equal to the size of any frame, even for single-frame images. See also
+    /// `canvas_dimensions()` and `first_frame_dimensions()` methods.
     pub dimensions: Dimensions,
 
     /// Information about global color table, if it is present.
@@ -470,7 +626,14 @@ pub struct Metadata {
     pub pixel_aspect_ratio: u8,
 
     /// Metadata for each block in the GIF image.
-    pub blocks: Vec<Block>
+    pub blocks: Vec<Block>,
+
+    /// Whether loading of blocks was stopped because of `LoadOptions::max_gif_frames`.
+    ///
+    /// If `true`, the image contains more frames than there are image descriptors in
+    /// `blocks`, and everything computed from blocks, like `frames_number()` or
+    /// `total_duration_ms()`, only covers the loaded frames.
+    pub has_more_frames: bool
 }
 
 impl Metadata {
@@ -486,7 +649,42 @@ impl Metadata {
         }
     }
 
+    /// Returns dimensions of the logical screen as it should be displayed, taking pixel aspect
+    /// ratio into account.
+    ///
+    /// See `Dimensions::scaled_by_pixel_aspect()`. If pixel aspect ratio is not available,
+    /// pixels are assumed to be square and the result is equal to `dimensions`.
+    pub fn display_dimensions(&self) -> Dimensions {
+        match self.pixel_aspect_ratio_approx() {
+            Some(par) => self.dimensions.scaled_by_pixel_aspect(par),
+            None => self.dimensions
+        }
+    }
+
+    /// Returns dimensions of the logical screen, i.e. the `dimensions` field.
+    ///
+    /// Frames are drawn on the logical screen at their offsets, so they may be smaller than
+    /// the logical screen; use `first_frame_dimensions()` or `frames()` to obtain their sizes.
+    #[inline]
+    pub fn canvas_dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// Returns dimensions of the first frame, i.e. of the first image descriptor block,
+    /// if there is one.
+    pub fn first_frame_dimensions(&self) -> Option<Dimensions> {
+        self.blocks.iter()
+            .filter_map(|b| match *b {
+                Block::ImageDescriptor(ref id) => Some((id.width, id.height).into()),
+                _ => None
+            })
+            .next()
+    }
+
     /// Computes the number of frames, i.e. the number of image descriptor blocks.
+    ///
+    /// Zero is returned if the metadata was loaded with `LoadOptions::gif_header_only`,
+    /// because no blocks are loaded then.
     #[inline]
     pub fn frames_number(&self) -> usize {
         self.blocks.iter().filter(|b| match **b {
@@ -495,19 +693,186 @@ impl Metadata {
         }).count()
     }
 
+    /// Returns the number of times the animation should be looped, if it is available.
+    ///
+    /// The value is taken from the Netscape 2.0 (or AnimExts 1.0) application extension block.
+    /// Zero means that the animation should be looped infinitely. If there is no such block,
+    /// `None` is returned; usually it means that the animation should be played only once.
+    pub fn loop_count(&self) -> Option<u16> {
+        self.blocks.iter()
+            .filter_map(|b| match *b {
+                Block::ApplicationExtension(ref ext) if ext.has_loop_count() => Some(ext),
+                _ => None
+            })
+            .filter_map(|ext| match ext.data.get(..3) {
+                // first data sub-block has id 1 and contains little-endian loop count
+                Some(&[1, lo, hi]) => Some(lo as u16 | (hi as u16) << 8),
+                _ => None
+            })
+            .next()
+    }
+
+    /// Computes the total duration of the animation in milliseconds.
+    ///
+    /// This is the sum of delay times of all graphic control extension blocks which precede
+    /// an image descriptor block; graphic control extensions which are not followed by
+    /// an image are ignored.
+    ///
+    /// Zero delay time of a frame is counted as zero here. Note, however, that many players
+    /// substitute some minimal delay (e.g. 100 ms) for zero or very small delays, so the
+    /// actual playback time may be longer.
+    pub fn total_duration_ms(&self) -> u32 {
+        let mut total = 0;
+        let mut pending_delay = None;
+        for block in &self.blocks {
+            match *block {
+                Block::GraphicControlExtension(ref gce) => pending_delay = Some(gce.delay_time_ms()),
+                Block::ImageDescriptor(_) => total += pending_delay.take().unwrap_or(0),
+                _ => {}
+            }
+        }
+        total
+    }
+
+    /// Returns information about each frame of the image, in order.
+    ///
+    /// Each image descriptor block is paired with the graphic control extension block
+    /// which precedes it, if any. As in `total_duration_ms()`, a graphic control extension
+    /// only applies to the image immediately following it.
+    pub fn frames(&self) -> Vec<FrameInfo> {
+        self.frame_blocks().into_iter()
+            .map(|(gce, id)| FrameInfo {
+                dimensions: (id.width, id.height).into(),
+                offset: (id.left, id.top),
+                delay_ms: gce.map(|g| g.delay_time_ms()).unwrap_or(0),
+                disposal_method: gce.map(|g| g.disposal_method).unwrap_or(DisposalMethod::None),
+                transparent_color_index: gce.and_then(|g| g.transparent_color_index),
+                interlace: id.interlace
+            })
+            .collect()
+    }
+
+    /// Returns the transparent color of the frame with the given index, if it is available.
+    ///
+    /// The transparent color index of the frame (see `FrameInfo::transparent_color_index`) is
+    /// resolved against the local color table of the frame or, if the frame does not have one,
+    /// against the global color table. `None` is returned if the frame does not exist, if it
+    /// does not have a transparent color, or if the color table is not present or was not
+    /// loaded (see `LoadOptions::load_color_tables`).
+    pub fn transparent_color(&self, frame: usize) -> Option<[u8; 3]> {
+        let (gce, id) = match self.frame_blocks().into_iter().nth(frame) {
+            Some(blocks) => blocks,
+            None => return None
+        };
+        let index = match gce.and_then(|g| g.transparent_color_index) {
+            Some(index) => index,
+            None => return None
+        };
+        id.local_color_table.as_ref()
+            .or(self.global_color_table.as_ref())
+            .and_then(|t| t.colors.as_ref())
+            .and_then(|colors| colors.get(index as usize))
+            .cloned()
+    }
+
+    /// Checks that the header fields are consistent with each other.
+    ///
+    /// This verifies that all frames lie within the logical screen (see
+    /// `ImageDescriptor::exceeds_canvas()`) and that the background color index fits into
+    /// the global color table, if it is present.
+    pub fn validate(&self) -> Result<()> {
+        for (i, &(_, id)) in self.frame_blocks().iter().enumerate() {
+            if id.exceeds_canvas(self.dimensions) {
+                return Err(invalid_format!(
+                    "frame {} ({}x{} at ({}, {})) does not fit into logical screen {}",
+                    i, id.width, id.height, id.left, id.top, self.dimensions
+                ));
+            }
+        }
+
+        if let Some(ref table) = self.global_color_table {
+            if self.background_color_index as u16 >= table.size {
+                return Err(invalid_format!(
+                    "background color index {} is out of global color table of size {}",
+                    self.background_color_index, table.size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pairs each image descriptor block with the graphic control extension block preceding it.
+    fn frame_blocks(&self) -> Vec<(Option<&GraphicControlExtension>, &ImageDescriptor)> {
+        let mut frames = Vec::new();
+        let mut pending_gce = None;
+        for block in &self.blocks {
+            match *block {
+                Block::GraphicControlExtension(ref gce) => pending_gce = Some(gce),
+                Block::ImageDescriptor(ref id) => frames.push((pending_gce.take(), id)),
+                _ => {}
+            }
+        }
+        frames
+    }
+
     /// Returns `true` if the image is animated, `false` otherwise.
     ///
     /// This is currently decided based on the number of frames. If there are more than one frames,
-    /// then the image is considered animated.
+    /// then the image is considered animated. Frames which were not loaded because of the frame
+    /// limit are also taken into account, see `has_more_frames`. With
+    /// `LoadOptions::gif_header_only`, no frames are loaded, so `false` is always returned.
     #[inline]
     pub fn is_animated(&self) -> bool {
         // TODO: is this right?
-        self.frames_number() > 1
+        self.frames_number() > 1 || (self.has_more_frames && self.frames_number() > 0)
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "GIF image, version {}, {}, {}{} frame(s)",
+                    self.version, self.dimensions,
+                    if self.has_more_frames { "at least " } else { "" }, self.frames_number()));
+        if self.is_animated() {
+            try!(write!(f, ", animated, {} ms", self.total_duration_ms()));
+        }
+        Ok(())
     }
 }
 
 impl LoadableMetadata for Metadata {
+    /// Signature and logical screen descriptor.
+    #[inline]
+    fn min_header_len() -> usize {
+        13
+    }
+
+    #[inline]
     fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_with_options(r, &LoadOptions::default())
+    }
+
+    #[inline]
+    fn load_from_seek<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_from_seek_with_options(r, &LoadOptions::default())
+    }
+
+    #[inline]
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        Metadata::load_from_skip(&mut Streaming(r), options)
+    }
+
+    #[inline]
+    fn load_from_seek_with_options<R: ?Sized + BufRead + Seek>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        Metadata::load_from_skip(&mut Seekable(r), options)
+    }
+}
+
+impl Metadata {
+    /// Loads metadata, skipping the data which is not needed in the most efficient way
+    /// supported by the stream.
+    fn load_from_skip<R: ?Sized + Skip>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
         let mut signature = [0u8; 6];
         try!(r.read_exact(&mut signature).map_err(if_eof!(std, "when reading GIF signature")));
 
@@ -532,45 +897,68 @@ impl LoadableMetadata for Metadata {
         let background_color_index = try_if_eof!(r.read_u8(), "when reading background color index");
         let pixel_aspect_ratio = try_if_eof!(r.read_u8(), "when reading pixel aspect ration");
 
-        if global_color_table {
-            let skip_size = global_color_table_size as u64 * 3;
-            if try!(r.skip_exact(skip_size)) != skip_size {
-                return Err(unexpected_eof!("when reading global color table"));
-            }
-        }
+        let global_color_table_colors = if global_color_table {
+            try!(read_color_table(r, global_color_table_size, options.load_color_tables,
+                                  || "when reading global color table".into()))
+        } else {
+            None
+        };
 
+        let max_frames = options.max_gif_frames.map(|n| cmp::max(n, 1));
         let mut blocks = Vec::new();
+        let mut frames = 0usize;
+        let mut has_more_frames = false;
         let mut index = 0usize;
-        loop {
-            let separator = try_if_eof!(r.read_u8(), "when reading separator of block {}", index);
-            let block = match separator {
-                0x2c => Block::ImageDescriptor(try!(ImageDescriptor::load(index, r))),
-                0x21 => {
-                    let label = try_if_eof!(r.read_u8(), "when reading label of block {}", index);
-                    match label {
-                        0x01 => Block::PlainTextExtension(try!(PlainTextExtension::load(index, r))),
-                        0xf9 => Block::GraphicControlExtension(try!(GraphicControlExtension::load(index, r))),
-                        0xfe => Block::CommentExtension(try!(CommentExtension::load(index, r))),
-                        0xff => Block::ApplicationExtension(try!(ApplicationExtension::load(index, r))),
-                        _ => return Err(invalid_format!("unknown extension type of block {}: 0x{:X}", index, label))
+        // with only the header requested, blocks are not read at all
+        if !options.gif_header_only {
+            loop {
+                let block = (|| -> Result<Option<Block>> {
+                    let separator = try_if_eof!(r.read_u8(), "when reading separator of block {}", index);
+                    if separator == 0x2c {
+                        if max_frames.map_or(false, |max| frames >= max) {
+                            has_more_frames = true;
+                            return Ok(None);
+                        }
+                        frames += 1;
                     }
-                },
-                0x3b => break,
-                _ => return Err(invalid_format!("unknown block type of block {}: 0x{:X}", index, separator))
-            };
-            blocks.push(block);
-            index += 1;
+                    Ok(Some(match separator {
+                        0x2c => Block::ImageDescriptor(try!(ImageDescriptor::load(index, r, options.load_color_tables))),
+                        0x21 => {
+                            let label = try_if_eof!(r.read_u8(), "when reading label of block {}", index);
+                            match label {
+                                0x01 => Block::PlainTextExtension(try!(PlainTextExtension::load(index, r))),
+                                0xf9 => Block::GraphicControlExtension(try!(GraphicControlExtension::load(index, r))),
+                                0xfe => Block::CommentExtension(try!(CommentExtension::load(index, r))),
+                                0xff => Block::ApplicationExtension(try!(ApplicationExtension::load(index, r))),
+                                _ => return Err(invalid_format!("unknown extension type of block {}: 0x{:X}", index, label))
+                            }
+                        },
+                        0x3b => return Ok(None),
+                        _ => return Err(invalid_format!("unknown block type of block {}: 0x{:X}", index, separator))
+                    }))
+                })();
+
+                match block {
+                    Ok(Some(block)) => blocks.push(block),
+                    Ok(None) => break,
+                    // a truncated block is dropped
+                    Err(Error::UnexpectedEndOfFile(..)) if options.allow_truncated => break,
+                    Err(e) => return Err(e)
+                }
+                index += 1;
+            }
         }
 
         Ok(Metadata {
             version: version,
 
-            dimensions: (width, height).into(),
+            dimensions: try!(Dimensions::non_zero((width, height))),
 
             global_color_table: if global_color_table {
                 Some(ColorTable {
                     size: global_color_table_size,
-                    sorted: global_color_table_sorted
+                    sorted: global_color_table_sorted,
+                    colors: global_color_table_colors
                 })
             } else {
                 None
@@ -581,7 +969,8 @@ impl LoadableMetadata for Metadata {
             background_color_index: background_color_index,
             pixel_aspect_ratio: pixel_aspect_ratio,
 
-            blocks: blocks
+            blocks: blocks,
+            has_more_frames: has_more_frames
         })
     }
 }
@@ -0,0 +1,293 @@
+//! Metadata of HEIF images, including AVIF images.
+//!
+//! Both formats are based on ISO base media file format (ISOBMFF), which consists of nested
+//! boxes. Image dimensions are stored in the image spatial extents (`ispe`) property of
+//! the primary image item.
+
+use std::io::{Read, BufRead, Take};
+use std::u64;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::{ReadExt, BufReadExt};
+
+/// Represents metadata of a HEIF or an AVIF image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Dimensions of the primary image.
+    pub dimensions: Dimensions,
+    /// Major brand from the file type box, e.g. `heic` or `avif`.
+    pub major_brand: String,
+    /// Minor version from the file type box.
+    pub minor_version: u32,
+    /// Compatible brands from the file type box.
+    pub compatible_brands: Vec<String>
+}
+
+impl Metadata {
+    /// Returns `true` if this is an AVIF image, i.e. if it contains AV1-encoded images.
+    ///
+    /// This is decided based on the brands from the file type box.
+    pub fn is_avif(&self) -> bool {
+        is_avif_brand(self.major_brand.as_bytes()) ||
+            !is_heic_brand(self.major_brand.as_bytes()) &&
+                self.compatible_brands.iter().any(|b| is_avif_brand(b.as_bytes()))
+    }
+}
+
+#[inline]
+fn is_avif_brand(brand: &[u8]) -> bool {
+    brand == b"avif" || brand == b"avis"
+}
+
+#[inline]
+fn is_heic_brand(brand: &[u8]) -> bool {
+    match brand {
+        b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" => true,
+        _ => false
+    }
+}
+
+/// Returns `true` if the brand denotes a HEIF-based image format supported by this module.
+pub(crate) fn is_supported_brand(brand: &[u8]) -> bool {
+    is_avif_brand(brand) || is_heic_brand(brand) || brand == b"mif1" || brand == b"msf1"
+}
+
+#[inline]
+fn brand_to_string(brand: &[u8]) -> String {
+    String::from_utf8_lossy(brand).into_owned()
+}
+
+/// Reads a box header and returns the box type and the size of the box contents.
+///
+/// The size is `None` if the box extends to the end of the file. `None` is returned
+/// instead of the header at the end of the input.
+pub(crate) fn read_box_header<R: ?Sized + Read>(r: &mut R) -> Result<Option<([u8; 4], Option<u64>)>> {
+    let mut header = [0u8; 8];
+    match try!(r.read_exact_0(&mut header)) {
+        0 => return Ok(None),
+        8 => {}
+        _ => return Err(unexpected_eof!("when reading box header"))
+    }
+
+    let size = (&header[..4]).read_u32::<BigEndian>().unwrap();
+    let mut box_type = [0u8; 4];
+    box_type.copy_from_slice(&header[4..]);
+
+    let size = match size {
+        0 => None,
+        // 64-bit box size follows the box type
+        1 => {
+            let size = try_if_eof!(r.read_u64::<BigEndian>(), "when reading size of box {:?}", box_type);
+            if size < 16 {
+                return Err(invalid_format!("invalid size of box {:?}: {}", box_type, size));
+            }
+            Some(size - 16)
+        }
+        n if n < 8 => return Err(invalid_format!("invalid size of box {:?}: {}", box_type, n)),
+        n => Some(n as u64 - 8)
+    };
+
+    Ok(Some((box_type, size)))
+}
+
+/// Invokes the provided function for each box in the input, passing it the box type and
+/// a reader limited to the box contents.
+///
+/// The function returns `true` to stop the iteration. Box contents not consumed by
+/// the function are skipped.
+pub(crate) fn read_boxes<R: ?Sized + BufRead, F>(r: &mut R, mut f: F) -> Result<()>
+    where F: FnMut([u8; 4], &mut Take<&mut R>) -> Result<bool>
+{
+    loop {
+        let (box_type, size) = match try!(read_box_header(r)) {
+            Some(header) => header,
+            None => return Ok(())
+        };
+
+        let mut contents = (&mut *r).take(size.unwrap_or(u64::MAX));
+        let stop = try!(f(box_type, &mut contents));
+        // nested boxes end silently at the end of the input, so a truncated box must be
+        // detected here
+        if size.is_some() && contents.limit() > 0 && try!(contents.fill_buf()).is_empty() {
+            return Err(unexpected_eof!("when reading contents of box {:?}", box_type));
+        }
+        if stop || size.is_none() {
+            return Ok(());
+        }
+
+        let remaining = contents.limit();
+        if try!(contents.skip_exact(remaining)) != remaining {
+            return Err(unexpected_eof!("when skipping contents of box {:?}", box_type));
+        }
+    }
+}
+
+/// Skips version and flags of a full box, returning them.
+fn read_full_box_header<R: ?Sized + Read>(r: &mut R, box_type: &str) -> Result<(u8, u32)> {
+    let n = try_if_eof!(r.read_u32::<BigEndian>(), "when reading version of {} box", box_type);
+    Ok(((n >> 24) as u8, n & 0xffffff))
+}
+
+/// Item properties collected from the meta box.
+#[derive(Default)]
+struct Properties {
+    primary_item: Option<u32>,
+    // spatial extents along with their 1-based index in the property container
+    extents: Vec<(u16, Dimensions)>,
+    // property indices associated with the primary item
+    primary_associations: Vec<u16>
+}
+
+impl Properties {
+    fn primary_dimensions(&self) -> Option<Dimensions> {
+        self.primary_associations.iter()
+            .filter_map(|&i| self.extents.iter().find(|&&(j, _)| i == j))
+            .map(|&(_, d)| d)
+            .next()
+            // fall back to the first extents if there is no information on the primary item
+            .or_else(|| self.extents.first().map(|&(_, d)| d))
+    }
+}
+
+fn read_meta<R: ?Sized + BufRead>(r: &mut R) -> Result<Properties> {
+    try!(read_full_box_header(r, "meta"));
+
+    let mut props = Properties::default();
+    // ipma box may precede pitm box, so associations of all items are kept until the end
+    let mut associations = Vec::new();
+
+    try!(read_boxes(r, |box_type, r| {
+        match &box_type {
+            b"pitm" => {
+                let (version, _) = try!(read_full_box_header(r, "pitm"));
+                props.primary_item = Some(if version == 0 {
+                    try_if_eof!(r.read_u16::<BigEndian>(), "when reading primary item ID") as u32
+                } else {
+                    try_if_eof!(r.read_u32::<BigEndian>(), "when reading primary item ID")
+                });
+            }
+            b"iprp" => try!(read_boxes(r, |box_type, r| {
+                match &box_type {
+                    b"ipco" => try!(read_item_properties(r, &mut props.extents)),
+                    b"ipma" => try!(read_item_associations(r, &mut associations)),
+                    _ => {}
+                }
+                Ok(false)
+            })),
+            _ => {}
+        }
+        Ok(false)
+    }));
+
+    if let Some(primary_item) = props.primary_item {
+        props.primary_associations = associations.into_iter()
+            .filter(|&(item, _)| item == primary_item)
+            .map(|(_, index)| index)
+            .collect();
+    }
+
+    Ok(props)
+}
+
+fn read_item_properties<R: ?Sized + BufRead>(r: &mut R, extents: &mut Vec<(u16, Dimensions)>) -> Result<()> {
+    let mut index = 0;
+    read_boxes(r, |box_type, r| {
+        index += 1;
+        if &box_type == b"ispe" {
+            try!(read_full_box_header(r, "ispe"));
+            let width = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image width");
+            let height = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image height");
+            extents.push((index, try!(Dimensions::non_zero((width, height)))));
+        }
+        Ok(false)
+    })
+}
+
+fn read_item_associations<R: ?Sized + Read>(r: &mut R, associations: &mut Vec<(u32, u16)>) -> Result<()> {
+    let (version, flags) = try!(read_full_box_header(r, "ipma"));
+
+    let entry_count = try_if_eof!(r.read_u32::<BigEndian>(), "when reading number of item associations");
+    for _ in 0..entry_count {
+        let item = if version < 1 {
+            try_if_eof!(r.read_u16::<BigEndian>(), "when reading item ID") as u32
+        } else {
+            try_if_eof!(r.read_u32::<BigEndian>(), "when reading item ID")
+        };
+
+        let count = try_if_eof!(r.read_u8(), "when reading number of associations of item {}", item);
+        for _ in 0..count {
+            // the highest bit is the "essential" flag
+            let index = if flags & 1 != 0 {
+                try_if_eof!(r.read_u16::<BigEndian>(), "when reading property index") & 0x7fff
+            } else {
+                (try_if_eof!(r.read_u8(), "when reading property index") & 0x7f) as u16
+            };
+            associations.push((item, index));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_file_type<R: ?Sized + Read>(r: &mut R) -> Result<([u8; 4], u32, Vec<String>)> {
+    let mut major_brand = [0u8; 4];
+    try_if_eof!(std, r.read_exact(&mut major_brand), "when reading major brand");
+    let minor_version = try_if_eof!(r.read_u32::<BigEndian>(), "when reading minor version");
+
+    let mut compatible_brands = Vec::new();
+    let mut brand = [0u8; 4];
+    while try!(r.read_exact_0(&mut brand)) == 4 {
+        compatible_brands.push(brand_to_string(&brand));
+    }
+
+    Ok((major_brand, minor_version, compatible_brands))
+}
+
+impl LoadableMetadata for Metadata {
+    /// File type box and the smallest meta box with spatial extents.
+    #[inline]
+    fn min_header_len() -> usize {
+        64
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut file_type = None;
+        let mut props = None;
+
+        try!(read_boxes(r, |box_type, r| {
+            match &box_type {
+                b"ftyp" => file_type = Some(try!(read_file_type(r))),
+                b"meta" => props = Some(try!(read_meta(r))),
+                _ if file_type.is_none() =>
+                    return Err(invalid_format!("file type box is missing, found {:?}", box_type)),
+                _ => {}
+            }
+            // everything we need is located in the meta box, which precedes the image data
+            Ok(props.is_some())
+        }));
+
+        let (major_brand, minor_version, compatible_brands) = try!(
+            file_type.ok_or(invalid_format!("file type box is missing"))
+        );
+        if !is_supported_brand(&major_brand) &&
+           !compatible_brands.iter().any(|b| is_supported_brand(b.as_bytes())) {
+            return Err(invalid_format!("unsupported ISOBMFF brand: {:?}", major_brand));
+        }
+
+        // the boxes are only read until the end of the input if there is no meta box
+        let props = try!(props.ok_or(unexpected_eof!("when looking for meta box")));
+
+        Ok(Metadata {
+            dimensions: try!(
+                props.primary_dimensions().ok_or(invalid_format!("image spatial extents are missing"))
+            ),
+            major_brand: brand_to_string(&major_brand),
+            minor_version: minor_version,
+            compatible_brands: compatible_brands
+        })
+    }
+}
@@ -0,0 +1,144 @@
+//! Metadata of ICO and CUR images.
+
+use std::io::BufRead;
+use std::fmt;
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+
+/// Type of the resource file.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IconType {
+    /// Icon (.ico) file.
+    Icon,
+    /// Cursor (.cur) file.
+    Cursor
+}
+
+impl fmt::Display for IconType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            IconType::Icon => "Icon",
+            IconType::Cursor => "Cursor",
+        })
+    }
+}
+
+impl IconType {
+    fn from_u16(n: u16) -> Option<IconType> {
+        match n {
+            1 => Some(IconType::Icon),
+            2 => Some(IconType::Cursor),
+            _ => None
+        }
+    }
+}
+
+/// Represents a single image in the icon directory.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IconEntry {
+    /// Width and height of the image.
+    ///
+    /// Zero width or height in the directory entry means 256 pixels, and it is converted
+    /// accordingly.
+    pub dimensions: Dimensions,
+    /// Number of colors in the palette of the image, or 0 if the palette is not used.
+    pub color_count: u8,
+    /// Number of bits per pixel; only available for icons.
+    pub bit_count: Option<u16>,
+    /// Horizontal and vertical coordinates of the hotspot; only available for cursors.
+    pub hotspot: Option<(u16, u16)>,
+    /// Size of the image data in bytes.
+    pub size: u32,
+    /// Offset of the image data from the beginning of the file.
+    pub offset: u32
+}
+
+/// Represents metadata of an ICO or CUR image.
+///
+/// These files contain a directory of several images, usually of different sizes.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Type of the file, icon or cursor.
+    pub icon_type: IconType,
+    /// Directory entries, one for each image in the file.
+    pub entries: Vec<IconEntry>
+}
+
+impl Metadata {
+    /// Returns dimensions of the largest image in the file.
+    ///
+    /// Images are compared by their area.
+    pub fn largest(&self) -> Dimensions {
+        self.entries.iter()
+            .map(|e| e.dimensions)
+            .max_by_key(|d| d.width as u64 * d.height as u64)
+            .unwrap_or(Dimensions { width: 0, height: 0 })
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// Icon directory header and a single directory entry.
+    #[inline]
+    fn min_header_len() -> usize {
+        22
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let reserved = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading ICO header");
+        if reserved != 0 {
+            return Err(invalid_format!("invalid ICO header reserved field: {}", reserved));
+        }
+
+        let icon_type = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading ICO image type");
+        let icon_type = try!(
+            IconType::from_u16(icon_type)
+                .ok_or(invalid_format!("invalid ICO image type: {}", icon_type))
+        );
+
+        let count = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading number of ICO images");
+        if count == 0 {
+            return Err(invalid_format!("ICO file does not contain any images"));
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let width = try_if_eof!(r.read_u8(), "when reading width of ICO image {}", i);
+            let height = try_if_eof!(r.read_u8(), "when reading height of ICO image {}", i);
+            let color_count = try_if_eof!(r.read_u8(), "when reading color count of ICO image {}", i);
+            let _ = try_if_eof!(r.read_u8(), "when reading reserved field of ICO image {}", i);
+            // color planes and bit count for icons, hotspot coordinates for cursors
+            let field1 = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading directory entry {}", i);
+            let field2 = try_if_eof!(r.read_u16::<LittleEndian>(), "when reading directory entry {}", i);
+            let size = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading data size of ICO image {}", i);
+            let offset = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading data offset of ICO image {}", i);
+
+            let (bit_count, hotspot) = match icon_type {
+                IconType::Icon => (Some(field2), None),
+                IconType::Cursor => (None, Some((field1, field2)))
+            };
+
+            entries.push(IconEntry {
+                dimensions: Dimensions {
+                    width: if width == 0 { 256 } else { width as u32 },
+                    height: if height == 0 { 256 } else { height as u32 }
+                },
+                color_count: color_count,
+                bit_count: bit_count,
+                hotspot: hotspot,
+                size: size,
+                offset: offset
+            });
+        }
+
+        Ok(Metadata {
+            icon_type: icon_type,
+            entries: entries
+        })
+    }
+}
@@ -0,0 +1,126 @@
+//! Metadata of JPEG 2000 images.
+//!
+//! Both raw codestreams (usually with `.j2k` or `.j2c` extension) and codestreams wrapped
+//! into the JP2 file format are supported. The former store image parameters in the image
+//! and tile size (SIZ) marker segment, the latter in the image header (`ihdr`) box.
+
+use std::io::BufRead;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use formats::heif::read_boxes;
+
+/// Represents metadata of a JPEG 2000 image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions.
+    pub dimensions: Dimensions,
+    /// Number of image components, e.g. 3 for RGB images.
+    pub components: u16,
+    /// Whether the codestream is wrapped into the JP2 file format.
+    pub jp2_container: bool
+}
+
+/// The first four bytes of a raw codestream: SOC marker followed by SIZ marker.
+const CODESTREAM_MAGIC: &'static [u8] = b"\xff\x4f\xff\x51";
+/// The length of the JP2 signature box.
+const SIGNATURE_BOX_LENGTH: &'static [u8] = b"\0\0\0\x0c";
+/// The type and contents of the JP2 signature box.
+const SIGNATURE_BOX: &'static [u8] = b"jP  \r\n\x87\n";
+
+fn read_codestream_header<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+    let _lsiz = try_if_eof!(r.read_u16::<BigEndian>(), "when reading SIZ marker segment length");
+    let _rsiz = try_if_eof!(r.read_u16::<BigEndian>(), "when reading codestream capabilities");
+
+    let width = try_if_eof!(r.read_u32::<BigEndian>(), "when reading reference grid width");
+    let height = try_if_eof!(r.read_u32::<BigEndian>(), "when reading reference grid height");
+    let x_offset = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image horizontal offset");
+    let y_offset = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image vertical offset");
+
+    // tile sizes and offsets
+    let mut tiles = [0u8; 16];
+    try_if_eof!(std, r.read_exact(&mut tiles), "when reading tile parameters");
+
+    let components = try_if_eof!(r.read_u16::<BigEndian>(), "when reading number of components");
+
+    if x_offset >= width || y_offset >= height {
+        return Err(invalid_format!(
+            "image offset ({}, {}) is outside of reference grid {}x{}", x_offset, y_offset, width, height
+        ));
+    }
+
+    Ok(Metadata {
+        dimensions: try!(Dimensions::non_zero((width - x_offset, height - y_offset))),
+        components: components,
+        jp2_container: false
+    })
+}
+
+fn read_image_header<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+    let height = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image height");
+    let width = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image width");
+    let components = try_if_eof!(r.read_u16::<BigEndian>(), "when reading number of components");
+
+    Ok(Metadata {
+        dimensions: try!(Dimensions::non_zero((width, height))),
+        components: components,
+        jp2_container: true
+    })
+}
+
+impl LoadableMetadata for Metadata {
+    /// SOC marker and SIZ marker segment of a raw codestream, which is shorter than JP2 headers.
+    #[inline]
+    fn min_header_len() -> usize {
+        42
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut magic = [0u8; 4];
+        try_if_eof!(std, r.read_exact(&mut magic), "when reading JPEG 2000 signature");
+
+        if magic == CODESTREAM_MAGIC {
+            return read_codestream_header(r);
+        }
+
+        let mut signature = [0u8; 8];
+        try_if_eof!(std, r.read_exact(&mut signature), "when reading JP2 signature box");
+        if magic != SIGNATURE_BOX_LENGTH || signature != SIGNATURE_BOX {
+            return Err(invalid_format!("invalid JPEG 2000 signature"));
+        }
+
+        let mut seen_file_type = false;
+        let mut seen_codestream = false;
+        let mut result = None;
+        try!(read_boxes(r, |box_type, r| {
+            match &box_type {
+                b"ftyp" => seen_file_type = true,
+                _ if !seen_file_type =>
+                    return Err(invalid_format!("file type box is missing, found {:?}", box_type)),
+                b"jp2h" => try!(read_boxes(r, |box_type, r| {
+                    if &box_type == b"ihdr" {
+                        result = Some(try!(read_image_header(r)));
+                        return Ok(true);
+                    }
+                    Ok(false)
+                })),
+                // the codestream box follows the header box
+                b"jp2c" => {
+                    seen_codestream = true;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+            Ok(result.is_some())
+        }));
+
+        match result {
+            Some(result) => Ok(result),
+            None if seen_codestream => Err(invalid_format!("image header box is missing")),
+            None => Err(unexpected_eof!("when looking for image header box"))
+        }
+    }
+}
@@ -1,16 +1,19 @@
 //! Metadata of JPEG images.
 
-use std::io::BufRead;
+use std::io::{self, BufRead, Seek};
 use std::fmt;
 
-use byteorder::{ReadBytesExt, BigEndian};
+use byteorder::{ReadBytesExt, ByteOrder, BigEndian};
 
-use types::{Result, Dimensions};
+use types::{Result, Error, Dimensions, LoadOptions};
 use traits::LoadableMetadata;
-use utils::BufReadExt;
+use utils::{BufReadExt, Skip, Streaming, Seekable};
+use common::exif::{Exif, Orientation};
 
 /// Coding process used in an image.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum CodingProcess {
     /// Sequential DCT (discrete cosine transform).
     DctSequential,
@@ -42,7 +45,9 @@ impl CodingProcess {
 }
 
 /// Entropy coding method used in an image.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum EntropyCoding {
     /// Huffman coding.
     Huffman,
@@ -69,11 +74,185 @@ impl EntropyCoding {
     }
 }
 
+/// Chroma subsampling scheme used in an image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Subsampling {
+    /// No chroma subsampling.
+    Ratio444,
+    /// Chroma is subsampled horizontally by the factor of 2.
+    Ratio422,
+    /// Chroma is subsampled both horizontally and vertically by the factor of 2.
+    Ratio420,
+    /// Chroma is subsampled vertically by the factor of 2.
+    Ratio440,
+    /// Chroma is subsampled horizontally by the factor of 4.
+    Ratio411
+}
+
+impl fmt::Display for Subsampling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Subsampling::Ratio444 => "4:4:4",
+            Subsampling::Ratio422 => "4:2:2",
+            Subsampling::Ratio420 => "4:2:0",
+            Subsampling::Ratio440 => "4:4:0",
+            Subsampling::Ratio411 => "4:1:1",
+        })
+    }
+}
+
+impl Subsampling {
+    /// Determines subsampling scheme from sampling factors of the frame components.
+    ///
+    /// Each item contains horizontal and vertical sampling factors of the respective
+    /// component. Subsampling can only be determined for three-component images whose chroma
+    /// components have the same sampling factors.
+    fn from_sampling_factors(factors: &[(u8, u8)]) -> Option<Subsampling> {
+        let (luma, cb, cr) = match *factors {
+            [luma, cb, cr] => (luma, cb, cr),
+            _ => return None
+        };
+        if cb != cr || cb.0 == 0 || cb.1 == 0 || luma.0 % cb.0 != 0 || luma.1 % cb.1 != 0 {
+            return None;
+        }
+
+        match (luma.0 / cb.0, luma.1 / cb.1) {
+            (1, 1) => Some(Subsampling::Ratio444),
+            (2, 1) => Some(Subsampling::Ratio422),
+            (2, 2) => Some(Subsampling::Ratio420),
+            (1, 2) => Some(Subsampling::Ratio440),
+            (4, 1) => Some(Subsampling::Ratio411),
+            _ => None
+        }
+    }
+}
+
+/// Units of JFIF pixel density.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DensityUnit {
+    /// No units, the density only specifies the pixel aspect ratio.
+    None,
+    /// Dots per inch.
+    DotsPerInch,
+    /// Dots per centimeter.
+    DotsPerCm
+}
+
+impl fmt::Display for DensityUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DensityUnit::None => "None",
+            DensityUnit::DotsPerInch => "Dots per inch",
+            DensityUnit::DotsPerCm => "Dots per centimeter",
+        })
+    }
+}
+
+impl DensityUnit {
+    fn from_u8(n: u8) -> Option<DensityUnit> {
+        match n {
+            0 => Some(DensityUnit::None),
+            1 => Some(DensityUnit::DotsPerInch),
+            2 => Some(DensityUnit::DotsPerCm),
+            _ => None
+        }
+    }
+}
+
+/// Color transform applied to the image components, as stored in Adobe APP14 segment.
+///
+/// This segment is written by Adobe applications, and it is the only way to tell how
+/// the components of CMYK and YCCK images are encoded. Note that Adobe applications also
+/// store CMYK data inverted.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AdobeTransform {
+    /// No transform: RGB images with three components and CMYK images with four components.
+    Unknown,
+    /// YCbCr image with three components.
+    YCbCr,
+    /// YCCK image with four components.
+    Ycck
+}
+
+impl fmt::Display for AdobeTransform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            AdobeTransform::Unknown => "Unknown (RGB or CMYK)",
+            AdobeTransform::YCbCr => "YCbCr",
+            AdobeTransform::Ycck => "YCCK",
+        })
+    }
+}
+
+impl AdobeTransform {
+    fn from_u8(n: u8) -> Option<AdobeTransform> {
+        match n {
+            0 => Some(AdobeTransform::Unknown),
+            1 => Some(AdobeTransform::YCbCr),
+            2 => Some(AdobeTransform::Ycck),
+            _ => None
+        }
+    }
+
+    fn from_payload(data: &[u8]) -> Option<AdobeTransform> {
+        // version (2 bytes), flags0 (2 bytes), flags1 (2 bytes), transform (1 byte)
+        data.get(6).and_then(|&t| AdobeTransform::from_u8(t))
+    }
+}
+
+/// Pixel density of an image, as stored in JFIF APP0 segment.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JfifDensity {
+    /// Units of the density values.
+    pub units: DensityUnit,
+    /// Horizontal pixel density.
+    pub x_density: u16,
+    /// Vertical pixel density.
+    pub y_density: u16
+}
+
+impl JfifDensity {
+    /// Returns horizontal and vertical resolution in dots per inch, if the units are known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        const CM_PER_INCH: f64 = 2.54;
+        match self.units {
+            DensityUnit::DotsPerInch => Some((self.x_density as f64, self.y_density as f64)),
+            DensityUnit::DotsPerCm => Some((
+                self.x_density as f64 * CM_PER_INCH,
+                self.y_density as f64 * CM_PER_INCH
+            )),
+            DensityUnit::None => None
+        }
+    }
+
+    fn from_payload(data: &[u8]) -> Result<JfifDensity> {
+        // version (2 bytes), units (1 byte), X density (2 bytes), Y density (2 bytes)
+        if data.len() < 7 {
+            return Err(invalid_format!("JFIF segment is too short: {} bytes", data.len()));
+        }
+
+        let units = data[2];
+        Ok(JfifDensity {
+            units: try!(
+                DensityUnit::from_u8(units)
+                    .ok_or(invalid_format!("invalid JFIF density units: {}", units))
+            ),
+            x_density: BigEndian::read_u16(&data[3..5]),
+            y_density: BigEndian::read_u16(&data[5..7])
+        })
+    }
+}
+
 /// Represents metadata of a JPEG image.
 ///
 /// It provides information contained in JPEG frame header, including image dimensions,
 /// coding process type and entropy coding type.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Metadata {
     /// Image size.
     pub dimensions: Dimensions,
@@ -87,16 +266,101 @@ pub struct Metadata {
     pub baseline: bool,
     /// Whether this image uses a differential encoding.
     pub differential: bool,
+    /// EXIF metadata from the APP1 segment, if it is present.
+    pub exif: Option<Exif>,
+    /// Number of components in the frame, e.g. 1 for grayscale images and 3 for YCbCr images.
+    pub num_components: u8,
+    /// Chroma subsampling scheme, if it can be determined.
+    ///
+    /// Subsampling is only defined for images with three components.
+    pub subsampling: Option<Subsampling>,
+    /// Pixel density from JFIF APP0 segment, if it is present.
+    pub density: Option<JfifDensity>,
+    /// Embedded ICC color profile, if it is present.
+    ///
+    /// The profile may be split across several APP2 segments; they are reassembled in order
+    /// of their sequence numbers. If some of the segments are missing or inconsistent,
+    /// the profile is ignored.
+    pub icc_profile: Option<Vec<u8>>,
+    /// Number of scans (SOS segments) in the image.
+    ///
+    /// Baseline images usually contain only one scan, while progressive images contain several
+    /// ones. Counting scans requires reading the whole image; if the image is truncated, only
+    /// the scans present in the available data are counted.
+    pub scan_count: u16,
+    /// Contents of comment (COM) segments located before the frame header.
+    ///
+    /// Comments are decoded as UTF-8; invalid sequences are replaced with U+FFFD.
+    pub comments: Vec<String>,
+    /// XMP packet from the APP1 segment, if it is present.
+    ///
+    /// The packet is decoded as UTF-8; invalid sequences are replaced with U+FFFD. It is not
+    /// parsed in any way.
+    pub xmp: Option<String>,
+    /// Extended XMP packet, if it is present.
+    ///
+    /// Extended XMP is used when the XMP data does not fit into a single APP1 segment. It is
+    /// split across several APP1 segments which are reassembled according to their offsets.
+    /// Only the segments whose GUID matches the `xmpNote:HasExtendedXMP` property of the main
+    /// packet are used; if some of them are missing, the extended packet is ignored.
+    pub extended_xmp: Option<String>,
+    /// Color transform from Adobe APP14 segment, if it is present.
+    pub adobe_transform: Option<AdobeTransform>,
+    /// Restart interval in MCUs from DRI segment, if it is present.
+    ///
+    /// If there are several DRI segments, the last one before the first scan is used.
+    /// Zero means that restart markers are disabled.
+    pub restart_interval: Option<u16>,
+    /// Number of quantization tables defined in DQT segments.
+    ///
+    /// A single segment may define several tables. Tables which are redefined between scans
+    /// are counted each time they are defined.
+    pub quantization_tables: u8,
+    /// Number of Huffman tables defined in DHT segments.
+    ///
+    /// A single segment may define several tables. Tables which are redefined between scans,
+    /// as is common in progressive images, are counted each time they are defined.
+    pub huffman_tables: u8,
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JPEG image, {}, {}-bit samples, {}, {} coding",
+               self.dimensions, self.sample_precision, self.coding_process, self.entropy_coding)
+    }
 }
 
+impl Metadata {
+    /// Returns image orientation from EXIF metadata, if it is available.
+    ///
+    /// `None` is returned if there is no EXIF metadata in the image, or if it does not
+    /// contain a valid orientation tag.
+    #[inline]
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.exif.as_ref().and_then(|exif| exif.orientation())
+    }
+}
+
+const JFIF_IDENTIFIER: &'static [u8] = b"JFIF\0";
+const EXIF_IDENTIFIER: &'static [u8] = b"Exif\0\0";
+const ICC_PROFILE_IDENTIFIER: &'static [u8] = b"ICC_PROFILE\0";
+const XMP_IDENTIFIER: &'static [u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXTENDED_XMP_IDENTIFIER: &'static [u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const ADOBE_IDENTIFIER: &'static [u8] = b"Adobe";
+/// Length of the extended XMP chunk header: GUID, full length and offset.
+const EXTENDED_XMP_HEADER_LEN: usize = 32 + 4 + 4;
+
 fn find_marker<R: ?Sized, F>(r: &mut R, name: &str, mut matcher: F) -> Result<u8>
     where R: BufRead, F: FnMut(u8) -> bool
 {
     loop {
-        if try!(r.skip_until(0xff)) == 0 {
+        if try!(BufReadExt::skip_until(r, 0xff)) == 0 {
             return Err(unexpected_eof!("when searching for {} marker", name));
         }
-        let marker_type = try_if_eof!(r.read_u8(), "when reading marker type");
+        let mut marker_type = try_if_eof!(r.read_u8(), "when reading marker type");
+        while marker_type == 0xff {  // skip fill bytes
+            marker_type = try_if_eof!(r.read_u8(), "when reading marker type");
+        }
         if marker_type == 0 { continue; }  // skip "stuffed" byte
 
         if matcher(marker_type) {
@@ -106,15 +370,154 @@ fn find_marker<R: ?Sized, F>(r: &mut R, name: &str, mut matcher: F) -> Result<u8
 }
 
 impl LoadableMetadata for Metadata {
+    /// SOI marker and the smallest SOF marker segment.
+    #[inline]
+    fn min_header_len() -> usize {
+        15
+    }
+
+    #[inline]
     fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_with_options(r, &LoadOptions::default())
+    }
+
+    #[inline]
+    fn load_from_seek<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_from_seek_with_options(r, &LoadOptions::default())
+    }
+
+    #[inline]
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        Metadata::load_from_skip(&mut Streaming(r), options)
+    }
+
+    #[inline]
+    fn load_from_seek_with_options<R: ?Sized + BufRead + Seek>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        Metadata::load_from_skip(&mut Seekable(r), options)
+    }
+}
+
+impl Metadata {
+    /// Loads metadata, skipping the data which is not needed in the most efficient way
+    /// supported by the stream.
+    ///
+    /// The sizes of comments, extended XMP chunks and ICC profile chunks are checked against
+    /// the allocation limit before they are read, since their total size is only bounded by
+    /// the size of the image.
+    fn load_from_skip<R: ?Sized + Skip>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
         // read SOI marker, it must be present in all JPEG files
         try!(find_marker(r, "SOI", |m| m == 0xd8));
 
         // XXX: do we need to check for APP0 JFIF marker? This doesn't seem strictly necessary
         // XXX: to me, and it seems that other interchange formats are also possible.
 
-        // read SOF marker, it must also be present in all JPEG files
-        let marker = try!(find_marker(r, "SOF", is_sof_marker));
+        let mut exif = None;
+        let mut density = None;
+        let mut comments = Vec::new();
+        let mut comments_len = 0u64;
+        let mut icc_chunks = Vec::new();
+        let mut icc_len = 0u64;
+        let mut xmp = None;
+        let mut xmp_chunks = Vec::new();
+        let mut xmp_chunks_len = 0u64;
+        let mut adobe_transform = None;
+        let mut restart_interval = None;
+        let mut tables = TableCounts::default();
+
+        // read segments until SOF marker, it must also be present in all JPEG files
+        let marker = loop {
+            let marker = try!(find_marker(r, "SOF", |_| true));
+            if is_sof_marker(marker) {
+                break marker;
+            }
+            if is_standalone_marker(marker) {
+                continue;
+            }
+
+            let size = try_if_eof!(
+                r.read_u16::<BigEndian>(),
+                "when reading payload size of marker 0x{:X}", marker
+            );
+            if size < 2 {  // 2 bytes for the length itself
+                return Err(invalid_format!("invalid payload size of marker 0x{:X}: {}", marker, size));
+            }
+            let size = size - 2;
+
+            match marker {
+                // APP0, may contain JFIF header
+                0xe0 if density.is_none() => {
+                    let data = try!(read_payload(r, marker, size));
+                    if data.starts_with(JFIF_IDENTIFIER) {
+                        density = JfifDensity::from_payload(&data[JFIF_IDENTIFIER.len()..]).ok();
+                    }
+                }
+                // APP1, may contain EXIF metadata, XMP packet or a part of extended XMP packet
+                0xe1 => {
+                    let data = try!(read_payload(r, marker, size));
+                    if data.starts_with(EXIF_IDENTIFIER) {
+                        if exif.is_none() {
+                            // broken EXIF metadata should not prevent loading the frame header
+                            exif = Exif::load_with_options(&data[EXIF_IDENTIFIER.len()..], options).ok();
+                        }
+                    } else if data.starts_with(XMP_IDENTIFIER) {
+                        if xmp.is_none() {
+                            xmp = Some(String::from_utf8_lossy(&data[XMP_IDENTIFIER.len()..]).into_owned());
+                        }
+                    } else if data.starts_with(EXTENDED_XMP_IDENTIFIER) &&
+                              data.len() >= EXTENDED_XMP_IDENTIFIER.len() + EXTENDED_XMP_HEADER_LEN {
+                        // GUID is followed by the full length of the packet and the offset of this chunk
+                        let header = &data[EXTENDED_XMP_IDENTIFIER.len()..];
+                        let mut guid = [0u8; 32];
+                        guid.copy_from_slice(&header[..32]);
+                        let length = BigEndian::read_u32(&header[32..36]);
+                        let offset = BigEndian::read_u32(&header[36..40]);
+                        xmp_chunks_len += (header.len() - EXTENDED_XMP_HEADER_LEN) as u64;
+                        try!(options.check_alloc(xmp_chunks_len, "extended XMP chunks"));
+                        xmp_chunks.push((guid, length, offset, header[EXTENDED_XMP_HEADER_LEN..].to_vec()));
+                    }
+                }
+                // APP2, may contain a part of ICC profile
+                0xe2 => {
+                    let data = try!(read_payload(r, marker, size));
+                    // identifier is followed by the sequence number and the total number of chunks
+                    let n = ICC_PROFILE_IDENTIFIER.len();
+                    if data.starts_with(ICC_PROFILE_IDENTIFIER) && data.len() >= n + 2 {
+                        icc_len += (data.len() - n - 2) as u64;
+                        try!(options.check_alloc(icc_len, "ICC profile chunks"));
+                        icc_chunks.push((data[n], data[n + 1], data[n + 2..].to_vec()));
+                    }
+                }
+                // APP14, may contain Adobe color transform
+                0xee if adobe_transform.is_none() => {
+                    let data = try!(read_payload(r, marker, size));
+                    if data.starts_with(ADOBE_IDENTIFIER) {
+                        adobe_transform = AdobeTransform::from_payload(&data[ADOBE_IDENTIFIER.len()..]);
+                    }
+                }
+                // DRI
+                0xdd => {
+                    if size != 2 {
+                        return Err(invalid_format!("invalid DRI marker payload size: {}", size + 2));
+                    }
+                    restart_interval = Some(try_if_eof!(r.read_u16::<BigEndian>(), "when reading restart interval"));
+                }
+                // DQT and DHT
+                0xdb | 0xc4 => {
+                    let data = try!(read_payload(r, marker, size));
+                    try!(tables.add(marker, &data));
+                }
+                // COM
+                0xfe => {
+                    comments_len += size as u64;
+                    try!(options.check_alloc(comments_len, "JPEG comments"));
+                    let data = try!(read_payload(r, marker, size));
+                    comments.push(String::from_utf8_lossy(&data).into_owned());
+                }
+                _ => if try!(r.skip(size as u64)) != size as u64 {
+                    return Err(unexpected_eof!("when skipping payload of marker 0x{:X}", marker));
+                }
+            }
+        };
 
         // read and check SOF marker length
         let size = try_if_eof!(r.read_u16::<BigEndian>(), "when reading SOF marker payload size");
@@ -128,7 +531,39 @@ impl LoadableMetadata for Metadata {
         // read height and width
         let h = try_if_eof!(r.read_u16::<BigEndian>(), "when reading JPEG frame height");
         let w = try_if_eof!(r.read_u16::<BigEndian>(), "when reading JPEG frame width");
-        // TODO: handle h == 0 (we need to read a DNL marker after the first scan)
+
+        // read the number of components and check that they fit into the frame header
+        let num_components = try_if_eof!(r.read_u8(), "when reading number of frame components");
+        if (size as usize) < 8 + 3 * num_components as usize {
+            return Err(invalid_format!("JPEG frame header size {} is too small for {} components",
+                                       size, num_components));
+        }
+
+        let mut sampling_factors = Vec::with_capacity(num_components as usize);
+        for i in 0..num_components {
+            let mut component = [0u8; 3];  // identifier, sampling factors, quantization table
+            try!(r.read_exact(&mut component)
+                .map_err(if_eof!(std, "when reading frame component {}", i)));
+            sampling_factors.push((component[1] >> 4, component[1] & 0x0f));
+        }
+
+        let rest = size as u64 - 8 - 3 * num_components as u64;
+        if try!(r.skip(rest)) != rest {
+            return Err(unexpected_eof!("when skipping the rest of JPEG frame header"));
+        }
+
+        let (scan_count, dnl_lines, scan_restart_interval) = try!(walk_scans(r, &mut tables));
+
+        // zero height means that it is defined by a DNL marker after the first scan
+        let h = if h == 0 {
+            match dnl_lines {
+                Some(0) => return Err(invalid_format!("invalid number of lines in DNL marker: 0")),
+                Some(lines) => lines,
+                None => return Err(invalid_format!("JPEG frame height is zero but DNL marker is missing"))
+            }
+        } else {
+            h
+        };
 
         // there is only one baseline DCT marker, naturally
         let baseline = marker == 0xc0;
@@ -143,17 +578,234 @@ impl LoadableMetadata for Metadata {
         let coding_process = CodingProcess::from_marker(marker).unwrap();
         let entropy_coding = EntropyCoding::from_marker(marker).unwrap();
 
+        let extended_xmp = xmp.as_ref()
+            .and_then(|xmp| extended_xmp_guid(xmp))
+            .and_then(|guid| assemble_extended_xmp(guid, xmp_chunks));
+
         Ok(Metadata {
-            dimensions: (w, h).into(),
+            dimensions: try!(Dimensions::non_zero((w, h))),
             sample_precision: sample_precision,
             coding_process: coding_process,
             entropy_coding: entropy_coding,
             baseline: baseline,
             differential: differential,
+            exif: exif,
+            num_components: num_components,
+            subsampling: Subsampling::from_sampling_factors(&sampling_factors),
+            density: density,
+            icc_profile: assemble_icc_profile(icc_chunks),
+            scan_count: scan_count,
+            comments: comments,
+            xmp: xmp,
+            extended_xmp: extended_xmp,
+            adobe_transform: adobe_transform,
+            restart_interval: scan_restart_interval.or(restart_interval),
+            quantization_tables: tables.quantization,
+            huffman_tables: tables.huffman,
         })
     }
 }
 
+/// Numbers of tables defined in DQT and DHT segments.
+#[derive(Default)]
+struct TableCounts {
+    quantization: u8,
+    huffman: u8
+}
+
+impl TableCounts {
+    /// Counts the tables defined in the payload of a DQT or DHT segment.
+    fn add(&mut self, marker: u8, data: &[u8]) -> Result<()> {
+        let mut pos = 0;
+        while pos < data.len() {
+            // each table starts with a byte containing its class or precision and its identifier
+            let spec = data[pos];
+            pos += 1 + if marker == 0xdb {
+                // 8-bit or 16-bit values
+                if spec >> 4 == 0 { 64 } else { 128 }
+            } else {
+                // numbers of codes of each length are followed by the values
+                if data.len() < pos + 17 {
+                    return Err(invalid_format!("truncated Huffman table in DHT segment"));
+                }
+                16 + data[pos + 1..pos + 17].iter().map(|&n| n as usize).sum::<usize>()
+            };
+            if pos > data.len() {
+                return Err(invalid_format!("truncated table in marker 0x{:X} segment", marker));
+            }
+
+            if marker == 0xdb {
+                self.quantization = self.quantization.saturating_add(1);
+            } else {
+                self.huffman = self.huffman.saturating_add(1);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Walks the markers after the frame header until EOI, counting scans and tables, and
+/// looking for the first DNL marker and the last DRI marker before the first scan.
+///
+/// Truncated streams are tolerated: the walk stops at the end of the stream as if EOI
+/// was encountered.
+fn walk_scans<R: ?Sized + Skip>(r: &mut R, tables: &mut TableCounts) -> Result<(u16, Option<u16>, Option<u16>)> {
+    macro_rules! stop_on_eof {
+        ($e:expr) => {
+            match $e {
+                Ok(v) => v,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into())
+            }
+        }
+    }
+
+    let mut scan_count = 0u16;
+    let mut dnl_lines = None;
+    let mut restart_interval = None;
+
+    loop {
+        // entropy-coded data after scan headers is skipped by find_marker()
+        let marker = match find_marker(r, "EOI", |_| true) {
+            Ok(marker) => marker,
+            Err(Error::UnexpectedEndOfFile(..)) => break,
+            Err(e) => return Err(e)
+        };
+        if marker == 0xd9 {  // EOI
+            break;
+        }
+        if is_standalone_marker(marker) {
+            continue;
+        }
+
+        let size = stop_on_eof!(r.read_u16::<BigEndian>());
+        if size < 2 {
+            return Err(invalid_format!("invalid payload size of marker 0x{:X}: {}", marker, size));
+        }
+        let mut size = (size - 2) as u64;
+
+        match marker {
+            // SOS
+            0xda => scan_count = scan_count.saturating_add(1),
+            // DNL
+            0xdc if dnl_lines.is_none() => {
+                if size != 2 {
+                    return Err(invalid_format!("invalid DNL marker payload size: {}", size + 2));
+                }
+                dnl_lines = Some(stop_on_eof!(r.read_u16::<BigEndian>()));
+                size = 0;
+            }
+            // DRI, only the ones before the first scan are taken into account
+            0xdd if scan_count == 0 => {
+                if size != 2 {
+                    return Err(invalid_format!("invalid DRI marker payload size: {}", size + 2));
+                }
+                restart_interval = Some(stop_on_eof!(r.read_u16::<BigEndian>()));
+                size = 0;
+            }
+            // DQT and DHT
+            0xdb | 0xc4 => {
+                let mut data = vec![0u8; size as usize];
+                stop_on_eof!(r.read_exact(&mut data));
+                try!(tables.add(marker, &data));
+                size = 0;
+            }
+            _ => {}
+        }
+
+        if try!(r.skip(size)) != size {
+            break;
+        }
+    }
+
+    Ok((scan_count, dnl_lines, restart_interval))
+}
+
+/// Assembles ICC profile from APP2 chunks, each containing a sequence number, the total
+/// number of chunks and the chunk data.
+///
+/// Returns `None` if there are no chunks or if the chunks are inconsistent.
+fn assemble_icc_profile(mut chunks: Vec<(u8, u8, Vec<u8>)>) -> Option<Vec<u8>> {
+    chunks.sort_by_key(|&(seq, _, _)| seq);
+
+    let total = match chunks.first() {
+        Some(&(_, total, _)) if total as usize == chunks.len() => total,
+        _ => return None
+    };
+    // sequence numbers start from 1
+    let consistent = chunks.iter().enumerate()
+        .all(|(i, &(seq, count, _))| seq as usize == i + 1 && count == total);
+    if !consistent {
+        return None;
+    }
+
+    Some(chunks.into_iter().flat_map(|(_, _, data)| data).collect())
+}
+
+/// Finds the GUID of the extended XMP packet in the `xmpNote:HasExtendedXMP` property of
+/// the main XMP packet.
+///
+/// The property may be serialized either as an attribute or as an element.
+fn extended_xmp_guid(xmp: &str) -> Option<&[u8]> {
+    const PROPERTY: &'static str = "HasExtendedXMP";
+
+    let start = match xmp.find(PROPERTY) {
+        Some(start) => start + PROPERTY.len(),
+        None => return None
+    };
+    let value = xmp[start..].trim_start_matches(|c: char| c.is_whitespace() || "=\"'>".contains(c));
+
+    let guid = value.as_bytes();
+    if guid.len() >= 32 && guid[..32].iter().all(|b| b.is_ascii_hexdigit()) {
+        Some(&guid[..32])
+    } else {
+        None
+    }
+}
+
+/// Assembles extended XMP packet from APP1 chunks, each containing a GUID, the full length
+/// of the packet, the offset of the chunk and the chunk data.
+///
+/// Only the chunks with the given GUID are used. Returns `None` if there are no such chunks
+/// or if they do not cover the whole packet.
+fn assemble_extended_xmp(guid: &[u8], chunks: Vec<([u8; 32], u32, u32, Vec<u8>)>) -> Option<String> {
+    let mut chunks: Vec<_> = chunks.into_iter().filter(|&(ref g, _, _, _)| g[..] == *guid).collect();
+    chunks.sort_by_key(|&(_, _, offset, _)| offset);
+
+    let length = match chunks.first() {
+        Some(&(_, length, _, _)) => length,
+        None => return None
+    };
+
+    // chunks must be consistent and contiguous
+    let mut data = Vec::new();
+    for (_, chunk_length, offset, chunk) in chunks {
+        if chunk_length != length || offset as usize != data.len() {
+            return None;
+        }
+        data.extend(chunk);
+    }
+    if data.len() != length as usize {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+fn read_payload<R: ?Sized + BufRead>(r: &mut R, marker: u8, size: u16) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; size as usize];
+    try!(r.read_exact(&mut data).map_err(if_eof!(std, "when reading payload of marker 0x{:X}", marker)));
+    Ok(data)
+}
+
+fn is_standalone_marker(value: u8) -> bool {
+    match value {
+        // TEM, RST0-RST7, SOI and EOI don't have any payload
+        0x01 | 0xd0..=0xd9 => true,
+        _ => false
+    }
+}
+
 fn is_sof_marker(value: u8) -> bool {
     match value {
         // no 0xC4, 0xC8 and 0xCC, they are not SOF markers
@@ -4,3 +4,15 @@ pub mod jpeg;
 pub mod png;
 pub mod gif;
 pub mod webp;
+pub mod bmp;
+pub mod tiff;
+pub mod ico;
+pub mod pnm;
+pub mod exr;
+pub mod qoi;
+pub mod heif;
+pub mod svg;
+pub mod dds;
+pub mod psd;
+pub mod wbmp;
+pub mod jp2;
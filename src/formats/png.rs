@@ -1,17 +1,22 @@
 //! Metadata for PNG images.
 
-use std::io::BufRead;
+use std::io::{self, Read, BufRead};
 use std::fmt;
+use std::cmp;
+use std::hash::{Hash, Hasher};
 
-use byteorder::{ReadBytesExt, BigEndian};
+use byteorder::{ReadBytesExt, ByteOrder, BigEndian};
 
-use types::{Result, Dimensions};
+use types::{Result, Error, Dimensions, LoadOptions};
 use traits::LoadableMetadata;
+use utils::{ReadExt, f64_eq, hash_f64};
 
 /// Color type used in an image.
 ///
 /// These color types directly corresponds to those defined in PNG spec.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum ColorType {
     Grayscale,
     Rgb,
@@ -49,6 +54,27 @@ impl ColorType {
             _                  => None
         }
     }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ColorType::Grayscale      => CT_GRAYSCALE,
+            ColorType::Rgb            => CT_RGB,
+            ColorType::Indexed        => CT_INDEXED,
+            ColorType::GrayscaleAlpha => CT_GRAYSCALE_ALPHA,
+            ColorType::RgbAlpha       => CT_RGB_ALPHA
+        }
+    }
+}
+
+fn channels_number(color_type: u8) -> Option<u32> {
+    match color_type {
+        CT_GRAYSCALE => Some(1),
+        CT_GRAYSCALE_ALPHA => Some(2),
+        // significant bits of indexed images are specified for the palette RGB components
+        CT_RGB | CT_INDEXED => Some(3),
+        CT_RGB_ALPHA => Some(4),
+        _ => None
+    }
 }
 
 fn compute_color_depth(bit_depth: u8, color_type: u8) -> Option<u8> {
@@ -83,7 +109,8 @@ fn compute_color_depth(bit_depth: u8, color_type: u8) -> Option<u8> {
 ///
 /// > At present, only compression method 0 (deflate/inflate compression with a sliding window of
 /// at most 32768 bytes) is defined.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CompressionMethod {
     DeflateInflate
 }
@@ -111,7 +138,8 @@ impl CompressionMethod {
 ///
 /// > At present, only filter method 0 (adaptive filtering with five basic filter types) is
 /// defined.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FilterMethod {
     AdaptiveFiltering
 }
@@ -136,7 +164,8 @@ impl FilterMethod {
 /// Interlace method used in an image.
 ///
 /// PNG spec says that interlacing can be disabled or Adam7 interlace method can be used.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InterlaceMethod {
     Disabled,
     Adam7
@@ -161,72 +190,879 @@ impl InterlaceMethod {
     }
 }
 
+/// Unit of physical pixel dimensions.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PhysicalUnit {
+    /// Unit is unknown; pixel dimensions only define pixel aspect ratio.
+    Unknown,
+    /// Unit is the meter.
+    Meter
+}
+
+impl fmt::Display for PhysicalUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PhysicalUnit::Unknown => "Unknown",
+            PhysicalUnit::Meter => "Meter",
+        })
+    }
+}
+
+impl PhysicalUnit {
+    fn from_u8(n: u8) -> Option<PhysicalUnit> {
+        match n {
+            0 => Some(PhysicalUnit::Unknown),
+            1 => Some(PhysicalUnit::Meter),
+            _ => None
+        }
+    }
+}
+
+/// Physical pixel dimensions of an image, as stored in `pHYs` chunk.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PhysicalDimensions {
+    /// Number of pixels per unit along the X axis.
+    pub pixels_per_unit_x: u32,
+    /// Number of pixels per unit along the Y axis.
+    pub pixels_per_unit_y: u32,
+    /// Unit of measure.
+    pub unit: PhysicalUnit
+}
+
+impl PhysicalDimensions {
+    /// Returns horizontal and vertical resolution in dots per inch, if the unit is known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        const METERS_PER_INCH: f64 = 0.0254;
+        match self.unit {
+            PhysicalUnit::Meter => Some((
+                self.pixels_per_unit_x as f64 * METERS_PER_INCH,
+                self.pixels_per_unit_y as f64 * METERS_PER_INCH
+            )),
+            PhysicalUnit::Unknown => None
+        }
+    }
+
+    fn load<R: ?Sized + Read>(length: u32, r: &mut R) -> Result<PhysicalDimensions> {
+        if length != 9 {
+            return Err(invalid_format!("invalid pHYs chunk length: {}", length));
+        }
+
+        let x = try_if_eof!(r.read_u32::<BigEndian>(), "when reading pixels per unit along X axis");
+        let y = try_if_eof!(r.read_u32::<BigEndian>(), "when reading pixels per unit along Y axis");
+        let unit = try_if_eof!(r.read_u8(), "when reading physical unit specifier");
+
+        Ok(PhysicalDimensions {
+            pixels_per_unit_x: x,
+            pixels_per_unit_y: y,
+            unit: try!(
+                PhysicalUnit::from_u8(unit)
+                    .ok_or(invalid_format!("invalid physical unit specifier: {}", unit))
+            )
+        })
+    }
+}
+
+/// Rendering intent of an image, as stored in `sRGB` chunk.
+///
+/// Presence of `sRGB` chunk means that the image samples conform to the sRGB color space.
+/// Rendering intent defines how the colors should be mapped when the image is displayed
+/// in a different color space.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RenderingIntent {
+    /// For images preferring good adaptation to the output device gamut at the expense
+    /// of colorimetric accuracy, like photographs.
+    Perceptual,
+    /// For images requiring color appearance matching, like logos.
+    RelativeColorimetric,
+    /// For images preferring preservation of saturation at the expense of hue and lightness,
+    /// like charts and graphs.
+    Saturation,
+    /// For images requiring preservation of absolute colorimetry, like proofs.
+    AbsoluteColorimetric
+}
+
+impl fmt::Display for RenderingIntent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            RenderingIntent::Perceptual => "Perceptual",
+            RenderingIntent::RelativeColorimetric => "Relative colorimetric",
+            RenderingIntent::Saturation => "Saturation",
+            RenderingIntent::AbsoluteColorimetric => "Absolute colorimetric",
+        })
+    }
+}
+
+impl RenderingIntent {
+    fn from_u8(n: u8) -> Option<RenderingIntent> {
+        match n {
+            0 => Some(RenderingIntent::Perceptual),
+            1 => Some(RenderingIntent::RelativeColorimetric),
+            2 => Some(RenderingIntent::Saturation),
+            3 => Some(RenderingIntent::AbsoluteColorimetric),
+            _ => None
+        }
+    }
+
+    fn load<R: ?Sized + Read>(length: u32, r: &mut R) -> Result<RenderingIntent> {
+        if length != 1 {
+            return Err(invalid_format!("invalid sRGB chunk length: {}", length));
+        }
+
+        let intent = try_if_eof!(r.read_u8(), "when reading rendering intent");
+        RenderingIntent::from_u8(intent).ok_or(invalid_format!("invalid rendering intent: {}", intent))
+    }
+}
+
+/// Chromaticities of the white point and of the RGB primaries, as stored in `cHRM` chunk.
+///
+/// Each value is a CIE 1931 x or y coordinate; in the chunk, they are stored multiplied
+/// by 100000. Values are compared by their bit patterns, so this type implements `Eq`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Chromaticities {
+    /// White point x.
+    pub white_x: f64,
+    /// White point y.
+    pub white_y: f64,
+    /// Red x.
+    pub red_x: f64,
+    /// Red y.
+    pub red_y: f64,
+    /// Green x.
+    pub green_x: f64,
+    /// Green y.
+    pub green_y: f64,
+    /// Blue x.
+    pub blue_x: f64,
+    /// Blue y.
+    pub blue_y: f64
+}
+
+impl Chromaticities {
+    #[inline]
+    fn values(&self) -> [f64; 8] {
+        [self.white_x, self.white_y, self.red_x, self.red_y,
+         self.green_x, self.green_y, self.blue_x, self.blue_y]
+    }
+}
+
+impl PartialEq for Chromaticities {
+    fn eq(&self, other: &Chromaticities) -> bool {
+        self.values().iter().zip(other.values().iter()).all(|(&a, &b)| f64_eq(a, b))
+    }
+}
+
+impl Eq for Chromaticities {}
+
+impl Hash for Chromaticities {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for &v in &self.values() {
+            hash_f64(v, state);
+        }
+    }
+}
+
+impl Chromaticities {
+    fn load<R: ?Sized + Read>(length: u32, r: &mut R) -> Result<Chromaticities> {
+        if length != 32 {
+            return Err(invalid_format!("invalid cHRM chunk length: {}", length));
+        }
+
+        let mut values = [0f64; 8];
+        for v in &mut values {
+            *v = try_if_eof!(r.read_u32::<BigEndian>(), "when reading chromaticities") as f64 / 100000.0;
+        }
+
+        Ok(Chromaticities {
+            white_x: values[0],
+            white_y: values[1],
+            red_x: values[2],
+            red_y: values[3],
+            green_x: values[4],
+            green_y: values[5],
+            blue_x: values[6],
+            blue_y: values[7]
+        })
+    }
+}
+
+/// Animation parameters of an APNG image, as stored in `acTL` chunk.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ApngMetadata {
+    /// Number of frames in the animation.
+    pub num_frames: u32,
+    /// Number of times the animation should be played; 0 means infinite looping.
+    pub num_plays: u32
+}
+
+impl ApngMetadata {
+    fn load<R: ?Sized + Read>(length: u32, r: &mut R) -> Result<ApngMetadata> {
+        if length != 8 {
+            return Err(invalid_format!("invalid acTL chunk length: {}", length));
+        }
+
+        let num_frames = try_if_eof!(r.read_u32::<BigEndian>(), "when reading number of frames");
+        let num_plays = try_if_eof!(r.read_u32::<BigEndian>(), "when reading number of plays");
+
+        Ok(ApngMetadata {
+            num_frames: num_frames,
+            num_plays: num_plays
+        })
+    }
+}
+
+/// An embedded ICC color profile, as stored in `iCCP` chunk.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IccProfile {
+    /// Profile name.
+    pub name: String,
+    /// Profile data.
+    ///
+    /// If the `flate2` feature is enabled, this is the decompressed profile; otherwise,
+    /// it contains the raw zlib-compressed profile data. See `compressed` field.
+    pub data: Vec<u8>,
+    /// Whether `data` contains compressed data.
+    pub compressed: bool
+}
+
+impl IccProfile {
+    fn load<R: ?Sized + Read>(length: u32, r: &mut R, options: &LoadOptions) -> Result<IccProfile> {
+        try!(options.check_alloc(length as u64, "iCCP chunk"));
+        // the chunk may be shorter than its declared length, so don't preallocate it
+        let chunk = try!(r.read_to_vec_capped(length as usize));
+        if chunk.len() != length as usize {
+            return Err(unexpected_eof!("when reading iCCP chunk"));
+        }
+
+        let name_end = try!(
+            chunk.iter().position(|&b| b == 0)
+                .ok_or(invalid_format!("ICC profile name is not terminated"))
+        );
+        // profile name is encoded in Latin-1
+        let name = chunk[..name_end].iter().map(|&b| b as char).collect();
+
+        match chunk.get(name_end + 1) {
+            Some(&0) => {}
+            Some(&m) => return Err(invalid_format!("invalid ICC profile compression method: {}", m)),
+            None => return Err(unexpected_eof!("when reading ICC profile compression method"))
+        }
+
+        let (data, compressed) = try!(decompress(&chunk[name_end + 2..], options));
+        Ok(IccProfile {
+            name: name,
+            data: data,
+            compressed: compressed
+        })
+    }
+}
+
+/// Decompresses zlib-compressed data, if the `flate2` feature is enabled.
+///
+/// Returns the resulting data and a flag indicating whether it is still compressed.
+#[cfg(feature = "flate2")]
+fn decompress(data: &[u8], options: &LoadOptions) -> Result<(Vec<u8>, bool)> {
+    use std::io::Read;
+    use flate2::read::ZlibDecoder;
+
+    // read one byte more than allowed to detect overflowing data
+    let mut result = Vec::new();
+    let n = try!(ZlibDecoder::new(data).take(options.max_alloc as u64 + 1).read_to_end(&mut result)
+        .map_err(|e| invalid_format!("cannot decompress zlib data: {}", e)));
+    try!(options.check_alloc(n as u64, "decompressed data"));
+    Ok((result, false))
+}
+
+/// Decompresses zlib-compressed data, if the `flate2` feature is enabled.
+///
+/// Returns the resulting data and a flag indicating whether it is still compressed.
+#[cfg(not(feature = "flate2"))]
+fn decompress(data: &[u8], _options: &LoadOptions) -> Result<(Vec<u8>, bool)> {
+    Ok((data.to_vec(), true))
+}
+
+const SIGNATURE: &'static [u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A reader of raw PNG chunks.
+///
+/// This structure wraps a `Read` implementation and allows one to iterate over all chunks
+/// in a PNG image, including those which are not interpreted by this library, e.g. private
+/// chunks of some application. Like `common::tiff::TiffReader`, it is a low-level tool;
+/// `Metadata` is loaded on top of it.
+///
+/// Chunks are returned by the `next()` method. Each chunk borrows the reader, so it must be
+/// dropped before the next one is requested:
+///
+/// ```no_run
+/// # use std::io::Read;
+/// # use std::fs::File;
+/// use immeta::formats::png::ChunkReader;
+///
+/// let mut r = ChunkReader::new(File::open("kitty.png").unwrap()).unwrap();
+/// while let Some(chunk) = r.next() {
+///     let mut chunk = chunk.unwrap();
+///     if &chunk.chunk_type() == b"tEXt" {
+///         let mut data = Vec::new();
+///         chunk.read_to_end(&mut data).unwrap();
+///     }
+/// }
+/// ```
+pub struct ChunkReader<R: Read> {
+    source: R,
+    // offset of the next byte to be read from the source
+    offset: u64,
+    // offset of the current chunk
+    chunk_offset: u64,
+    // number of bytes remaining in the current chunk, including its CRC
+    remaining: u64,
+    finished: bool
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Wraps the provided `Read` implementation and returns a new PNG chunk reader.
+    ///
+    /// This method reads the PNG signature from the stream and checks that it is valid.
+    pub fn new(mut source: R) -> Result<ChunkReader<R>> {
+        let mut signature = [0u8; 8];
+        try_if_eof!(std, source.read_exact(&mut signature), "when reading PNG signature");
+
+        if &signature != SIGNATURE {
+            return Err(invalid_format!("invalid PNG header: {:?}", signature));
+        }
+
+        Ok(ChunkReader {
+            source: source,
+            offset: SIGNATURE.len() as u64,
+            chunk_offset: 0,
+            remaining: 0,
+            finished: false
+        })
+    }
+
+    /// Returns the next chunk of the image.
+    ///
+    /// Unread data of the previous chunk, if any, is skipped. `None` is returned after
+    /// `IEND` chunk, or if the stream ends exactly at a chunk boundary.
+    pub fn next(&mut self) -> Option<Result<Chunk<R>>> {
+        if self.finished {
+            return None;
+        }
+
+        match self.read_chunk_header() {
+            Ok(Some((chunk_type, length))) => Some(Ok(Chunk {
+                crc: crc32_update(!0, &chunk_type),
+                reader: self,
+                chunk_type: chunk_type,
+                length: length
+            })),
+            Ok(None) => None,
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn read_chunk_header(&mut self) -> Result<Option<([u8; 4], u32)>> {
+        if self.remaining > 0 {
+            let skipped = try!(self.source.skip_exact_0(self.remaining));
+            self.offset += skipped;
+            if skipped != self.remaining {
+                return Err(unexpected_eof!("when skipping chunk data").at_offset(self.chunk_offset));
+            }
+            self.remaining = 0;
+        }
+
+        self.chunk_offset = self.offset;
+
+        let mut header = [0u8; 8];
+        let n = try!(self.source.read_exact_0(&mut header));
+        self.offset += n as u64;
+        match n {
+            0 => return Ok(None),
+            8 => {}
+            _ => return Err(unexpected_eof!("when reading chunk header").at_offset(self.chunk_offset))
+        }
+
+        let length = BigEndian::read_u32(&header[..4]);
+        let mut chunk_type = [0u8; 4];
+        chunk_type.copy_from_slice(&header[4..]);
+
+        if &chunk_type == b"IEND" {
+            self.finished = true;
+        }
+        // chunk data is followed by 4 bytes of CRC
+        self.remaining = length as u64 + 4;
+
+        Ok(Some((chunk_type, length)))
+    }
+}
+
+/// A single chunk of a PNG image.
+///
+/// This structure implements `Read`, providing access to the chunk data. The chunk CRC
+/// is not checked unless `verify_crc()` is called.
+pub struct Chunk<'a, R: Read + 'a> {
+    reader: &'a mut ChunkReader<R>,
+    chunk_type: [u8; 4],
+    length: u32,
+    // CRC of the chunk type and data read so far
+    crc: u32
+}
+
+impl<'a, R: Read + 'a> Chunk<'a, R> {
+    /// Returns the four-byte type of this chunk, e.g. `IHDR`.
+    #[inline]
+    pub fn chunk_type(&self) -> [u8; 4] { self.chunk_type }
+
+    /// Returns the length of the chunk data.
+    #[inline]
+    pub fn length(&self) -> u32 { self.length }
+
+    /// Returns the offset of this chunk from the beginning of the stream.
+    #[inline]
+    pub fn offset(&self) -> u64 { self.reader.chunk_offset }
+
+    /// Returns `true` if the chunk is critical, i.e. it is required to display the image.
+    #[inline]
+    pub fn is_critical(&self) -> bool {
+        // critical chunks have uppercase first letter
+        self.chunk_type[0] & 0x20 == 0
+    }
+
+    /// Reads the rest of the chunk data and checks that the chunk CRC is correct.
+    ///
+    /// The remaining data is consumed, so the chunk can't be read after this method
+    /// is called.
+    pub fn verify_crc(mut self) -> Result<()> {
+        let offset = self.offset();
+
+        try!(io::copy(&mut self, &mut io::sink()));
+        // only the CRC field should remain unless the stream has ended
+        if self.reader.remaining != 4 {
+            return Err(unexpected_eof!("when reading chunk data").at_offset(offset));
+        }
+
+        let expected = try_if_eof!(self.reader.source.read_u32::<BigEndian>(), "when reading chunk CRC");
+        self.reader.offset += 4;
+        self.reader.remaining = 0;
+
+        let actual = !self.crc;
+        if actual != expected {
+            return Err(invalid_format!(
+                "invalid CRC of chunk {:?}: expected {:#010x}, computed {:#010x}",
+                self.chunk_type, expected, actual
+            ).at_offset(offset));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Read + 'a> Read for Chunk<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // do not read into the CRC field
+        let data_remaining = self.reader.remaining.saturating_sub(4);
+        let max = cmp::min(buf.len() as u64, data_remaining) as usize;
+
+        let n = try!(self.reader.source.read(&mut buf[..max]));
+        self.crc = crc32_update(self.crc, &buf[..n]);
+        self.reader.remaining -= n as u64;
+        self.reader.offset += n as u64;
+
+        Ok(n)
+    }
+}
+
+/// Updates a CRC-32 value, as used in PNG chunks, with the given data.
+///
+/// The initial value is `!0`, and the final CRC is the bitwise complement of the result.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+/// Represents the core metadata of a PNG image, as stored in its `IHDR` chunk.
+///
+/// This is returned by `Metadata::load_basic()`, which does not look at any chunks
+/// following `IHDR`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BasicMetadata {
+    /// Width and height.
+    pub dimensions: Dimensions,
+    /// Color type used in the image.
+    pub color_type: ColorType,
+    /// Color depth (bits per pixel) used in the image.
+    ///
+    /// This is the sum of the bit depths of all channels, e.g. 24 for an 8-bit RGB image.
+    /// For indexed images, it is the size of a palette index.
+    pub color_depth: u8,
+    /// Bit depth (bits per sample or per palette index), as stored in the `IHDR` chunk.
+    ///
+    /// Unlike `color_depth`, this does not depend on the number of channels, e.g. it is 8
+    /// for both 8-bit RGB and 8-bit grayscale images.
+    pub bit_depth: u8,
+    /// Compression method used in the image.
+    pub compression_method: CompressionMethod,
+    /// Preprocessing method used in the image.
+    pub filter_method: FilterMethod,
+    /// Transmission order used in the image.
+    pub interlace_method: InterlaceMethod
+}
+
+fn read_ihdr<R: Read>(chunks: &mut ChunkReader<R>) -> Result<BasicMetadata> {
+    let mut ihdr = match chunks.next() {
+        Some(chunk) => try!(chunk),
+        None => return Err(unexpected_eof!("when reading IHDR chunk"))
+    };
+
+    if ihdr.chunk_type() != *b"IHDR" {
+        // point to the chunk type field
+        return Err(invalid_format!("invalid PNG chunk: {:?}", ihdr.chunk_type())
+            .at_offset(ihdr.offset() + 4));
+    }
+
+    let r = &mut ihdr;
+    let width = try!(r.read_u32::<BigEndian>().map_err(if_eof!("when reading width")));
+    let height = try!(r.read_u32::<BigEndian>().map_err(if_eof!("when reading height")));
+    let bit_depth = try!(r.read_u8().map_err(if_eof!("when reading bit depth")));
+    let color_type = try!(r.read_u8().map_err(if_eof!("when reading color type")));
+    let compression_method = try!(r.read_u8().map_err(if_eof!("when reading compression method")));
+    let filter_method = try!(r.read_u8().map_err(if_eof!("when reading filter method")));
+    let interlace_method = try!(r.read_u8().map_err(if_eof!("when reading interlace method")));
+
+    Ok(BasicMetadata {
+        dimensions: try!(Dimensions::non_zero((width, height))),
+        color_type: try!(
+            ColorType::from_u8(color_type)
+                .ok_or(invalid_format!("invalid color type: {}", color_type))
+        ),
+        color_depth: try!(
+            compute_color_depth(bit_depth, color_type)
+                .ok_or(invalid_format!("invalid bit depth: {}", bit_depth))
+        ),
+        bit_depth: bit_depth,
+        compression_method: try!(
+            CompressionMethod::from_u8(compression_method)
+                .ok_or(invalid_format!("invalid compression method: {}", compression_method))
+        ),
+        filter_method: try!(
+            FilterMethod::from_u8(filter_method)
+                .ok_or(invalid_format!("invalid filter method: {}", filter_method))
+        ),
+        interlace_method: try!(
+            InterlaceMethod::from_u8(interlace_method)
+                .ok_or(invalid_format!("invalid interlace method: {}", interlace_method))
+        )
+    })
+}
+
 /// Represents metadata of a PNG image.
-#[derive(Clone, Eq, PartialEq, Debug)]
+///
+/// Floating-point values, like `gamma`, are compared by their bit patterns, so this type
+/// implements `Eq`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Metadata {
     /// Width and height.
     pub dimensions: Dimensions,
     /// Color type used in the image.
     pub color_type: ColorType,
     /// Color depth (bits per pixel) used in the image.
+    ///
+    /// This is the sum of the bit depths of all channels, e.g. 24 for an 8-bit RGB image.
+    /// For indexed images, it is the size of a palette index.
     pub color_depth: u8,
+    /// Bit depth (bits per sample or per palette index), as stored in the `IHDR` chunk.
+    ///
+    /// Unlike `color_depth`, this does not depend on the number of channels, e.g. it is 8
+    /// for both 8-bit RGB and 8-bit grayscale images.
+    pub bit_depth: u8,
     /// Compression method used in the image.
     pub compression_method: CompressionMethod,
     /// Preprocessing method used in the image.
     pub filter_method: FilterMethod,
     /// Transmission order used in the image.
-    pub interlace_method: InterlaceMethod
+    pub interlace_method: InterlaceMethod,
+    /// Physical pixel dimensions, if they are present in the image.
+    pub physical_dimensions: Option<PhysicalDimensions>,
+    /// Number of entries in the palette, if it is present in the image.
+    ///
+    /// Palette is required for indexed images, but it may also be present in truecolor
+    /// images as a suggestion for quantization.
+    pub palette_entries: Option<u16>,
+    /// Whether the image contains transparency information.
+    ///
+    /// This is true either if the color type has an alpha channel, or if the image contains
+    /// a `tRNS` chunk.
+    pub has_transparency: bool,
+    /// APNG animation parameters, if the image contains an `acTL` chunk.
+    pub animation: Option<ApngMetadata>,
+    /// Embedded ICC color profile, if it is present in the image.
+    pub icc_profile: Option<IccProfile>,
+    /// Image gamma, if the image contains a `gAMA` chunk.
+    ///
+    /// This is the stored value divided by 100000. Note that this is the gamma of the image
+    /// encoding, e.g. 0.45455 for images in sRGB color space.
+    pub gamma: Option<f64>,
+    /// Rendering intent, if the image contains an `sRGB` chunk.
+    pub rendering_intent: Option<RenderingIntent>,
+    /// Chromaticities of the white point and the primaries, if the image contains
+    /// a `cHRM` chunk.
+    pub chromaticities: Option<Chromaticities>,
+    /// Numbers of significant bits for each channel, if the image contains an `sBIT` chunk.
+    ///
+    /// The channels are ordered as in the image samples: gray, then alpha for grayscale images,
+    /// and red, green, blue, then alpha for truecolor images. For indexed images, the values
+    /// correspond to the red, green and blue components of the palette entries.
+    pub significant_bits: Option<Vec<u8>>
 }
 
-impl LoadableMetadata for Metadata {
-    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
-        let mut signature = [0u8; 8];
-        try!(r.read_exact(&mut signature).map_err(if_eof!(std, "when reading PNG signature")));
+impl Metadata {
+    /// Loads only the core metadata of a PNG image from the provided reader.
+    ///
+    /// Only the signature and `IHDR` chunk are read, and the reader is left right after
+    /// the `IHDR` chunk data. This is faster than `load()` for callers which only need
+    /// dimensions or color type, because ancillary chunks are not scanned.
+    pub fn load_basic<R: ?Sized + BufRead>(r: &mut R) -> Result<BasicMetadata> {
+        let mut chunks = try!(ChunkReader::new(r));
+        read_ihdr(&mut chunks)
+    }
 
-        if &signature != b"\x89PNG\r\n\x1a\n" {
-            return Err(invalid_format!("invalid PNG header: {:?}", signature));
+    /// Returns `true` if the image may contain transparent pixels.
+    ///
+    /// See `has_transparency` field description.
+    #[inline]
+    pub fn is_transparent(&self) -> bool {
+        self.has_transparency
+    }
+
+    /// Returns `true` if the image is an APNG image with more than one frame.
+    #[inline]
+    pub fn is_animated(&self) -> bool {
+        self.animation.map(|a| a.num_frames > 1).unwrap_or(false)
+    }
+
+    /// Checks that the header fields are consistent with each other.
+    ///
+    /// This verifies that the bit depth is allowed for the color type and agrees with the color
+    /// depth, that the palette
+    /// is present exactly when the color type permits it and is not larger than the bit
+    /// depth allows for indexed images, and that the numbers of significant bits do not
+    /// exceed the sample depth.
+    pub fn validate(&self) -> Result<()> {
+        let bit_depth = self.bit_depth;
+        match compute_color_depth(bit_depth, self.color_type.to_u8()) {
+            None => return Err(invalid_format!("bit depth {} is invalid for color type {}", bit_depth, self.color_type)),
+            Some(d) if d != self.color_depth =>
+                return Err(invalid_format!("color depth {} does not match bit depth {} for color type {}",
+                                           self.color_depth, bit_depth, self.color_type)),
+            _ => {}
         }
 
-        // chunk length
-        let _ = try!(r.read_u32::<BigEndian>().map_err(if_eof!("when reading chunk length")));
-        
-        let mut chunk_type = [0u8; 4];
-        try!(r.read_exact(&mut chunk_type).map_err(if_eof!(std, "when reading chunk type")));
+        match (self.color_type, self.palette_entries) {
+            (ColorType::Indexed, None) => return Err(invalid_format!("palette is missing in an indexed image")),
+            (ColorType::Indexed, Some(n)) if n > 1 << bit_depth =>
+                return Err(invalid_format!("palette of {} entries is too large for bit depth {}", n, bit_depth)),
+            (ColorType::Grayscale, Some(_)) | (ColorType::GrayscaleAlpha, Some(_)) =>
+                return Err(invalid_format!("palette is not allowed for color type {}", self.color_type)),
+            _ => {}
+        }
+
+        if let Some(ref bits) = self.significant_bits {
+            // palette entries always have 8-bit components
+            let sample_depth = if self.color_type == ColorType::Indexed { 8 } else { bit_depth };
+            if let Some(&b) = bits.iter().find(|&&b| b == 0 || b > sample_depth) {
+                return Err(invalid_format!("invalid number of significant bits {} for sample depth {}", b, sample_depth));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for Metadata {
+    fn eq(&self, other: &Metadata) -> bool {
+        let gamma_eq = match (self.gamma, other.gamma) {
+            (Some(a), Some(b)) => f64_eq(a, b),
+            (None, None) => true,
+            _ => false
+        };
+
+        self.dimensions == other.dimensions &&
+            self.color_type == other.color_type &&
+            self.color_depth == other.color_depth &&
+            self.bit_depth == other.bit_depth &&
+            self.compression_method == other.compression_method &&
+            self.filter_method == other.filter_method &&
+            self.interlace_method == other.interlace_method &&
+            self.physical_dimensions == other.physical_dimensions &&
+            self.palette_entries == other.palette_entries &&
+            self.has_transparency == other.has_transparency &&
+            self.animation == other.animation &&
+            self.icc_profile == other.icc_profile &&
+            gamma_eq &&
+            self.rendering_intent == other.rendering_intent &&
+            self.chromaticities == other.chromaticities &&
+            self.significant_bits == other.significant_bits
+    }
+}
+
+impl Eq for Metadata {}
 
-        if &chunk_type != b"IHDR" {
-            return Err(invalid_format!("invalid PNG chunk: {:?}", chunk_type));
+impl Hash for Metadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dimensions.hash(state);
+        self.color_type.hash(state);
+        self.color_depth.hash(state);
+        self.bit_depth.hash(state);
+        self.compression_method.hash(state);
+        self.filter_method.hash(state);
+        self.interlace_method.hash(state);
+        self.physical_dimensions.hash(state);
+        self.palette_entries.hash(state);
+        self.has_transparency.hash(state);
+        self.animation.hash(state);
+        self.icc_profile.hash(state);
+        match self.gamma {
+            Some(gamma) => {
+                1u8.hash(state);
+                hash_f64(gamma, state);
+            }
+            None => 0u8.hash(state)
         }
+        self.rendering_intent.hash(state);
+        self.chromaticities.hash(state);
+        self.significant_bits.hash(state);
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "PNG image, {}, {}, {} bpp", self.dimensions, self.color_type, self.color_depth));
+        if self.interlace_method == InterlaceMethod::Adam7 {
+            try!(f.write_str(", interlaced"));
+        }
+        if self.is_animated() {
+            try!(f.write_str(", animated"));
+        }
+        Ok(())
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// Signature and IHDR chunk.
+    #[inline]
+    fn min_header_len() -> usize {
+        33
+    }
+
+    #[inline]
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_with_options(r, &LoadOptions::default())
+    }
 
-        let width = try!(r.read_u32::<BigEndian>().map_err(if_eof!("when reading width")));
-        let height = try!(r.read_u32::<BigEndian>().map_err(if_eof!("when reading height")));
-        let bit_depth = try!(r.read_u8().map_err(if_eof!("when reading bit depth")));
-        let color_type = try!(r.read_u8().map_err(if_eof!("when reading color type")));
-        let compression_method = try!(r.read_u8().map_err(if_eof!("when reading compression method")));
-        let filter_method = try!(r.read_u8().map_err(if_eof!("when reading filter method")));
-        let interlace_method = try!(r.read_u8().map_err(if_eof!("when reading interlace method")));
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        let mut chunks = try!(ChunkReader::new(r));
+
+        let header = try!(read_ihdr(&mut chunks));
+        let color_type = header.color_type.to_u8();
+
+        let mut physical_dimensions = None;
+        let mut palette_entries = None;
+        let mut has_transparency_chunk = false;
+        let mut animation = None;
+        let mut icc_profile = None;
+        let mut gamma = None;
+        let mut rendering_intent = None;
+        let mut chromaticities = None;
+        let mut significant_bits = None;
+
+        // ancillary chunks we're interested in are located before the image data
+        loop {
+            let mut chunk = match chunks.next() {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(Error::UnexpectedEndOfFile(..))) | None if options.allow_truncated => break,
+                Some(Err(e)) => return Err(e),
+                None => return Err(unexpected_eof!("when reading chunk header"))
+            };
+            let chunk_offset = chunk.offset();
+            let length = chunk.length();
+
+            let result = (|| -> Result<bool> {
+                let r = &mut chunk;
+                match &r.chunk_type() {
+                    b"IDAT" | b"IEND" => return Ok(true),
+                    b"pHYs" => physical_dimensions = Some(try!(PhysicalDimensions::load(length, r))),
+                    b"iCCP" => icc_profile = Some(try!(IccProfile::load(length, r, options))),
+                    b"acTL" => animation = Some(try!(ApngMetadata::load(length, r))),
+                    b"sRGB" => rendering_intent = Some(try!(RenderingIntent::load(length, r))),
+                    b"cHRM" => chromaticities = Some(try!(Chromaticities::load(length, r))),
+                    b"sBIT" => {
+                        if channels_number(color_type) != Some(length) {
+                            return Err(invalid_format!("invalid sBIT chunk length for color type {}: {}",
+                                                       color_type, length));
+                        }
+                        let mut bits = vec![0; length as usize];
+                        try!(r.read_exact(&mut bits).map_err(if_eof!(std, "when reading significant bits")));
+                        significant_bits = Some(bits);
+                    }
+                    b"gAMA" => {
+                        if length != 4 {
+                            return Err(invalid_format!("invalid gAMA chunk length: {}", length));
+                        }
+                        let value = try_if_eof!(r.read_u32::<BigEndian>(), "when reading image gamma");
+                        gamma = Some(value as f64 / 100000.0);
+                    }
+                    b"PLTE" => {
+                        if length % 3 != 0 || length > 256*3 {
+                            return Err(invalid_format!("invalid PLTE chunk length: {}", length));
+                        }
+                        palette_entries = Some((length / 3) as u16);
+                    }
+                    b"tRNS" => has_transparency_chunk = true,
+                    // the rest of the chunk data is skipped by the chunk reader
+                    _ => {}
+                }
+                Ok(false)
+            })();
+
+            match result {
+                Ok(false) => {}
+                Ok(true) => break,
+                // a truncated chunk is ignored
+                Err(Error::UnexpectedEndOfFile(..)) if options.allow_truncated => break,
+                Err(e) => return Err(e.at_offset(chunk_offset))
+            }
+        }
 
         Ok(Metadata {
-            dimensions: (width, height).into(),
-            color_type: try!(
-                ColorType::from_u8(color_type)
-                    .ok_or(invalid_format!("invalid color type: {}", color_type))
-            ),
-            color_depth: try!(
-                compute_color_depth(bit_depth, color_type)
-                    .ok_or(invalid_format!("invalid bit depth: {}", bit_depth))
-            ),
-            compression_method: try!(
-                CompressionMethod::from_u8(compression_method)
-                    .ok_or(invalid_format!("invalid compression method: {}", compression_method))
-            ),
-            filter_method: try!(
-                FilterMethod::from_u8(filter_method)
-                    .ok_or(invalid_format!("invalid filter method: {}", filter_method))
-            ),
-            interlace_method: try!(
-                InterlaceMethod::from_u8(interlace_method)
-                    .ok_or(invalid_format!("invalid interlace method: {}", interlace_method))
-            )
+            dimensions: header.dimensions,
+            color_type: header.color_type,
+            color_depth: header.color_depth,
+            bit_depth: header.bit_depth,
+            compression_method: header.compression_method,
+            filter_method: header.filter_method,
+            interlace_method: header.interlace_method,
+            physical_dimensions: physical_dimensions,
+            palette_entries: palette_entries,
+            has_transparency: has_transparency_chunk ||
+                color_type == CT_GRAYSCALE_ALPHA || color_type == CT_RGB_ALPHA,
+            animation: animation,
+            icc_profile: icc_profile,
+            gamma: gamma,
+            rendering_intent: rendering_intent,
+            chromaticities: chromaticities,
+            significant_bits: significant_bits
         })
     }
 }
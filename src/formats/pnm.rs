@@ -0,0 +1,180 @@
+//! Metadata of PNM (PBM, PGM and PPM) images.
+
+use std::io::BufRead;
+use std::fmt;
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+
+/// Kind of a Netpbm image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Format {
+    /// Portable bitmap (PBM), black and white image.
+    Bitmap,
+    /// Portable graymap (PGM), grayscale image.
+    Graymap,
+    /// Portable pixmap (PPM), RGB image.
+    Pixmap
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Format::Bitmap => "Bitmap",
+            Format::Graymap => "Graymap",
+            Format::Pixmap => "Pixmap",
+        })
+    }
+}
+
+impl Format {
+    /// Returns the format and the binary flag for the second byte of the magic number.
+    fn from_magic(b: u8) -> Option<(Format, bool)> {
+        match b {
+            b'1' => Some((Format::Bitmap, false)),
+            b'2' => Some((Format::Graymap, false)),
+            b'3' => Some((Format::Pixmap, false)),
+            b'4' => Some((Format::Bitmap, true)),
+            b'5' => Some((Format::Graymap, true)),
+            b'6' => Some((Format::Pixmap, true)),
+            _ => None
+        }
+    }
+}
+
+/// Represents metadata of a PNM image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions.
+    pub dimensions: Dimensions,
+    /// Kind of the image.
+    pub format: Format,
+    /// Whether the raster is stored in binary ("raw") form rather than as ASCII decimals.
+    pub binary: bool,
+    /// Maximum sample value; not available for bitmaps, whose samples are always 0 or 1.
+    pub max_value: Option<u16>
+}
+
+impl Metadata {
+    /// Returns the number of bits needed to store a single sample of the image.
+    pub fn bits_per_sample(&self) -> u8 {
+        match self.max_value {
+            Some(max_value) => 16 - max_value.leading_zeros() as u8,
+            None => 1
+        }
+    }
+}
+
+#[inline]
+fn is_whitespace(b: u8) -> bool {
+    match b {
+        b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r' => true,
+        _ => false
+    }
+}
+
+fn peek_byte<R: ?Sized + BufRead>(r: &mut R) -> Result<Option<u8>> {
+    Ok(try!(r.fill_buf()).first().cloned())
+}
+
+/// Reads a decimal header value, skipping any whitespace and comments in front of it.
+///
+/// A comment starts with `#` and continues up to the end of the line. The value itself
+/// is terminated by the first byte which is not a digit; this byte is not consumed.
+fn read_header_value<R: ?Sized + BufRead>(r: &mut R, what: &str) -> Result<u32> {
+    loop {
+        match try!(peek_byte(r)) {
+            Some(b) if is_whitespace(b) => r.consume(1),
+            Some(b'#') => loop {
+                // comments may end with either CR or LF
+                match try!(peek_byte(r)) {
+                    Some(b'\n') | Some(b'\r') => break,
+                    Some(_) => r.consume(1),
+                    None => return Err(unexpected_eof!("when reading PNM {}", what))
+                }
+            },
+            Some(_) => break,
+            None => return Err(unexpected_eof!("when reading PNM {}", what))
+        }
+    }
+
+    let mut value: u32 = 0;
+    let mut digits = 0;
+    while let Some(b) = try!(peek_byte(r)) {
+        if !b.is_ascii_digit() {
+            if digits == 0 {
+                return Err(invalid_format!("invalid PNM {}: unexpected byte {:#04x}", what, b));
+            }
+            break;
+        }
+        value = try!(
+            value.checked_mul(10).and_then(|v| v.checked_add((b - b'0') as u32))
+                .ok_or(invalid_format!("PNM {} is too large", what))
+        );
+        digits += 1;
+        r.consume(1);
+    }
+
+    if digits == 0 {
+        return Err(unexpected_eof!("when reading PNM {}", what));
+    }
+
+    Ok(value)
+}
+
+impl LoadableMetadata for Metadata {
+    /// Magic number and dimensions, e.g. `P4 1 1`.
+    #[inline]
+    fn min_header_len() -> usize {
+        6
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut magic = [0u8; 2];
+        try_if_eof!(std, r.read_exact(&mut magic), "when reading PNM magic number");
+        let (format, binary) = match magic {
+            [b'P', n] => try!(
+                Format::from_magic(n).ok_or(invalid_format!("unknown PNM magic number: P{}", n as char))
+            ),
+            _ => return Err(invalid_format!("invalid PNM magic number: {:?}", magic))
+        };
+
+        // the magic number must be separated from the header values by whitespace
+        match try!(peek_byte(r)) {
+            Some(b) if is_whitespace(b) || b == b'#' => {}
+            Some(b) => return Err(invalid_format!("unexpected byte after PNM magic number: {:#04x}", b)),
+            None => return Err(unexpected_eof!("when reading PNM header"))
+        }
+
+        let width = try!(read_header_value(r, "width"));
+        let height = try!(read_header_value(r, "height"));
+
+        let max_value = match format {
+            Format::Bitmap => None,
+            _ => {
+                let max_value = try!(read_header_value(r, "maximum color value"));
+                if max_value == 0 || max_value > 65535 {
+                    return Err(invalid_format!("invalid PNM maximum color value: {}", max_value));
+                }
+                Some(max_value as u16)
+            }
+        };
+
+        // the last header value is followed by exactly one whitespace byte, and the raster
+        // starts right after it, so comments are not allowed here
+        match try!(peek_byte(r)) {
+            Some(b) if is_whitespace(b) => r.consume(1),
+            Some(b) => return Err(invalid_format!("PNM header is not terminated by whitespace: {:#04x}", b)),
+            None => return Err(unexpected_eof!("when reading PNM header"))
+        }
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width, height))),
+            format: format,
+            binary: binary,
+            max_value: max_value
+        })
+    }
+}
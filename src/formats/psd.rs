@@ -0,0 +1,136 @@
+//! Metadata of PSD (Adobe Photoshop) images.
+//!
+//! Both PSD documents and large documents (PSB) are supported.
+
+use std::io::BufRead;
+use std::fmt;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::BufReadExt;
+
+/// Color mode of a Photoshop document.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ColorMode {
+    Bitmap,
+    Grayscale,
+    Indexed,
+    Rgb,
+    Cmyk,
+    Multichannel,
+    Duotone,
+    Lab
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ColorMode::Bitmap => "Bitmap",
+            ColorMode::Grayscale => "Grayscale",
+            ColorMode::Indexed => "Indexed",
+            ColorMode::Rgb => "RGB",
+            ColorMode::Cmyk => "CMYK",
+            ColorMode::Multichannel => "Multichannel",
+            ColorMode::Duotone => "Duotone",
+            ColorMode::Lab => "Lab",
+        })
+    }
+}
+
+impl ColorMode {
+    fn from_u16(n: u16) -> Option<ColorMode> {
+        match n {
+            0 => Some(ColorMode::Bitmap),
+            1 => Some(ColorMode::Grayscale),
+            2 => Some(ColorMode::Indexed),
+            3 => Some(ColorMode::Rgb),
+            4 => Some(ColorMode::Cmyk),
+            7 => Some(ColorMode::Multichannel),
+            8 => Some(ColorMode::Duotone),
+            9 => Some(ColorMode::Lab),
+            _ => None
+        }
+    }
+}
+
+/// Represents metadata of a PSD image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions.
+    pub dimensions: Dimensions,
+    /// Format version: 1 for PSD and 2 for PSB (large document format).
+    pub version: u16,
+    /// Number of channels, including alpha channels.
+    pub channels: u16,
+    /// Number of bits per channel: 1, 8, 16 or 32.
+    pub depth: u16,
+    /// Color mode of the document.
+    pub color_mode: ColorMode
+}
+
+impl Metadata {
+    /// Returns `true` if this is a large document (PSB) image.
+    #[inline]
+    pub fn is_psb(&self) -> bool {
+        self.version == 2
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// File header.
+    #[inline]
+    fn min_header_len() -> usize {
+        26
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut signature = [0u8; 4];
+        try_if_eof!(std, r.read_exact(&mut signature), "when reading PSD signature");
+        if &signature != b"8BPS" {
+            return Err(invalid_format!("invalid PSD signature: {:?}", signature));
+        }
+
+        let version = try_if_eof!(r.read_u16::<BigEndian>(), "when reading PSD version");
+        if version != 1 && version != 2 {
+            return Err(invalid_format!("invalid PSD version: {}", version));
+        }
+
+        // reserved bytes
+        if try!(r.skip_exact(6)) != 6 {
+            return Err(unexpected_eof!("when reading PSD header"));
+        }
+
+        let channels = try_if_eof!(r.read_u16::<BigEndian>(), "when reading number of PSD channels");
+        if channels == 0 || channels > 56 {
+            return Err(invalid_format!("invalid number of PSD channels: {}", channels));
+        }
+
+        // note that the height comes first
+        let height = try_if_eof!(r.read_u32::<BigEndian>(), "when reading PSD image height");
+        let width = try_if_eof!(r.read_u32::<BigEndian>(), "when reading PSD image width");
+
+        let depth = try_if_eof!(r.read_u16::<BigEndian>(), "when reading PSD image depth");
+        match depth {
+            1 | 8 | 16 | 32 => {}
+            _ => return Err(invalid_format!("invalid PSD image depth: {}", depth))
+        }
+
+        let color_mode = try_if_eof!(r.read_u16::<BigEndian>(), "when reading PSD color mode");
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width, height))),
+            version: version,
+            channels: channels,
+            depth: depth,
+            color_mode: try!(
+                ColorMode::from_u16(color_mode)
+                    .ok_or(invalid_format!("invalid PSD color mode: {}", color_mode))
+            )
+        })
+    }
+}
@@ -0,0 +1,87 @@
+//! Metadata of QOI (Quite OK Image) images.
+
+use std::io::BufRead;
+use std::fmt;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+
+/// Color space of an image.
+///
+/// This is purely informative and does not affect how the image is encoded.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Colorspace {
+    /// sRGB color channels with linear alpha channel.
+    SrgbLinearAlpha,
+    /// All channels are linear.
+    Linear
+}
+
+impl fmt::Display for Colorspace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Colorspace::SrgbLinearAlpha => "sRGB with linear alpha",
+            Colorspace::Linear => "Linear",
+        })
+    }
+}
+
+impl Colorspace {
+    fn from_u8(n: u8) -> Option<Colorspace> {
+        match n {
+            0 => Some(Colorspace::SrgbLinearAlpha),
+            1 => Some(Colorspace::Linear),
+            _ => None
+        }
+    }
+}
+
+/// Represents metadata of a QOI image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions.
+    pub dimensions: Dimensions,
+    /// Number of color channels: 3 for RGB and 4 for RGBA images.
+    pub channels: u8,
+    /// Color space of the image.
+    pub colorspace: Colorspace
+}
+
+impl LoadableMetadata for Metadata {
+    /// File header.
+    #[inline]
+    fn min_header_len() -> usize {
+        14
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut magic = [0u8; 4];
+        try_if_eof!(std, r.read_exact(&mut magic), "when reading QOI magic number");
+        if &magic != b"qoif" {
+            return Err(invalid_format!("invalid QOI magic number: {:?}", magic));
+        }
+
+        let width = try_if_eof!(r.read_u32::<BigEndian>(), "when reading QOI image width");
+        let height = try_if_eof!(r.read_u32::<BigEndian>(), "when reading QOI image height");
+
+        let channels = try_if_eof!(r.read_u8(), "when reading number of QOI image channels");
+        if channels != 3 && channels != 4 {
+            return Err(invalid_format!("invalid number of QOI image channels: {}", channels));
+        }
+
+        let colorspace = try_if_eof!(r.read_u8(), "when reading QOI image colorspace");
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width, height))),
+            channels: channels,
+            colorspace: try!(
+                Colorspace::from_u8(colorspace)
+                    .ok_or(invalid_format!("invalid QOI image colorspace: {}", colorspace))
+            )
+        })
+    }
+}
@@ -0,0 +1,282 @@
+//! Metadata of SVG images.
+//!
+//! SVG images are vector images, but they may still declare their intrinsic size with
+//! `width` and `height` attributes of the root `<svg>` element, or via its `viewBox`.
+//! Only the root element tag is inspected, so the rest of the document is not read.
+
+use std::io::{BufRead, Read};
+use std::str;
+use std::hash::{Hash, Hasher};
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+use utils::{f64_eq, hash_f64};
+
+/// Maximum number of bytes which are scanned in search for the root `<svg>` element.
+const MAX_SCAN_LEN: u64 = 64 * 1024;
+
+/// Represents metadata of an SVG image.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Intrinsic image dimensions in pixels.
+    ///
+    /// These are computed from `width` and `height` attributes of the root element, with
+    /// the view box used to fill in a missing or relative size. `None` is returned if
+    /// the image is sized purely relatively, e.g. with percentages and without a view box,
+    /// or if either of the sizes is zero. Nonzero sizes below one pixel are rounded up.
+    pub dimensions: Option<Dimensions>,
+    /// View box of the image as `(min-x, min-y, width, height)`, if it is present.
+    ///
+    /// The values are compared by their bit patterns, so `Metadata` implements `Eq`.
+    pub view_box: Option<(f64, f64, f64, f64)>
+}
+
+impl PartialEq for Metadata {
+    fn eq(&self, other: &Metadata) -> bool {
+        self.dimensions == other.dimensions && match (self.view_box, other.view_box) {
+            (Some((x1, y1, w1, h1)), Some((x2, y2, w2, h2))) =>
+                f64_eq(x1, x2) && f64_eq(y1, y2) && f64_eq(w1, w2) && f64_eq(h1, h2),
+            (None, None) => true,
+            _ => false
+        }
+    }
+}
+
+impl Eq for Metadata {}
+
+impl Hash for Metadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dimensions.hash(state);
+        match self.view_box {
+            Some((x, y, w, h)) => {
+                1u8.hash(state);
+                for &v in &[x, y, w, h] {
+                    hash_f64(v, state);
+                }
+            }
+            None => 0u8.hash(state)
+        }
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// The smallest root element, `<svg/>`.
+    #[inline]
+    fn min_header_len() -> usize {
+        6
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let mut r = r.take(MAX_SCAN_LEN);
+        let mut buf = Vec::new();
+        let mut pos = 0;
+
+        // every markup construct preceding the root element ends with '>', so it is
+        // sufficient to read the input up to the next '>' until the root tag is complete
+        let attrs_range = loop {
+            if try!(r.read_until(b'>', &mut buf)) == 0 {
+                return Err(if buf.len() as u64 >= MAX_SCAN_LEN {
+                    invalid_format!("root svg element is not found in the first {} bytes", MAX_SCAN_LEN)
+                } else {
+                    unexpected_eof!("when searching for root svg element")
+                });
+            }
+            if let Some(range) = try!(find_root_tag(&buf, &mut pos)) {
+                break range;
+            }
+        };
+
+        let attrs = try!(parse_attributes(&buf[attrs_range.0..attrs_range.1]));
+        let attr = |name: &str| attrs.iter().find(|&&(n, _)| n == name).map(|&(_, v)| v);
+
+        let width = attr("width").and_then(parse_length);
+        let height = attr("height").and_then(parse_length);
+        let view_box = attr("viewBox").and_then(parse_view_box);
+
+        let dimensions = match (width, height, view_box) {
+            (Some(w), Some(h), _) => Some((w, h)),
+            (Some(w), None, Some((_, _, vw, vh))) if vw > 0.0 => Some((w, w * vh / vw)),
+            (None, Some(h), Some((_, _, vw, vh))) if vh > 0.0 => Some((h * vw / vh, h)),
+            (None, None, Some((_, _, vw, vh))) => Some((vw, vh)),
+            _ => None
+        };
+
+        Ok(Metadata {
+            dimensions: dimensions.and_then(|(w, h)| {
+                // sizes below one pixel are rounded up, but zero sizes can't be represented
+                if w > 0.0 && h > 0.0 {
+                    Dimensions::new(w.round().max(1.0) as u32, h.round().max(1.0) as u32)
+                } else {
+                    None
+                }
+            }),
+            view_box: view_box
+        })
+    }
+}
+
+#[inline]
+fn is_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
+}
+
+#[inline]
+fn find(buf: &[u8], pattern: &[u8]) -> Option<usize> {
+    buf.windows(pattern.len()).position(|w| w == pattern)
+}
+
+/// Returns the position of the first unquoted '>' character in the buffer.
+fn find_tag_end(buf: &[u8]) -> Option<usize> {
+    let mut quote = None;
+    for (i, &b) in buf.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b == b'>' => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Skips the prolog of an XML document and finds the attributes of the root element,
+/// which must be `svg`.
+///
+/// Returns the range of the attributes part of the root tag, or `None` if the root tag
+/// is not complete in the buffer yet. In the latter case `pos` is updated to point after
+/// the last complete construct, so the search may be resumed once more data is available.
+fn find_root_tag(buf: &[u8], pos: &mut usize) -> Result<Option<(usize, usize)>> {
+    if *pos == 0 && buf.starts_with(b"\xef\xbb\xbf") {
+        *pos = 3;
+    }
+    let mut i = *pos;
+    loop {
+        while i < buf.len() && is_whitespace(buf[i]) {
+            i += 1;
+        }
+
+        let rest = &buf[i..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        if rest[0] != b'<' {
+            return Err(invalid_format!("unexpected character data before root svg element"));
+        }
+
+        let skip = if rest.starts_with(b"<?") {
+            find(rest, b"?>").map(|n| n + 2)
+        } else if rest.starts_with(b"<!--") {
+            find(&rest[4..], b"-->").map(|n| n + 7)
+        } else if rest.starts_with(b"<!") {
+            // document type declaration, possibly with an internal subset
+            let mut depth = 0;
+            rest.iter().position(|&b| match b {
+                b'[' => { depth += 1; false }
+                b']' => { depth -= 1; false }
+                b'>' => depth <= 0,
+                _ => false
+            }).map(|n| n + 1)
+        } else {
+            let name_len = rest[1..].iter()
+                .position(|&b| is_whitespace(b) || b == b'/' || b == b'>')
+                .unwrap_or(rest.len() - 1);
+            let name = &rest[1..1 + name_len];
+            if name != b"svg" && !name.ends_with(b":svg") {
+                return Err(invalid_format!("root element is not svg: {}", String::from_utf8_lossy(name)));
+            }
+
+            let attrs_start = 1 + name_len;
+            return Ok(find_tag_end(&rest[attrs_start..]).map(|n| (i + attrs_start, i + attrs_start + n)));
+        };
+
+        match skip {
+            Some(n) => {
+                i += n;
+                *pos = i;
+            }
+            None => return Ok(None)
+        }
+    }
+}
+
+/// Parses attributes of a tag into a list of names and values, without unescaping them.
+fn parse_attributes(buf: &[u8]) -> Result<Vec<(&str, &str)>> {
+    let s = try!(str::from_utf8(buf).map_err(|e| invalid_format!("svg element is not valid UTF-8: {}", e)));
+
+    let mut attrs = Vec::new();
+    let mut rest = s;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return Ok(attrs);
+        }
+
+        let eq = try!(rest.find('=').ok_or(invalid_format!("attribute without a value in svg element")));
+        let name = rest[..eq].trim();
+        rest = rest[eq + 1..].trim_start();
+
+        let quote = match rest.chars().next() {
+            Some(c @ '"') | Some(c @ '\'') => c,
+            _ => return Err(invalid_format!("unquoted value of attribute {} in svg element", name))
+        };
+        let end = try!(rest[1..].find(quote).ok_or(invalid_format!("unterminated value of attribute {}", name)));
+        attrs.push((name, &rest[1..1 + end]));
+        rest = &rest[end + 2..];
+    }
+}
+
+/// Parses a number from the beginning of the string, returning it and the rest of the string.
+fn parse_number(s: &str) -> Option<(f64, &str)> {
+    let bytes = s.as_bytes();
+    let mut end = 0;
+    while end < bytes.len() {
+        match bytes[end] {
+            b'0'..=b'9' | b'.' => {}
+            b'+' | b'-' if end == 0 || bytes[end - 1] == b'e' || bytes[end - 1] == b'E' => {}
+            // an exponent must be followed by digits, otherwise it is a unit like "em" or "ex"
+            b'e' | b'E' if bytes[end + 1..].iter().skip_while(|&&b| b == b'+' || b == b'-')
+                               .next().map_or(false, |b| b.is_ascii_digit()) => {}
+            _ => break
+        }
+        end += 1;
+    }
+
+    s[..end].parse::<f64>().ok()
+        .and_then(|n| if n.is_finite() { Some((n, &s[end..])) } else { None })
+}
+
+/// Parses an absolute length into pixels, returning `None` for relative or invalid lengths.
+fn parse_length(s: &str) -> Option<f64> {
+    let (n, unit) = match parse_number(s.trim()) {
+        Some(r) => r,
+        None => return None
+    };
+    let scale = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        // percentages and font-relative units
+        _ => return None
+    };
+    if n >= 0.0 { Some(n * scale) } else { None }
+}
+
+fn parse_view_box(s: &str) -> Option<(f64, f64, f64, f64)> {
+    let mut values = s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .map(|v| match parse_number(v) {
+            Some((n, "")) => Some(n),
+            _ => None
+        });
+
+    match (values.next(), values.next(), values.next(), values.next(), values.next()) {
+        (Some(Some(x)), Some(Some(y)), Some(Some(w)), Some(Some(h)), None) if w >= 0.0 && h >= 0.0 =>
+            Some((x, y, w, h)),
+        _ => None
+    }
+}
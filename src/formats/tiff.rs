@@ -0,0 +1,307 @@
+//! Metadata of TIFF images.
+
+use std::io::{self, Read, BufRead, Seek, Cursor};
+use std::collections::HashSet;
+use std::fmt;
+
+use types::{Result, Dimensions, LoadOptions};
+use traits::LoadableMetadata;
+use common::tiff::{TiffReader, Ifd, Entry, EntryType, EntryTypeRepr, Tag, entry_types};
+use utils::ReadExt;
+
+/// Compression scheme used in an image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// CCITT Group 3 1-dimensional modified Huffman run-length encoding.
+    CcittRle,
+    /// CCITT Group 3 fax encoding.
+    CcittGroup3,
+    /// CCITT Group 4 fax encoding.
+    CcittGroup4,
+    /// Lempel-Ziv-Welch compression.
+    Lzw,
+    /// Old-style JPEG compression, as defined in TIFF 6.0.
+    OldJpeg,
+    /// JPEG compression, as defined in TIFF Technical Note #2.
+    Jpeg,
+    /// Deflate compression, using the code assigned by Adobe.
+    AdobeDeflate,
+    /// Deflate compression, using the legacy code.
+    Deflate,
+    /// PackBits run-length compression.
+    PackBits,
+    /// Unknown compression scheme.
+    Unknown(u16)
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Compression::None => f.write_str("None"),
+            Compression::CcittRle => f.write_str("CCITT modified Huffman RLE"),
+            Compression::CcittGroup3 => f.write_str("CCITT Group 3 fax"),
+            Compression::CcittGroup4 => f.write_str("CCITT Group 4 fax"),
+            Compression::Lzw => f.write_str("LZW"),
+            Compression::OldJpeg => f.write_str("JPEG (old-style)"),
+            Compression::Jpeg => f.write_str("JPEG"),
+            Compression::AdobeDeflate => f.write_str("Deflate (Adobe)"),
+            Compression::Deflate => f.write_str("Deflate"),
+            Compression::PackBits => f.write_str("PackBits"),
+            Compression::Unknown(n) => write!(f, "Unknown ({})", n),
+        }
+    }
+}
+
+impl Compression {
+    fn from_u16(n: u16) -> Compression {
+        match n {
+            1 => Compression::None,
+            2 => Compression::CcittRle,
+            3 => Compression::CcittGroup3,
+            4 => Compression::CcittGroup4,
+            5 => Compression::Lzw,
+            6 => Compression::OldJpeg,
+            7 => Compression::Jpeg,
+            8 => Compression::AdobeDeflate,
+            32946 => Compression::Deflate,
+            32773 => Compression::PackBits,
+            n => Compression::Unknown(n)
+        }
+    }
+}
+
+/// Color space of the image data.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum PhotometricInterpretation {
+    /// Bilevel or grayscale image where 0 is imaged as white.
+    WhiteIsZero,
+    /// Bilevel or grayscale image where 0 is imaged as black.
+    BlackIsZero,
+    /// RGB image.
+    Rgb,
+    /// Palette color image, colors are defined by a color map.
+    Palette,
+    /// Transparency mask for another image in the same file.
+    TransparencyMask,
+    /// Separated image, usually CMYK.
+    Cmyk,
+    /// YCbCr image.
+    YCbCr,
+    /// CIE L*a*b* image.
+    CieLab,
+    /// Unknown photometric interpretation.
+    Unknown(u16)
+}
+
+impl fmt::Display for PhotometricInterpretation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PhotometricInterpretation::WhiteIsZero => f.write_str("White is zero"),
+            PhotometricInterpretation::BlackIsZero => f.write_str("Black is zero"),
+            PhotometricInterpretation::Rgb => f.write_str("RGB"),
+            PhotometricInterpretation::Palette => f.write_str("Palette"),
+            PhotometricInterpretation::TransparencyMask => f.write_str("Transparency mask"),
+            PhotometricInterpretation::Cmyk => f.write_str("CMYK"),
+            PhotometricInterpretation::YCbCr => f.write_str("YCbCr"),
+            PhotometricInterpretation::CieLab => f.write_str("CIE L*a*b*"),
+            PhotometricInterpretation::Unknown(n) => write!(f, "Unknown ({})", n),
+        }
+    }
+}
+
+impl PhotometricInterpretation {
+    fn from_u16(n: u16) -> PhotometricInterpretation {
+        match n {
+            0 => PhotometricInterpretation::WhiteIsZero,
+            1 => PhotometricInterpretation::BlackIsZero,
+            2 => PhotometricInterpretation::Rgb,
+            3 => PhotometricInterpretation::Palette,
+            4 => PhotometricInterpretation::TransparencyMask,
+            5 => PhotometricInterpretation::Cmyk,
+            6 => PhotometricInterpretation::YCbCr,
+            8 => PhotometricInterpretation::CieLab,
+            n => PhotometricInterpretation::Unknown(n)
+        }
+    }
+}
+
+/// Contains metadata of a single image (page) of a TIFF document, i.e. of a single IFD.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Page {
+    /// Width and height.
+    pub dimensions: Dimensions,
+    /// Number of bits for each component of a pixel.
+    ///
+    /// This vector contains one item for each sample of a pixel.
+    pub bits_per_sample: Vec<u16>,
+    /// Number of components per pixel.
+    pub samples_per_pixel: u16,
+    /// Color space of the image data.
+    pub photometric_interpretation: PhotometricInterpretation,
+    /// Compression scheme used in the image.
+    pub compression: Compression
+}
+
+/// Represents metadata of a TIFF image.
+///
+/// A TIFF document may contain several images (pages), e.g. a multi-page fax. All of them
+/// are available in `pages`; the rest of the fields describe the first page.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Width and height.
+    pub dimensions: Dimensions,
+    /// Number of bits for each component of a pixel.
+    ///
+    /// This vector contains one item for each sample of a pixel.
+    pub bits_per_sample: Vec<u16>,
+    /// Number of components per pixel.
+    pub samples_per_pixel: u16,
+    /// Color space of the image data.
+    pub photometric_interpretation: PhotometricInterpretation,
+    /// Compression scheme used in the image.
+    pub compression: Compression,
+    /// All pages of the document, in the order of their IFDs; never empty.
+    pub pages: Vec<Page>
+}
+
+impl LoadableMetadata for Metadata {
+    /// Image file header and an IFD with width and height entries.
+    #[inline]
+    fn min_header_len() -> usize {
+        38
+    }
+
+    /// Loads TIFF metadata from the given input stream.
+    ///
+    /// TIFF documents can only be parsed with random access, so this method buffers the whole
+    /// stream into memory, up to the default allocation limit (see `LoadOptions`). Use
+    /// `load_from_seek()` to avoid this.
+    #[inline]
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_with_options(r, &LoadOptions::default())
+    }
+
+    #[inline]
+    fn load_from_seek<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_from_seek_with_options(r, &LoadOptions::default())
+    }
+
+    /// Loads TIFF metadata from the given input stream with the provided options.
+    ///
+    /// Like `load()`, this method buffers the whole stream into memory; the size of the stream
+    /// must not exceed the allocation limit.
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        let data = try!(r.read_to_vec_capped(options.max_alloc).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => invalid_format!("TIFF document exceeds the allocation limit of {} bytes",
+                                                          options.max_alloc),
+            _ => e.into()
+        }));
+        Metadata::load_from_seek_with_options(&mut Cursor::new(data), options)
+    }
+
+    fn load_from_seek_with_options<R: ?Sized + BufRead + Seek>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
+        let ifds = try!(TiffReader::with_options(r, options).ifds());
+
+        let mut pages = Vec::new();
+        let mut offsets = HashSet::new();
+        for ifd in &ifds {
+            let ifd = try!(ifd);
+            if !offsets.insert(ifd.offset()) {
+                return Err(invalid_format!("TIFF IFD chain contains a cycle at offset {}", ifd.offset()));
+            }
+            pages.push(try!(read_page(ifd)));
+        }
+
+        let first = match pages.first() {
+            Some(page) => page.clone(),
+            None => return Err(invalid_format!("TIFF document does not contain any images"))
+        };
+
+        Ok(Metadata {
+            dimensions: first.dimensions,
+            bits_per_sample: first.bits_per_sample,
+            samples_per_pixel: first.samples_per_pixel,
+            photometric_interpretation: first.photometric_interpretation,
+            compression: first.compression,
+            pages: pages
+        })
+    }
+}
+
+fn read_page<R: Read + Seek>(ifd: Ifd<R>) -> Result<Page> {
+    let mut width = None;
+    let mut height = None;
+    let mut bits_per_sample = None;
+    let mut samples_per_pixel = None;
+    let mut photometric_interpretation = None;
+    let mut compression = None;
+
+    for entry in ifd {
+        let entry = try!(entry);
+        match entry.tag_name() {
+            Tag::ImageWidth => width = try!(read_integer(&entry)),
+            Tag::ImageLength => height = try!(read_integer(&entry)),
+            Tag::BitsPerSample => bits_per_sample = match entry.all_values::<entry_types::Short>() {
+                Some(values) => Some(try!(values)),
+                None => return Err(invalid_format!("invalid type of bits per sample entry: {:?}",
+                                                   entry.entry_type()))
+            },
+            Tag::Compression =>
+                compression = try!(read_short(&entry)).map(Compression::from_u16),
+            Tag::PhotometricInterpretation =>
+                photometric_interpretation = try!(read_short(&entry)).map(PhotometricInterpretation::from_u16),
+            Tag::SamplesPerPixel => samples_per_pixel = try!(read_short(&entry)),
+            _ => {}
+        }
+    }
+
+    let width = try!(width.ok_or(invalid_format!("TIFF image width is missing")));
+    let height = try!(height.ok_or(invalid_format!("TIFF image length is missing")));
+    let photometric_interpretation = try!(
+        photometric_interpretation.ok_or(invalid_format!("TIFF photometric interpretation is missing"))
+    );
+
+    // default values are defined by the TIFF spec
+    let samples_per_pixel = samples_per_pixel.unwrap_or(1);
+    let bits_per_sample = bits_per_sample.unwrap_or_else(|| vec![1; samples_per_pixel as usize]);
+
+    Ok(Page {
+        dimensions: try!(Dimensions::non_zero((width, height))),
+        bits_per_sample: bits_per_sample,
+        samples_per_pixel: samples_per_pixel,
+        photometric_interpretation: photometric_interpretation,
+        compression: compression.unwrap_or(Compression::None)
+    })
+}
+
+fn first_value<T, R>(entry: &Entry<R>) -> Result<Option<T::Repr>>
+    where T: EntryTypeRepr, R: Read + Seek
+{
+    match entry.values::<T>().and_then(|mut values| values.next()) {
+        Some(value) => value.map(Some),
+        None => Ok(None)
+    }
+}
+
+fn read_short<R: Read + Seek>(entry: &Entry<R>) -> Result<Option<u16>> {
+    match entry.entry_type() {
+        EntryType::Short => first_value::<entry_types::Short, _>(entry),
+        t => Err(invalid_format!("invalid type of TIFF entry {}: {:?}", entry.tag(), t))
+    }
+}
+
+fn read_integer<R: Read + Seek>(entry: &Entry<R>) -> Result<Option<u32>> {
+    match entry.entry_type() {
+        EntryType::Short => first_value::<entry_types::Short, _>(entry).map(|v| v.map(|v| v as u32)),
+        EntryType::Long => first_value::<entry_types::Long, _>(entry),
+        t => Err(invalid_format!("invalid type of TIFF entry {}: {:?}", entry.tag(), t))
+    }
+}
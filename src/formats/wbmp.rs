@@ -0,0 +1,83 @@
+//! Metadata of WBMP (Wireless Bitmap) images.
+//!
+//! Only type 0 images, i.e. uncompressed monochrome bitmaps, are supported, which is
+//! the only type defined by the WAP specification. WBMP images do not have a distinctive
+//! signature, so they are never detected automatically by `immeta::load()`.
+
+use std::io::BufRead;
+
+use byteorder::ReadBytesExt;
+
+use types::{Result, Dimensions};
+use traits::LoadableMetadata;
+
+/// Represents metadata of a WBMP image.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Image dimensions.
+    pub dimensions: Dimensions
+}
+
+/// Reads a multi-byte integer, which is stored in big-endian groups of 7 bits, with the high
+/// bit of each byte indicating that more bytes follow.
+fn read_multibyte_int<R: ?Sized + BufRead>(r: &mut R, what: &str) -> Result<u32> {
+    let mut result = 0u32;
+    loop {
+        let b = try_if_eof!(r.read_u8(), "when reading {}", what);
+        if result > u32::max_value() >> 7 {
+            return Err(invalid_format!("{} is too large", what));
+        }
+        result = (result << 7) | (b & 0x7f) as u32;
+        if b & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+}
+
+impl LoadableMetadata for Metadata {
+    /// Type, fixed header and single-byte dimensions.
+    #[inline]
+    fn min_header_len() -> usize {
+        4
+    }
+
+    fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        let image_type = try!(read_multibyte_int(r, "WBMP image type"));
+        if image_type != 0 {
+            return Err(invalid_format!("unsupported WBMP image type: {}", image_type));
+        }
+
+        let fixed_header = try_if_eof!(r.read_u8(), "when reading WBMP fixed header");
+        if fixed_header & 0x80 != 0 {
+            return Err(invalid_format!("WBMP extension headers are not supported"));
+        }
+
+        let width = try!(read_multibyte_int(r, "WBMP image width"));
+        let height = try!(read_multibyte_int(r, "WBMP image height"));
+
+        Ok(Metadata {
+            dimensions: try!(Dimensions::non_zero((width, height)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_multibyte_int;
+
+    #[test]
+    fn test_read_multibyte_int() {
+        assert_eq!(read_multibyte_int(&mut &[0x00][..], "value").unwrap(), 0);
+        assert_eq!(read_multibyte_int(&mut &[0x7f, 0xff][..], "value").unwrap(), 0x7f);
+        assert_eq!(read_multibyte_int(&mut &[0x81, 0x00][..], "value").unwrap(), 0x80);
+        assert_eq!(read_multibyte_int(&mut &[0x82, 0x30][..], "value").unwrap(), 0x130);
+        assert_eq!(read_multibyte_int(&mut &[0x8f, 0xff, 0xff, 0xff, 0x7f][..], "value").unwrap(),
+                   u32::max_value());
+
+        // the continuation bit is set in the last byte
+        assert!(read_multibyte_int(&mut &[0x81][..], "value").is_err());
+        // the value does not fit into 32 bits
+        assert!(read_multibyte_int(&mut &[0x90, 0x80, 0x80, 0x80, 0x00][..], "value").is_err());
+    }
+}
@@ -1,53 +1,235 @@
-use std::io::BufRead;
+use std::io::{self, Read, BufRead};
 
-use types::{Result, Dimensions};
-use common::riff::{RiffReader, RiffChunk, ChunkId};
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use types::{Result, Error, Dimensions, LoadOptions};
+use common::riff::{RiffReader, RiffChunk, RiffListChunk, ChunkId};
 use traits::LoadableMetadata;
+use utils::{ReadExt, BitReader, BitOrder};
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Metadata {
     VP8(VP8Metadata),
     VP8L(VP8LMetadata),
     VP8X(VP8XMetadata)
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct VP8Metadata {
     pub version_number: u8,
     pub show_frame: bool,
     pub first_partition_len: u32,
-    pub frame: VP8Frame
+    pub frame: VP8Frame,
+    /// IDs and lengths of the top-level chunks encountered when loading the image, in order.
+    ///
+    /// See `Metadata::chunks()` for details.
+    pub chunks: Vec<(ChunkId, u32)>
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum VP8Frame {
     Key { dimensions: Dimensions, x_scale: u8, y_scale: u8 },
+    /// An interframe, which does not carry image dimensions.
+    ///
+    /// This variant is never produced by the loader, which rejects images that do not start
+    /// with a key frame.
     Inter
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct VP8LMetadata;
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VP8LMetadata {
+    pub dimensions: Dimensions,
+    pub has_alpha: bool,
+    pub version: u8,
+    /// IDs and lengths of the top-level chunks encountered when loading the image, in order.
+    ///
+    /// See `Metadata::chunks()` for details.
+    pub chunks: Vec<(ChunkId, u32)>
+}
+
+/// Contains metadata of an extended WebP image.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VP8XMetadata {
+    /// Canvas dimensions.
+    pub dimensions: Dimensions,
+    /// Whether the image contains an ICC profile.
+    pub has_icc_profile: bool,
+    /// Whether any of the frames of the image contains transparency information.
+    pub has_alpha: bool,
+    /// Whether the image contains EXIF metadata.
+    pub has_exif: bool,
+    /// Whether the image contains XMP metadata.
+    pub has_xmp: bool,
+    /// Whether the image is animated.
+    pub has_animation: bool,
+    /// Animation metadata, present only if the image is animated.
+    pub animation: Option<AnimationMetadata>,
+    /// Contents of the ICCP chunk, i.e. the ICC color profile, if it is present.
+    pub icc_profile: Option<Vec<u8>>,
+    /// Contents of the EXIF chunk, if it is present.
+    ///
+    /// This is a TIFF document which can be inspected with `common::tiff::TiffReader`.
+    /// The `Exif\0\0` prefix written by some encoders is removed.
+    pub exif: Option<Vec<u8>>,
+    /// Contents of the XMP chunk, if it is present.
+    pub xmp: Option<String>,
+    /// IDs and lengths of the top-level chunks encountered when loading the image, in order.
+    ///
+    /// See `Metadata::chunks()` for details.
+    pub chunks: Vec<(ChunkId, u32)>
+}
+
+/// Contains metadata of an animated WebP image.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnimationMetadata {
+    /// Default background color of the canvas, in `[Blue, Green, Red, Alpha]` byte order.
+    pub background_color: u32,
+    /// Number of times to loop the animation, zero means infinitely.
+    pub loop_count: u16,
+    /// Metadata for each frame of the animation.
+    pub frames: Vec<Frame>
+}
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct VP8XMetadata;
+/// Contains metadata of a single frame of an animated WebP image.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Frame {
+    /// Offset of the frame from the left boundary of the canvas.
+    pub left: u32,
+    /// Offset of the frame from the top boundary of the canvas.
+    pub top: u32,
+    /// Frame dimensions.
+    pub dimensions: Dimensions,
+    /// Time to wait before displaying the next frame, in milliseconds.
+    pub duration: u32,
+    /// How the frame should be blended with the canvas.
+    pub blending_method: BlendingMethod,
+    /// How the frame should be treated after it is displayed.
+    pub disposal_method: DisposalMethod
+}
+
+/// Describes how the pixels of a frame are combined with the pixels of the canvas.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BlendingMethod {
+    /// The frame is alpha-blended with the canvas.
+    AlphaBlending,
+    /// The frame overwrites the canvas.
+    NoBlending
+}
+
+/// Describes how the area of a frame is treated after the frame is displayed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DisposalMethod {
+    /// The canvas is left as is.
+    None,
+    /// The area of the frame is filled with the background color.
+    Background
+}
 
 const WEBP_CHUNK_TYPE: ChunkId = ChunkId([b'W', b'E', b'B', b'P']);
 const ALPH_CHUNK_ID: ChunkId   = ChunkId([b'A', b'L', b'P', b'H']);
 const VP8_CHUNK_ID: ChunkId    = ChunkId([b'V', b'P', b'8', b' ']);
 const VP8L_CHUNK_ID: ChunkId   = ChunkId([b'V', b'P', b'8', b'L']);
 const VP8X_CHUNK_ID: ChunkId   = ChunkId([b'V', b'P', b'8', b'X']);
+const ANIM_CHUNK_ID: ChunkId   = ChunkId([b'A', b'N', b'I', b'M']);
+const ANMF_CHUNK_ID: ChunkId   = ChunkId([b'A', b'N', b'M', b'F']);
+const ICCP_CHUNK_ID: ChunkId   = ChunkId([b'I', b'C', b'C', b'P']);
+const EXIF_CHUNK_ID: ChunkId   = ChunkId([b'E', b'X', b'I', b'F']);
+const XMP_CHUNK_ID: ChunkId    = ChunkId([b'X', b'M', b'P', b' ']);
+
+const EXIF_PREFIX: &'static [u8] = b"Exif\0\0";
 
 impl Metadata {
+    /// Returns image dimensions.
+    ///
+    /// Panics if the image is a VP8 interframe; loaded metadata never contains one.
     pub fn dimensions(&self) -> Dimensions {
         match *self {
             Metadata::VP8(VP8Metadata { frame: VP8Frame::Key { dimensions, .. }, .. }) => dimensions,
-            _ => unimplemented!()
+            Metadata::VP8L(VP8LMetadata { dimensions, .. }) => dimensions,
+            Metadata::VP8X(VP8XMetadata { dimensions, .. }) => dimensions,
+            Metadata::VP8(VP8Metadata { frame: VP8Frame::Inter, .. }) =>
+                panic!("VP8 interframe does not have dimensions")
+        }
+    }
+
+    /// Returns animation metadata if the image is animated.
+    ///
+    /// Only extended WebP images can be animated.
+    pub fn animation(&self) -> Option<&AnimationMetadata> {
+        match *self {
+            Metadata::VP8X(ref md) => md.animation.as_ref(),
+            _ => None
+        }
+    }
+
+    /// Returns the ICC color profile, if it is present.
+    ///
+    /// Only extended WebP images can contain ICC profiles.
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        match *self {
+            Metadata::VP8X(ref md) => md.icc_profile.as_ref().map(|v| &v[..]),
+            _ => None
+        }
+    }
+
+    /// Returns the contents of the EXIF chunk, if it is present.
+    ///
+    /// Only extended WebP images can contain EXIF metadata.
+    pub fn exif(&self) -> Option<&[u8]> {
+        match *self {
+            Metadata::VP8X(ref md) => md.exif.as_ref().map(|v| &v[..]),
+            _ => None
+        }
+    }
+
+    /// Returns IDs and lengths of the top-level chunks encountered when loading the image,
+    /// in order.
+    ///
+    /// This includes the chunks which are not recognized by this library, which is useful
+    /// for inspecting custom chunks. Reading stops as soon as all of the metadata is loaded,
+    /// so only the first chunk is listed for simple (VP8 and VP8L) images, and all chunks are
+    /// listed for extended images which have animation, ICC profile, EXIF or XMP flags set.
+    pub fn chunks(&self) -> &[(ChunkId, u32)] {
+        match *self {
+            Metadata::VP8(ref md) => &md.chunks,
+            Metadata::VP8L(ref md) => &md.chunks,
+            Metadata::VP8X(ref md) => &md.chunks
+        }
+    }
+
+    /// Returns the contents of the XMP chunk, if it is present.
+    ///
+    /// Only extended WebP images can contain XMP metadata.
+    pub fn xmp(&self) -> Option<&str> {
+        match *self {
+            Metadata::VP8X(ref md) => md.xmp.as_ref().map(|s| &s[..]),
+            _ => None
         }
     }
 }
 
 impl LoadableMetadata for Metadata {
+    /// RIFF header and the smallest (VP8L) image chunk.
+    #[inline]
+    fn min_header_len() -> usize {
+        25
+    }
+
+    #[inline]
     fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Metadata> {
+        Metadata::load_with_options(r, &LoadOptions::default())
+    }
+
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Metadata> {
         let mut rr = RiffReader::new(r);
 
         let mut root = try!(rr.root());
@@ -55,19 +237,33 @@ impl LoadableMetadata for Metadata {
             return Err(invalid_format!("invalid WEBP signature"));
         }
 
-        loop {
-            let mut chunk = match root.next() {
-                Some(c) => try!(c),
-                None => return Err(unexpected_eof!("when reading first WEBP chunk"))
-            };
+        let mut chunk = match root.next() {
+            Some(c) => try!(c),
+            None => return Err(unexpected_eof!("when reading first WEBP chunk"))
+        };
 
-            match chunk.chunk_id() {
-                VP8_CHUNK_ID => return read_vp8_chunk(&mut chunk).map(Metadata::VP8),
-                VP8L_CHUNK_ID => return Err(invalid_format!("unsupported (yet) VP8 chunk id")),
-                VP8X_CHUNK_ID => return Err(invalid_format!("unsupported (yet) VP8 chunk id")),
-                ALPH_CHUNK_ID => return Err(invalid_format!("unsupported (yet) VP8 chunk id")),
-                cid => return Err(invalid_format!("invalid WEBP chunk id: {}", cid))
+        let mut chunks = vec![(chunk.chunk_id(), chunk.len())];
+        match chunk.chunk_id() {
+            VP8_CHUNK_ID => {
+                let mut md = try!(read_vp8_chunk(&mut chunk));
+                md.chunks = chunks;
+                Ok(Metadata::VP8(md))
             }
+            VP8L_CHUNK_ID => {
+                let mut md = try!(read_vp8l_chunk(&mut chunk));
+                md.chunks = chunks;
+                Ok(Metadata::VP8L(md))
+            }
+            VP8X_CHUNK_ID => {
+                let mut md = try!(read_vp8x_chunk(&mut chunk));
+                if md.has_animation || md.has_icc_profile || md.has_exif || md.has_xmp {
+                    try!(read_extended_chunks(&mut root, &mut md, &mut chunks, options));
+                }
+                md.chunks = chunks;
+                Ok(Metadata::VP8X(md))
+            }
+            ALPH_CHUNK_ID => Err(invalid_format!("unsupported (yet) VP8 chunk id")),
+            cid => Err(invalid_format!("invalid WEBP chunk id: {}", cid))
         }
     }
 }
@@ -78,13 +274,6 @@ fn read_vp8_chunk(chunk: &mut RiffChunk) -> Result<VP8Metadata> {
     let mut hdr = [0u8; 3];
     try!(r.read_exact(&mut hdr).map_err(if_eof!(std, "when reading VP8 frame header")));
 
-    let mut result = VP8Metadata {
-        version_number: 0,
-        show_frame: false,
-        first_partition_len: 0,
-        frame: VP8Frame::Inter
-    };
-
     // bits of first three bytes:
     //    xxxsvvvf xxxxxxxx xxxxxxxx
     // where
@@ -93,42 +282,234 @@ fn read_vp8_chunk(chunk: &mut RiffChunk) -> Result<VP8Metadata> {
     //    s  --  show frame flag, 1 is display, 0 is don't display
     //    x  --  size of first data partition in bytes
 
-    let key_frame = hdr[0] & 1 == 0;
-    result.version_number = (hdr[0] >> 1) & 7;
-    result.show_frame = (hdr[0] >> 4) & 1 == 1;
-    result.first_partition_len = ((hdr[0] >> 5) as u32) | 
-                                 ((hdr[1] as u32) << 3) | 
-                                 ((hdr[2] as u32) << 11);
+    // the header is long enough for all of the fields, so unwrapping is safe
+    let mut bits = BitReader::new(&hdr, BitOrder::LsbFirst);
+    let key_frame = !bits.read_flag().unwrap();
+    let version_number = bits.read_bits(3).unwrap() as u8;
+    let show_frame = bits.read_flag().unwrap();
+    let first_partition_len = bits.read_bits(19).unwrap();
 
-    if key_frame {
-        let mut hdr = [0u8; 7];
-        try!(r.read_exact(&mut hdr).map_err(if_eof!(std, "when reading VP8 key frame header")));
+    // a still image consists of a single frame, which therefore must be a key frame
+    if !key_frame {
+        return Err(invalid_format!("VP8 image does not start with a key frame"));
+    }
 
-        // check magic value
-        if &hdr[..3] != &[0x9d, 0x01, 0x2a] {
-            return Err(invalid_format!("VP8 key frame magic code is invalid: {:?}", &hdr[..3]));
-        }
+    let mut hdr = [0u8; 7];
+    try!(r.read_exact(&mut hdr).map_err(if_eof!(std, "when reading VP8 key frame header")));
 
-        // bits of next four bytes:
-        //    wwwwwwww xxwwwwww hhhhhhhh yyhhhhhh
-        // where
-        //    x  --  horizontal scale
-        //    w  --  width
-        //    y  --  vertical scale
-        //    h  --  height
-
-        let width  = ((hdr[4] & 0x3f) as u32) << 8 | hdr[3] as u32;
-        let height = ((hdr[6] & 0x3f) as u32) << 8 | hdr[5] as u32;
-        let x_scale = hdr[4] >> 6;
-        let y_scale = hdr[6] >> 6;
-
-        result.frame = VP8Frame::Key {
-            dimensions: (width, height).into(),
+    // check magic value
+    if &hdr[..3] != &[0x9d, 0x01, 0x2a] {
+        return Err(invalid_format!("VP8 key frame magic code is invalid: {:?}", &hdr[..3]));
+    }
+
+    // bits of next four bytes:
+    //    wwwwwwww xxwwwwww hhhhhhhh yyhhhhhh
+    // where
+    //    x  --  horizontal scale
+    //    w  --  width
+    //    y  --  vertical scale
+    //    h  --  height
+
+    let mut bits = BitReader::new(&hdr[3..], BitOrder::LsbFirst);
+    let width = bits.read_bits(14).unwrap();
+    let x_scale = bits.read_bits(2).unwrap() as u8;
+    let height = bits.read_bits(14).unwrap();
+    let y_scale = bits.read_bits(2).unwrap() as u8;
+
+    Ok(VP8Metadata {
+        version_number: version_number,
+        show_frame: show_frame,
+        first_partition_len: first_partition_len,
+        frame: VP8Frame::Key {
+            dimensions: try!(Dimensions::non_zero((width, height))),
             x_scale: x_scale,
             y_scale: y_scale
+        },
+        chunks: Vec::new()
+    })
+}
+
+
+fn read_vp8l_chunk(chunk: &mut RiffChunk) -> Result<VP8LMetadata> {
+    let r = chunk.contents();
+
+    let signature = try_if_eof!(r.read_u8(), "when reading VP8L signature");
+    if signature != 0x2f {
+        return Err(invalid_format!("VP8L signature is invalid: 0x{:X}", signature));
+    }
+
+    let hdr = try_if_eof!(r.read_u32::<LittleEndian>(), "when reading VP8L header");
+
+    // bits of the header, starting from the least significant one:
+    //    wwwwwwwwwwwwww hhhhhhhhhhhhhh a vvv
+    // where
+    //    w  --  width minus one
+    //    h  --  height minus one
+    //    a  --  alpha is used flag
+    //    v  --  version number, 0 is the only one defined at the moment
+
+    let width = (hdr & 0x3fff) + 1;
+    let height = ((hdr >> 14) & 0x3fff) + 1;
+    let has_alpha = (hdr >> 28) & 1 == 1;
+    let version = (hdr >> 29) as u8;
+
+    Ok(VP8LMetadata {
+        dimensions: (width, height).into(),
+        has_alpha: has_alpha,
+        version: version,
+        chunks: Vec::new()
+    })
+}
+
+fn read_u24<R: ?Sized + Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 3];
+    try!(r.read_exact(&mut buf));
+    Ok(buf[0] as u32 | (buf[1] as u32) << 8 | (buf[2] as u32) << 16)
+}
+
+fn read_vp8x_chunk(chunk: &mut RiffChunk) -> Result<VP8XMetadata> {
+    let r = chunk.contents();
+
+    // bits of the first byte:
+    //    rrilexar
+    // where
+    //    r  --  reserved
+    //    i  --  ICC profile flag
+    //    l  --  alpha flag
+    //    e  --  EXIF metadata flag
+    //    x  --  XMP metadata flag
+    //    a  --  animation flag
+    // it is followed by three reserved bytes
+    let mut flags = [0u8; 4];
+    try!(r.read_exact(&mut flags).map_err(if_eof!(std, "when reading VP8X flags")));
+
+    let width = try!(read_u24(r).map_err(if_eof!(std, "when reading VP8X canvas width"))) + 1;
+    let height = try!(read_u24(r).map_err(if_eof!(std, "when reading VP8X canvas height"))) + 1;
+
+    Ok(VP8XMetadata {
+        dimensions: (width, height).into(),
+        has_icc_profile: flags[0] & 0b00100000 != 0,
+        has_alpha:       flags[0] & 0b00010000 != 0,
+        has_exif:        flags[0] & 0b00001000 != 0,
+        has_xmp:         flags[0] & 0b00000100 != 0,
+        has_animation:   flags[0] & 0b00000010 != 0,
+        animation: None,
+        icc_profile: None,
+        exif: None,
+        xmp: None,
+        chunks: Vec::new()
+    })
+}
+
+/// Reads the chunks following the VP8X chunk, i.e. the animation, color profile and
+/// metadata chunks, recording IDs and lengths of all of them.
+fn read_extended_chunks(root: &mut RiffListChunk, md: &mut VP8XMetadata, chunks: &mut Vec<(ChunkId, u32)>,
+                        options: &LoadOptions) -> Result<()> {
+    let mut parameters = None;
+    let mut frames = Vec::new();
+
+    while let Some(chunk) = root.next() {
+        let mut chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(Error::UnexpectedEndOfFile(..)) if options.allow_truncated => break,
+            Err(e) => return Err(e)
         };
+        chunks.push((chunk.chunk_id(), chunk.len()));
+        let result = (|| -> Result<()> {
+            match chunk.chunk_id() {
+                ANIM_CHUNK_ID if md.has_animation => {
+                    let r = chunk.contents();
+                    let background_color = try_if_eof!(
+                        r.read_u32::<LittleEndian>(), "when reading animation background color"
+                    );
+                    let loop_count = try_if_eof!(
+                        r.read_u16::<LittleEndian>(), "when reading animation loop count"
+                    );
+                    parameters = Some((background_color, loop_count));
+                }
+                ANMF_CHUNK_ID if md.has_animation => {
+                    let index = frames.len();
+                    frames.push(try!(read_anmf_chunk(index, &mut chunk)));
+                }
+                ICCP_CHUNK_ID if md.has_icc_profile => {
+                    md.icc_profile = Some(try!(read_chunk_data(&mut chunk, options)));
+                }
+                EXIF_CHUNK_ID if md.has_exif => {
+                    let mut data = try!(read_chunk_data(&mut chunk, options));
+                    if data.starts_with(EXIF_PREFIX) {
+                        data.drain(..EXIF_PREFIX.len());
+                    }
+                    md.exif = Some(data);
+                }
+                XMP_CHUNK_ID if md.has_xmp => {
+                    let data = try!(read_chunk_data(&mut chunk, options));
+                    md.xmp = Some(String::from_utf8_lossy(&data).into_owned());
+                }
+                _ => {}
+            }
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => {}
+            // a truncated chunk is ignored
+            Err(Error::UnexpectedEndOfFile(..)) if options.allow_truncated => break,
+            Err(e) => return Err(e)
+        }
     }
 
-    Ok(result)
+    if md.has_animation {
+        md.animation = match parameters {
+            Some((background_color, loop_count)) => Some(AnimationMetadata {
+                background_color: background_color,
+                loop_count: loop_count,
+                frames: frames
+            }),
+            // the chunk may be located after the end of a truncated stream
+            None if options.allow_truncated => None,
+            None => return Err(invalid_format!("ANIM chunk is missing in an animated WEBP image"))
+        };
+    }
+
+    Ok(())
+}
+
+fn read_chunk_data(chunk: &mut RiffChunk, options: &LoadOptions) -> Result<Vec<u8>> {
+    let id = chunk.chunk_id();
+    let len = chunk.len();
+    try!(options.check_alloc(len as u64, &format!("WEBP {} chunk", id)));
+    let data = try!(chunk.contents().read_to_vec());
+    if data.len() as u64 != len as u64 {
+        return Err(unexpected_eof!("when reading WEBP {} chunk", id));
+    }
+    Ok(data)
 }
 
+fn read_anmf_chunk(index: usize, chunk: &mut RiffChunk) -> Result<Frame> {
+    let r = chunk.contents();
+
+    let left = try!(read_u24(r).map_err(if_eof!(std, "when reading left offset of frame {}", index)));
+    let top = try!(read_u24(r).map_err(if_eof!(std, "when reading top offset of frame {}", index)));
+    let width = try!(read_u24(r).map_err(if_eof!(std, "when reading width of frame {}", index))) + 1;
+    let height = try!(read_u24(r).map_err(if_eof!(std, "when reading height of frame {}", index))) + 1;
+    let duration = try!(read_u24(r).map_err(if_eof!(std, "when reading duration of frame {}", index)));
+    let flags = try_if_eof!(r.read_u8(), "when reading flags of frame {}", index);
+
+    Ok(Frame {
+        // offsets are stored divided by two
+        left: left * 2,
+        top: top * 2,
+        dimensions: (width, height).into(),
+        duration: duration,
+        blending_method: if flags & 0b10 != 0 {
+            BlendingMethod::NoBlending
+        } else {
+            BlendingMethod::AlphaBlending
+        },
+        disposal_method: if flags & 0b01 != 0 {
+            DisposalMethod::Background
+        } else {
+            DisposalMethod::None
+        }
+    })
+}
@@ -1,12 +1,19 @@
-use std::io::{BufRead, Cursor, Seek, SeekFrom, BufReader};
+use std::io::{Read, BufRead, Cursor, Seek, SeekFrom, BufReader};
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::result;
+use std::fmt;
+use std::cmp;
+use std::u8;
 
-use types::{Result, Dimensions};
+use byteorder::{WriteBytesExt, LittleEndian};
+
+use types::{Result, Dimensions, Error, LoadOptions, Resolution};
 use traits::LoadableMetadata;
-use formats::{jpeg, png, gif, webp};
+use formats::{jpeg, png, gif, webp, bmp, tiff, ico, pnm, exr, qoi, heif, svg, dds, psd, wbmp, jp2};
 use generic::markers::MetadataMarker;
+use common::exif::Orientation;
+use utils::{ReadExt, PrefixCursor, BorrowedBufReader, fnv1a};
 
 /// Contains metadata marker types.
 ///
@@ -37,13 +44,13 @@ use generic::markers::MetadataMarker;
 /// `MetadataMarker::Metadata` associated type always points to concrete metadata type
 /// from one of `immeta::formats` submodules.
 pub mod markers {
-    use std::io::{BufRead, Seek};
+    use std::io::{Read, BufRead, Seek, BufReader};
     use std::path::Path;
     use std::result;
 
     use generic::GenericMetadata;
     use types::Result;
-    use formats::{jpeg, png, gif, webp};
+    use formats::{jpeg, png, gif, webp, bmp, tiff, ico, pnm, exr, qoi, heif, svg, dds, psd, wbmp, jp2};
 
     /// A marker trait for a specific metadata type.
     pub trait MetadataMarker {
@@ -120,6 +127,23 @@ pub mod markers {
         /// ```
         fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Self::Metadata>;
 
+        /// Attempts to load metadata for an image of a concrete type from the provided
+        /// unbuffered reader.
+        ///
+        /// Invokes `LoadableMetadata::load_from_read()` for the associated metadata type. Use
+        /// this method instead of calling `load_from_read()` on the metadata type directly.
+        ///
+        /// # Examples
+        ///
+        /// ```no_run
+        /// use std::fs::File;
+        /// use immeta::markers::{MetadataMarker, Png};
+        ///
+        /// let mut f = File::open("kitty.png").unwrap();
+        /// let metadata = Png::load_from_read(&mut f);
+        /// ```
+        fn load_from_read<R: ?Sized + Read>(r: &mut R) -> Result<Self::Metadata>;
+
         /// Attempts to load metadata for an image of a concrete type from the provided
         /// seekable reader.
         ///
@@ -169,6 +193,26 @@ pub mod markers {
         /// let metadata = Jpeg::load_from_buf(buf);
         /// ```
         fn load_from_buf(b: &[u8]) -> Result<Self::Metadata>;
+
+        /// Attempts to load metadata for an image of a concrete type from the provided
+        /// seekable reader and wraps it into the corresponding `GenericMetadata` variant.
+        ///
+        /// Unlike `immeta::load()`, this method does not try to detect the image format and
+        /// starts reading from the current position of the reader. This is useful when the
+        /// image format is known in advance, especially for formats without reliable magic
+        /// bytes, like JPEG.
+        ///
+        /// # Examples
+        ///
+        /// ```no_run
+        /// use std::fs::File;
+        /// use immeta::markers::{MetadataMarker, Jpeg};
+        ///
+        /// let mut f = File::open("kitty.jpg").unwrap();
+        /// let metadata = Jpeg::load_generic(&mut f).unwrap();
+        /// assert_eq!(metadata.mime_type(), "image/jpeg");
+        /// ```
+        fn load_generic<R: ?Sized + Read + Seek>(r: &mut R) -> Result<GenericMetadata>;
     }
 
     macro_rules! impl_metadata_marker {
@@ -199,6 +243,11 @@ pub mod markers {
                     $crate::traits::LoadableMetadata::load(r)
                 }
 
+                #[inline]
+                fn load_from_read<R: ?Sized + Read>(r: &mut R) -> Result<$mtpe> {
+                    $crate::traits::LoadableMetadata::load_from_read(r)
+                }
+
                 #[inline]
                 fn load_from_seek<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<$mtpe> {
                     $crate::traits::LoadableMetadata::load_from_seek(r)
@@ -213,6 +262,21 @@ pub mod markers {
                 fn load_from_buf(b: &[u8]) -> Result<$mtpe> {
                     $crate::traits::LoadableMetadata::load_from_buf(b)
                 }
+
+                #[inline]
+                fn load_generic<R: ?Sized + Read + Seek>(r: &mut R) -> Result<GenericMetadata> {
+                    let mut r = BufReader::new(r);
+                    <$mtpe as $crate::traits::LoadableMetadata>::load_from_seek(&mut r)
+                        .map($crate::generic::GenericMetadata::$gvar)
+                        .and_then($crate::generic::reject_unsized)
+                }
+            }
+
+            impl From<$mtpe> for GenericMetadata {
+                #[inline]
+                fn from(md: $mtpe) -> GenericMetadata {
+                    $crate::generic::GenericMetadata::$gvar(md)
+                }
             }
         }
     }
@@ -221,40 +285,314 @@ pub mod markers {
     impl_metadata_marker! { Png, Png, png::Metadata }
     impl_metadata_marker! { Gif, Gif, gif::Metadata }
     impl_metadata_marker! { Webp, Webp, webp::Metadata }
+    impl_metadata_marker! { Bmp, Bmp, bmp::Metadata }
+    impl_metadata_marker! { Tiff, Tiff, tiff::Metadata }
+    impl_metadata_marker! { Ico, Ico, ico::Metadata }
+    impl_metadata_marker! { Pnm, Pnm, pnm::Metadata }
+    impl_metadata_marker! { Exr, Exr, exr::Metadata }
+    impl_metadata_marker! { Qoi, Qoi, qoi::Metadata }
+    impl_metadata_marker! { Heif, Heif, heif::Metadata }
+    impl_metadata_marker! { Svg, Svg, svg::Metadata }
+    impl_metadata_marker! { Dds, Dds, dds::Metadata }
+    impl_metadata_marker! { Psd, Psd, psd::Metadata }
+    impl_metadata_marker! { Wbmp, Wbmp, wbmp::Metadata }
+    impl_metadata_marker! { Jp2, Jp2, jp2::Metadata }
 }
 
 /// Represents metadata loaded from a file whose format was determined automatically.
 ///
 /// Values of this type are obtained via `immeta::load()` function and its derivatives.
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// They can also be created from concrete metadata values with `From`, which is the reverse
+/// of `MetadataMarker::from_generic()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum GenericMetadata {
     Png(png::Metadata),
     Gif(gif::Metadata),
     Jpeg(jpeg::Metadata),
-    Webp(webp::Metadata)
+    Webp(webp::Metadata),
+    Bmp(bmp::Metadata),
+    Tiff(tiff::Metadata),
+    Ico(ico::Metadata),
+    Pnm(pnm::Metadata),
+    Exr(exr::Metadata),
+    Qoi(qoi::Metadata),
+    Heif(heif::Metadata),
+    Svg(svg::Metadata),
+    Dds(dds::Metadata),
+    Psd(psd::Metadata),
+    Wbmp(wbmp::Metadata),
+    Jp2(jp2::Metadata)
 }
 
 impl GenericMetadata {
     /// Returns image dimensions from the contained metadata.
+    ///
+    /// SVG images which do not have an intrinsic size are rejected by the generic loaders, so
+    /// zero dimensions are only returned for such metadata wrapped with `From` explicitly.
     pub fn dimensions(&self) -> Dimensions {
         match *self {
             GenericMetadata::Png(ref md) => md.dimensions,
             GenericMetadata::Gif(ref md) => md.dimensions,
             GenericMetadata::Jpeg(ref md) => md.dimensions,
-            GenericMetadata::Webp(ref md) => md.dimensions()
+            GenericMetadata::Webp(ref md) => md.dimensions(),
+            GenericMetadata::Bmp(ref md) => md.dimensions,
+            GenericMetadata::Tiff(ref md) => md.dimensions,
+            GenericMetadata::Ico(ref md) => md.largest(),
+            GenericMetadata::Pnm(ref md) => md.dimensions,
+            GenericMetadata::Exr(ref md) => md.dimensions,
+            GenericMetadata::Qoi(ref md) => md.dimensions,
+            GenericMetadata::Heif(ref md) => md.dimensions,
+            GenericMetadata::Svg(ref md) => md.dimensions.unwrap_or(Dimensions { width: 0, height: 0 }),
+            GenericMetadata::Dds(ref md) => md.dimensions,
+            GenericMetadata::Psd(ref md) => md.dimensions,
+            GenericMetadata::Wbmp(ref md) => md.dimensions,
+            GenericMetadata::Jp2(ref md) => md.dimensions
+        }
+    }
+
+    /// Returns color depth of the image, in bits per pixel, if it is available.
+    ///
+    /// The exact meaning of this value depends on the image format:
+    ///
+    ///   * for PNG, it is the `color_depth` field;
+    ///   * for JPEG, it is the `sample_precision` field, i.e. the number of bits per sample;
+    ///   * for GIF, it is the number of bits per primary color of the original image,
+    ///     derived from the `color_resolution` field;
+    ///   * for BMP, it is the `bit_depth` field;
+    ///   * for TIFF, it is the sum of bits of all samples of a pixel;
+    ///   * for ICO, it is the maximum bit count among all directory entries;
+    ///   * for PNM, it is the number of bits per sample multiplied by the number of samples
+    ///     per pixel;
+    ///   * for OpenEXR, it is the sum of sample sizes of all channels;
+    ///   * for QOI, it is 8 bits multiplied by the number of channels;
+    ///   * for DDS, it is the `bit_count` field, which is only present for uncompressed images;
+    ///   * for PSD, it is the `depth` field multiplied by the number of channels;
+    ///   * for WBMP, it is always 1.
+    ///
+    /// `None` is returned for WebP, HEIF, AVIF, SVG and JPEG 2000 images, block-compressed DDS images and
+    /// cursors, for which this information is not available or is not extracted.
+    pub fn color_depth(&self) -> Option<u8> {
+        match *self {
+            GenericMetadata::Png(ref md) => Some(md.color_depth),
+            GenericMetadata::Gif(ref md) => Some(md.color_resolution.trailing_zeros() as u8),
+            GenericMetadata::Jpeg(ref md) => Some(md.sample_precision),
+            GenericMetadata::Webp(_) => None,
+            GenericMetadata::Bmp(ref md) => if md.bit_depth <= u8::MAX as u16 {
+                Some(md.bit_depth as u8)
+            } else {
+                None
+            },
+            GenericMetadata::Tiff(ref md) => {
+                let total: u32 = md.bits_per_sample.iter().map(|&b| b as u32).sum();
+                if total <= u8::MAX as u32 { Some(total as u8) } else { None }
+            }
+            GenericMetadata::Ico(ref md) => md.entries.iter()
+                .filter_map(|e| e.bit_count)
+                .max()
+                .and_then(|b| if b <= u8::MAX as u16 { Some(b as u8) } else { None }),
+            GenericMetadata::Pnm(ref md) => Some(match md.format {
+                pnm::Format::Pixmap => md.bits_per_sample() * 3,
+                _ => md.bits_per_sample()
+            }),
+            GenericMetadata::Exr(ref md) => {
+                let total: u32 = md.channels.iter().map(|c| c.pixel_type.bits() as u32).sum();
+                if total <= u8::MAX as u32 { Some(total as u8) } else { None }
+            }
+            GenericMetadata::Qoi(ref md) => Some(md.channels * 8),
+            GenericMetadata::Heif(_) => None,
+            GenericMetadata::Svg(_) => None,
+            GenericMetadata::Dds(ref md) => md.bit_count
+                .and_then(|b| if b <= u8::MAX as u32 { Some(b as u8) } else { None }),
+            GenericMetadata::Psd(ref md) => {
+                let total = md.depth as u32 * md.channels as u32;
+                if total <= u8::MAX as u32 { Some(total as u8) } else { None }
+            }
+            GenericMetadata::Wbmp(_) => Some(1),
+            GenericMetadata::Jp2(_) => None
+        }
+    }
+
+    /// Returns the number of frames in the image.
+    ///
+    /// For GIF images, this is the number of image descriptor blocks; for WebP and APNG
+    /// images, this is the number of animation frames. All other images, including JPEG
+    /// images, are always treated as consisting of a single frame.
+    ///
+    /// If GIF metadata was loaded with `LoadOptions::max_gif_frames` and the limit was hit
+    /// (see `gif::Metadata::has_more_frames`), this is only a lower bound.
+    pub fn frame_count(&self) -> usize {
+        match *self {
+            GenericMetadata::Gif(ref md) => md.frames_number(),
+            GenericMetadata::Webp(ref md) => md.animation().map(|a| a.frames.len()).unwrap_or(1),
+            GenericMetadata::Png(ref md) => md.animation.map(|a| a.num_frames as usize).unwrap_or(1),
+            _ => 1
+        }
+    }
+
+    /// Returns `true` if the image is animated, `false` otherwise.
+    ///
+    /// An image is considered animated if it has more than one frame, see `frame_count()`.
+    /// Consequently, JPEG images are never considered animated. For GIF images, frames
+    /// skipped because of `LoadOptions::max_gif_frames` are taken into account, see
+    /// `gif::Metadata::is_animated()`.
+    pub fn is_animated(&self) -> bool {
+        match *self {
+            GenericMetadata::Gif(ref md) => md.is_animated(),
+            _ => self.frame_count() > 1
+        }
+    }
+
+    /// Returns `true` if the image may contain transparent pixels.
+    ///
+    /// This is the case for PNG images with an alpha channel or a `tRNS` chunk
+    /// (see `png::Metadata::is_transparent()`), GIF images with a transparent color index
+    /// in any of the frames, and WebP images whose VP8L or VP8X header reports alpha.
+    /// `false` is returned for all other images.
+    pub fn has_alpha(&self) -> bool {
+        match *self {
+            GenericMetadata::Png(ref md) => md.is_transparent(),
+            GenericMetadata::Gif(ref md) => md.frames().iter().any(|f| f.transparent_color_index.is_some()),
+            GenericMetadata::Webp(webp::Metadata::VP8L(ref md)) => md.has_alpha,
+            GenericMetadata::Webp(webp::Metadata::VP8X(ref md)) => md.has_alpha,
+            _ => false
+        }
+    }
+
+    /// Returns image dimensions as the image should be displayed.
+    ///
+    /// This method only differs from `dimensions()` for JPEG images whose EXIF orientation
+    /// tag requires a rotation by 90 or 270 degrees (orientations 5 to 8), in which case width
+    /// and height are swapped, and for GIF images with non-square pixels, in which case the
+    /// width is scaled according to the pixel aspect ratio (see `gif::Metadata::display_dimensions()`).
+    /// For all other images, including JPEG images without EXIF metadata, it returns exactly
+    /// the same value as `dimensions()`.
+    pub fn display_dimensions(&self) -> Dimensions {
+        let dimensions = self.dimensions();
+        match *self {
+            GenericMetadata::Jpeg(ref md) => match md.orientation() {
+                Some(Orientation::Transpose) | Some(Orientation::Rotate90) |
+                Some(Orientation::Transverse) | Some(Orientation::Rotate270) => Dimensions {
+                    width: dimensions.height,
+                    height: dimensions.width
+                },
+                _ => dimensions
+            },
+            GenericMetadata::Gif(ref md) => md.display_dimensions(),
+            _ => dimensions
+        }
+    }
+
+    /// Returns image resolution in dots per inch, if it is available.
+    ///
+    /// For PNG images, the resolution is obtained from the `pHYs` chunk
+    /// (see `png::PhysicalDimensions::dpi()`). For JPEG images, the JFIF density is used
+    /// if its units are known (see `jpeg::JfifDensity::dpi()`), and EXIF XResolution and
+    /// YResolution entries are used otherwise (see `common::exif::Exif::dpi()`). `None` is returned for
+    /// all other images and when the image does not specify its resolution in absolute units.
+    pub fn resolution(&self) -> Option<Resolution> {
+        let dpi = match *self {
+            GenericMetadata::Png(ref md) => md.physical_dimensions.and_then(|pd| pd.dpi()),
+            GenericMetadata::Jpeg(ref md) => md.density.and_then(|d| d.dpi())
+                .or_else(|| md.exif.as_ref().and_then(|e| e.dpi())),
+            _ => None
+        };
+        dpi.map(Resolution::from)
+    }
+
+    /// Checks that the header fields of the contained metadata are consistent with each other.
+    ///
+    /// Loaders are lenient and may accept corrupt images whose metadata is structurally valid
+    /// but makes no sense. This method allows rejecting such images. Currently the following
+    /// checks are performed:
+    ///
+    ///   * for PNG, see `png::Metadata::validate()`;
+    ///   * for GIF, see `gif::Metadata::validate()`.
+    ///
+    /// Metadata of other formats is always considered valid. An `Error::InvalidFormat` error
+    /// describing the problem is returned if a check fails.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            GenericMetadata::Png(ref md) => md.validate(),
+            GenericMetadata::Gif(ref md) => md.validate(),
+            _ => Ok(())
+        }
+    }
+
+    /// Returns the image format of the contained metadata.
+    pub fn format(&self) -> ImageFormat {
+        match *self {
+            GenericMetadata::Png(_) => ImageFormat::Png,
+            GenericMetadata::Gif(_) => ImageFormat::Gif,
+            GenericMetadata::Jpeg(_) => ImageFormat::Jpeg,
+            GenericMetadata::Webp(_) => ImageFormat::Webp,
+            GenericMetadata::Bmp(_) => ImageFormat::Bmp,
+            GenericMetadata::Tiff(_) => ImageFormat::Tiff,
+            GenericMetadata::Ico(_) => ImageFormat::Ico,
+            GenericMetadata::Pnm(_) => ImageFormat::Pnm,
+            GenericMetadata::Exr(_) => ImageFormat::Exr,
+            GenericMetadata::Qoi(_) => ImageFormat::Qoi,
+            GenericMetadata::Heif(ref md) => if md.is_avif() { ImageFormat::Avif } else { ImageFormat::Heif },
+            GenericMetadata::Svg(_) => ImageFormat::Svg,
+            GenericMetadata::Dds(_) => ImageFormat::Dds,
+            GenericMetadata::Psd(_) => ImageFormat::Psd,
+            GenericMetadata::Wbmp(_) => ImageFormat::Wbmp,
+            GenericMetadata::Jp2(_) => ImageFormat::Jp2
         }
     }
 
     /// Returns a MIME type string for the image type of the contained metadata.
+    #[inline]
     pub fn mime_type(&self) -> &'static str {
-        match *self {
-            GenericMetadata::Png(_) => "image/png",
-            GenericMetadata::Gif(_) => "image/gif",
-            GenericMetadata::Jpeg(_) => "image/jpeg",
-            GenericMetadata::Webp(_) => "image/webp"
+        self.format().mime_type()
+    }
+
+    /// Returns a summary of the most common metadata properties of the image.
+    ///
+    /// This is a shortcut for calling `format()`, `dimensions()`, `color_depth()`,
+    /// `mime_type()`, `is_animated()` and `frame_count()` methods at once.
+    pub fn common(&self) -> CommonMetadata {
+        CommonMetadata {
+            format: self.format(),
+            dimensions: self.dimensions(),
+            color_depth: self.color_depth(),
+            mime_type: self.mime_type(),
+            is_animated: self.is_animated(),
+            frame_count: self.frame_count()
         }
     }
 
+    /// Returns a hash of the structural metadata of the image, e.g. for bucketing images
+    /// during deduplication.
+    ///
+    /// The hash is computed over the format, dimensions, color depth and number of frames
+    /// of the image (see `common()`), so images which agree on all of them have equal hashes.
+    /// This is not a content hash: it depends neither on pixel data nor on any other
+    /// metadata.
+    ///
+    /// The hash is stable, so it may be persisted. It is the 64-bit FNV-1a hash of
+    /// the following bytes:
+    ///
+    /// * the format name (see `ImageFormat::as_str()`) in UTF-8, followed by a zero byte;
+    /// * width and height as 32-bit little-endian integers;
+    /// * a zero byte if the color depth is unknown, or a byte of 1 followed by the color depth;
+    /// * the number of frames as a 64-bit little-endian integer.
+    pub fn structural_hash(&self) -> u64 {
+        let dimensions = self.dimensions();
+        let mut data = Vec::new();
+        data.extend_from_slice(self.format().as_str().as_bytes());
+        data.push(0);
+        data.write_u32::<LittleEndian>(dimensions.width).unwrap();
+        data.write_u32::<LittleEndian>(dimensions.height).unwrap();
+        match self.color_depth() {
+            Some(depth) => data.extend_from_slice(&[1, depth]),
+            None => data.push(0)
+        }
+        data.write_u64::<LittleEndian>(self.frame_count() as u64).unwrap();
+        fnv1a(&data)
+    }
+
     /// Attemts to convert this value to the specific metadata type by value.
     ///
     /// This method is needed only to provide a convenient syntax and it is not necessary
@@ -274,38 +612,400 @@ impl GenericMetadata {
     }
 }
 
-/// Attempts to load metadata for an image contained in the provided input stream.
+/// Contains metadata properties which are available for all image formats.
 ///
-/// This method automatically determines the format of the contained image. Because it may
-/// need to read the stream from the beginning several times, a `Seek` bound is necessary
-/// on the input stream. This may cause problems only with network streams as they are
-/// naturally not seekable, so one would need to buffer the data from them first.
-pub fn load<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<GenericMetadata> {
-    // try png
-    try!(r.seek(SeekFrom::Start(0)));
-    if let Ok(md) = png::Metadata::load_from_seek(r) {
-        return Ok(GenericMetadata::Png(md));
+/// Values of this type are obtained via `GenericMetadata::common()` method. See the
+/// documentation of the corresponding `GenericMetadata` methods for the exact meaning of
+/// each field.
+///
+/// With the `serde` feature enabled, this type only implements `Serialize`, because
+/// the MIME type is a static string.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CommonMetadata {
+    /// Image format.
+    pub format: ImageFormat,
+    /// Image dimensions.
+    pub dimensions: Dimensions,
+    /// Color depth in bits per pixel, if it is available.
+    pub color_depth: Option<u8>,
+    /// MIME type of the image format.
+    pub mime_type: &'static str,
+    /// Whether the image is animated.
+    pub is_animated: bool,
+    /// Number of frames in the image.
+    pub frame_count: usize
+}
+
+impl fmt::Display for GenericMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} image, {} ({})", self.format(), self.dimensions(), self.mime_type())
     }
+}
 
-    // try gif
-    try!(r.seek(SeekFrom::Start(0)));
-    if let Ok(md) = gif::Metadata::load_from_seek(r) {
-        return Ok(GenericMetadata::Gif(md));
+/// Represents an image format supported by this library.
+///
+/// All formats except WBMP can be detected by `detect_format()`. WBMP images do not have
+/// a distinctive signature, so they can only be loaded explicitly or by file extension,
+/// see `load_from_file_with_hint()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ImageFormat {
+    Png,
+    Gif,
+    Jpeg,
+    Webp,
+    Bmp,
+    Tiff,
+    Ico,
+    Pnm,
+    Exr,
+    Qoi,
+    Heif,
+    Avif,
+    Svg,
+    Dds,
+    Psd,
+    Wbmp,
+    Jp2
+}
+
+impl fmt::Display for ImageFormat {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
     }
+}
 
-    // try webp
-    try!(r.seek(SeekFrom::Start(0)));
-    if let Ok(md) = webp::Metadata::load_from_seek(r) {
-        return Ok(GenericMetadata::Webp(md));
+impl ImageFormat {
+    /// All supported image formats.
+    const ALL: &'static [ImageFormat] = &[
+        ImageFormat::Png, ImageFormat::Gif, ImageFormat::Jpeg, ImageFormat::Webp,
+        ImageFormat::Bmp, ImageFormat::Tiff, ImageFormat::Ico, ImageFormat::Pnm,
+        ImageFormat::Exr, ImageFormat::Qoi, ImageFormat::Heif, ImageFormat::Avif,
+        ImageFormat::Svg, ImageFormat::Dds, ImageFormat::Psd, ImageFormat::Wbmp,
+        ImageFormat::Jp2,
+    ];
+
+    /// Returns a short human-readable name of this image format, e.g. `"JPEG"`.
+    ///
+    /// This is the same string which is produced by the `Display` implementation.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Gif => "GIF",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Webp => "WebP",
+            ImageFormat::Bmp => "BMP",
+            ImageFormat::Tiff => "TIFF",
+            ImageFormat::Ico => "ICO",
+            ImageFormat::Pnm => "PNM",
+            ImageFormat::Exr => "OpenEXR",
+            ImageFormat::Qoi => "QOI",
+            ImageFormat::Heif => "HEIF",
+            ImageFormat::Avif => "AVIF",
+            ImageFormat::Svg => "SVG",
+            ImageFormat::Dds => "DDS",
+            ImageFormat::Psd => "PSD",
+            ImageFormat::Wbmp => "WBMP",
+            ImageFormat::Jp2 => "JPEG 2000",
+        }
     }
 
-    // try jpeg
+    /// Returns file extensions commonly used for this image format, in lowercase and
+    /// without the leading dot.
+    ///
+    /// The first extension is the canonical one.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match *self {
+            ImageFormat::Png => &["png", "apng"],
+            ImageFormat::Gif => &["gif"],
+            ImageFormat::Jpeg => &["jpg", "jpeg", "jpe", "jfif"],
+            ImageFormat::Webp => &["webp"],
+            ImageFormat::Bmp => &["bmp", "dib"],
+            ImageFormat::Tiff => &["tiff", "tif"],
+            ImageFormat::Ico => &["ico", "cur"],
+            ImageFormat::Pnm => &["pnm", "pbm", "pgm", "ppm"],
+            ImageFormat::Exr => &["exr"],
+            ImageFormat::Qoi => &["qoi"],
+            ImageFormat::Heif => &["heif", "heic", "hif"],
+            ImageFormat::Avif => &["avif"],
+            ImageFormat::Svg => &["svg"],
+            ImageFormat::Dds => &["dds"],
+            ImageFormat::Psd => &["psd", "psb"],
+            ImageFormat::Wbmp => &["wbmp"],
+            ImageFormat::Jp2 => &["jp2", "j2k", "j2c", "jpc", "jpf", "jpx"],
+        }
+    }
+
+    /// Returns a MIME type string for this image format.
+    pub fn mime_type(&self) -> &'static str {
+        match *self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Tiff => "image/tiff",
+            ImageFormat::Ico => "image/x-icon",
+            ImageFormat::Pnm => "image/x-portable-anymap",
+            ImageFormat::Exr => "image/x-exr",
+            ImageFormat::Qoi => "image/qoi",
+            ImageFormat::Heif => "image/heif",
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Svg => "image/svg+xml",
+            ImageFormat::Dds => "image/vnd-ms.dds",
+            ImageFormat::Psd => "image/vnd.adobe.photoshop",
+            ImageFormat::Wbmp => "image/vnd.wap.wbmp",
+            ImageFormat::Jp2 => "image/jp2"
+        }
+    }
+
+    /// Returns a lower bound of the number of bytes needed to load metadata of this image
+    /// format.
+    ///
+    /// This delegates to `LoadableMetadata::min_header_len()` of the corresponding metadata
+    /// type; see its documentation for details.
+    pub fn min_header_len(&self) -> usize {
+        match *self {
+            ImageFormat::Png => png::Metadata::min_header_len(),
+            ImageFormat::Gif => gif::Metadata::min_header_len(),
+            ImageFormat::Jpeg => jpeg::Metadata::min_header_len(),
+            ImageFormat::Webp => webp::Metadata::min_header_len(),
+            ImageFormat::Bmp => bmp::Metadata::min_header_len(),
+            ImageFormat::Tiff => tiff::Metadata::min_header_len(),
+            ImageFormat::Ico => ico::Metadata::min_header_len(),
+            ImageFormat::Pnm => pnm::Metadata::min_header_len(),
+            ImageFormat::Exr => exr::Metadata::min_header_len(),
+            ImageFormat::Qoi => qoi::Metadata::min_header_len(),
+            ImageFormat::Heif | ImageFormat::Avif => heif::Metadata::min_header_len(),
+            ImageFormat::Svg => svg::Metadata::min_header_len(),
+            ImageFormat::Dds => dds::Metadata::min_header_len(),
+            ImageFormat::Psd => psd::Metadata::min_header_len(),
+            ImageFormat::Wbmp => wbmp::Metadata::min_header_len(),
+            ImageFormat::Jp2 => jp2::Metadata::min_header_len()
+        }
+    }
+
+    /// Returns the image format which is usually associated with the provided file extension.
+    ///
+    /// The extension is matched case-insensitively against `extensions()` of all formats and
+    /// must not contain the leading dot.
+    pub fn from_extension(ext: &str) -> Option<ImageFormat> {
+        let ext = ext.to_ascii_lowercase();
+        ImageFormat::ALL.iter().cloned().find(|f| f.extensions().contains(&&*ext))
+    }
+
+    /// Returns the image format which corresponds to the provided MIME type.
+    ///
+    /// Besides the MIME types returned by `mime_type()`, some common non-standard aliases
+    /// are recognized, e.g. `image/jpg` or `image/x-ms-bmp`. The MIME type is matched
+    /// case-insensitively, and its parameters, if any, are ignored.
+    pub fn from_mime_type(mime_type: &str) -> Option<ImageFormat> {
+        let mime_type = mime_type.split(';').next().unwrap().trim().to_ascii_lowercase();
+        ImageFormat::ALL.iter().cloned()
+            .find(|f| f.mime_type() == mime_type)
+            .or_else(|| MIME_TYPE_ALIASES.iter().find(|&&(alias, _)| alias == mime_type).map(|&(_, f)| f))
+    }
+
+    /// Determines the image format from the leading bytes of an image.
+    ///
+    /// This is the same check which is used by `detect_format()` and `load()`: the prefixes
+    /// returned by `signatures()` are tried first, followed by the formats which need a more
+    /// elaborate check, i.e. WebP, ICO/CUR, PNM and HEIF/AVIF. Providing at least 12 bytes is
+    /// enough to recognize any supported format; fewer bytes may not be enough to recognize
+    /// some of them.
+    ///
+    /// Only the signature is inspected, so the rest of the image may still be invalid.
+    pub fn from_magic(sig: &[u8]) -> Option<ImageFormat> {
+        if let Some(&(format, _)) = SIGNATURES.iter().find(|&&(_, magic)| sig.starts_with(magic)) {
+            return Some(format);
+        }
+
+        if sig.len() >= 12 && &sig[..4] == b"RIFF" && &sig[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else if sig.len() >= 6 && (sig.starts_with(b"\0\0\x01\0") || sig.starts_with(b"\0\0\x02\0")) &&
+                  (sig[4] != 0 || sig[5] != 0) {
+            // ICONDIR header with a non-zero number of images
+            Some(ImageFormat::Ico)
+        } else if sig.len() >= 3 && sig[0] == b'P' && b'1' <= sig[1] && sig[1] <= b'6' &&
+                  (sig[2].is_ascii_whitespace() || sig[2] == b'#') {
+            Some(ImageFormat::Pnm)
+        } else if sig.len() >= 12 && &sig[4..8] == b"ftyp" && heif::is_supported_brand(&sig[8..12]) {
+            // only the major brand is inspected here, so AVIF images whose major brand is
+            // a generic one like `mif1` are detected as HEIF
+            if &sig[8..12] == b"avif" || &sig[8..12] == b"avis" {
+                Some(ImageFormat::Avif)
+            } else {
+                Some(ImageFormat::Heif)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Non-standard MIME types which are used for some image formats in the wild.
+static MIME_TYPE_ALIASES: &'static [(&'static str, ImageFormat)] = &[
+    ("image/x-png", ImageFormat::Png),
+    ("image/apng", ImageFormat::Png),
+    ("image/jpg", ImageFormat::Jpeg),
+    ("image/pjpeg", ImageFormat::Jpeg),
+    ("image/x-ms-bmp", ImageFormat::Bmp),
+    ("image/x-bmp", ImageFormat::Bmp),
+    ("image/tiff-fx", ImageFormat::Tiff),
+    ("image/vnd.microsoft.icon", ImageFormat::Ico),
+    ("image/x-portable-bitmap", ImageFormat::Pnm),
+    ("image/x-portable-graymap", ImageFormat::Pnm),
+    ("image/x-portable-pixmap", ImageFormat::Pnm),
+    ("image/heic", ImageFormat::Heif),
+    ("image/heif-sequence", ImageFormat::Heif),
+    ("image/heic-sequence", ImageFormat::Heif),
+    ("image/x-dds", ImageFormat::Dds),
+    ("application/x-photoshop", ImageFormat::Psd),
+    ("image/x-photoshop", ImageFormat::Psd),
+    ("image/jpx", ImageFormat::Jp2),
+    ("image/j2c", ImageFormat::Jp2),
+];
+
+static SIGNATURES: &'static [(ImageFormat, &'static [u8])] = &[
+    (ImageFormat::Png, b"\x89PNG\r\n\x1a\n"),
+    (ImageFormat::Gif, b"GIF87a"),
+    (ImageFormat::Gif, b"GIF89a"),
+    (ImageFormat::Bmp, b"BM"),
+    (ImageFormat::Tiff, b"II*\0"),
+    (ImageFormat::Tiff, b"MM\0*"),
+    (ImageFormat::Exr, b"\x76\x2f\x31\x01"),
+    (ImageFormat::Qoi, b"qoif"),
+    (ImageFormat::Dds, b"DDS "),
+    (ImageFormat::Psd, b"8BPS"),
+    (ImageFormat::Jp2, b"\0\0\0\x0cjP  \r\n\x87\n"),
+    (ImageFormat::Jp2, b"\xff\x4f\xff\x51"),
+    // XML documents are assumed to be SVG images, which is checked when loading
+    (ImageFormat::Svg, b"<svg"),
+    (ImageFormat::Svg, b"<?xml"),
+    (ImageFormat::Svg, b"\xef\xbb\xbf<?xml"),
+    (ImageFormat::Jpeg, b"\xff\xd8"),
+];
+
+/// Returns the table of fixed byte prefixes which identify image formats.
+///
+/// An image whose leading bytes start with one of these prefixes is assumed to be of the
+/// corresponding format. Several prefixes may map to the same format. WebP, ICO/CUR, PNM
+/// and HEIF/AVIF images cannot be identified by a fixed prefix and are not listed here;
+/// use `ImageFormat::from_magic()` to recognize all supported formats.
+pub fn signatures() -> &'static [(ImageFormat, &'static [u8])] {
+    SIGNATURES
+}
+
+/// The number of leading bytes which is enough to determine the image format.
+const SIGNATURE_LEN: usize = 12;
+
+/// Determines the format of an image contained in the provided input stream.
+///
+/// Only the leading "magic" bytes of the stream are inspected, so this function is a lot
+/// cheaper than `load()`, but it does not check that the rest of the image is valid. The
+/// stream is rewound to its beginning before returning.
+pub fn detect_format<R: ?Sized + Read + Seek>(r: &mut R) -> Result<ImageFormat> {
+    try!(peek_format(r)).ok_or(Error::NoFormatMatched(Vec::new()))
+}
+
+fn peek_format<R: ?Sized + Read + Seek>(r: &mut R) -> Result<Option<ImageFormat>> {
+    let mut signature = [0u8; SIGNATURE_LEN];
+    try!(r.seek(SeekFrom::Start(0)));
+    let n = try!(r.read_exact_0(&mut signature));
     try!(r.seek(SeekFrom::Start(0)));
-    if let Ok(md) = jpeg::Metadata::load_from_seek(r) {
-        return Ok(GenericMetadata::Jpeg(md));
+
+    Ok(ImageFormat::from_magic(&signature[..n]))
+}
+
+/// Determines the format of an image contained in a file identified by the provided path.
+///
+/// This method delegates to `detect_format()` method.
+pub fn detect_format_from_file<P: AsRef<Path>>(p: P) -> Result<ImageFormat> {
+    let mut f = try!(File::open(p));
+    detect_format(&mut f)
+}
+
+/// Determines the format of an image contained in an in-memory buffer.
+///
+/// This method delegates to `detect_format()` method.
+pub fn detect_format_from_buf(b: &[u8]) -> Result<ImageFormat> {
+    detect_format(&mut Cursor::new(b))
+}
+
+/// Attempts to load metadata for an image contained in the provided input stream.
+///
+/// This method automatically determines the format of the contained image by its leading
+/// bytes (see `detect_format()`) and then loads the metadata with the corresponding decoder.
+/// Because the stream needs to be rewound after the format is determined, a `Seek` bound is
+/// necessary on the input stream. This may cause problems only with network streams as they
+/// are naturally not seekable, so one would need to buffer the data from them first.
+///
+/// JPEG images are not required to start with a well-defined signature, so if the format
+/// could not be determined, this method attempts to load the image as JPEG. If this fails
+/// too, `Error::NoFormatMatched` containing the JPEG decoder error is returned.
+///
+/// This function uses the default `LoadOptions`; see `load_with_options()`.
+#[inline]
+pub fn load<R: ?Sized + BufRead + Seek>(r: &mut R) -> Result<GenericMetadata> {
+    load_with_options(r, &LoadOptions::default())
+}
+
+/// Attempts to load metadata for an image contained in the provided input stream, using
+/// the provided options.
+///
+/// This function works exactly like `load()`, except that the options, e.g. the allocation
+/// limit, are passed to the format-specific decoder. Use it with a suitable limit when
+/// inspecting untrusted images.
+pub fn load_with_options<R: ?Sized + BufRead + Seek>(r: &mut R, options: &LoadOptions) -> Result<GenericMetadata> {
+    let format = try!(peek_format(r));
+    load_detected(r, format, options)
+}
+
+/// Loads metadata with the decoder for the detected format, falling back to the JPEG decoder
+/// if the format is unknown; errors of the latter are wrapped into `Error::NoFormatMatched`.
+fn load_detected<R: ?Sized + BufRead + Seek>(r: &mut R, format: Option<ImageFormat>,
+                                             options: &LoadOptions) -> Result<GenericMetadata> {
+    match format {
+        Some(format) => load_format(r, format, options),
+        None => load_format(r, ImageFormat::Jpeg, options)
+            .map_err(|e| Error::NoFormatMatched(vec![(ImageFormat::Jpeg, e)]))
     }
+}
 
-    Err(invalid_format!("unknown or unsupported image type"))
+fn load_format<R: ?Sized + BufRead + Seek>(r: &mut R, format: ImageFormat, options: &LoadOptions) -> Result<GenericMetadata> {
+    let md = try!(match format {
+        ImageFormat::Png => png::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Png),
+        ImageFormat::Gif => gif::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Gif),
+        ImageFormat::Webp => webp::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Webp),
+        ImageFormat::Bmp => bmp::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Bmp),
+        ImageFormat::Tiff => tiff::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Tiff),
+        ImageFormat::Ico => ico::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Ico),
+        ImageFormat::Pnm => pnm::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Pnm),
+        ImageFormat::Exr => exr::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Exr),
+        ImageFormat::Qoi => qoi::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Qoi),
+        ImageFormat::Heif | ImageFormat::Avif =>
+            heif::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Heif),
+        ImageFormat::Svg => svg::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Svg),
+        ImageFormat::Dds => dds::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Dds),
+        ImageFormat::Psd => psd::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Psd),
+        ImageFormat::Wbmp => wbmp::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Wbmp),
+        ImageFormat::Jp2 => jp2::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Jp2),
+        ImageFormat::Jpeg => jpeg::Metadata::load_from_seek_with_options(r, options).map(GenericMetadata::Jpeg)
+    });
+    reject_unsized(md)
+}
+
+/// Rejects SVG images which do not have an intrinsic size, because they have no meaningful
+/// `GenericMetadata::dimensions()`.
+fn reject_unsized(md: GenericMetadata) -> Result<GenericMetadata> {
+    match md {
+        GenericMetadata::Svg(ref svg) if svg.dimensions.is_none() =>
+            Err(invalid_format!("SVG image does not have an intrinsic size")),
+        md => Ok(md)
+    }
 }
 
 /// Attempts to load metadata for an image contained in a file identified by the provided path.
@@ -317,10 +1017,151 @@ pub fn load_from_file<P: AsRef<Path>>(p: P) -> Result<GenericMetadata> {
     load(&mut f)
 }
 
+/// The size of the read buffer shared by all files loaded by `load_many()`.
+const LOAD_MANY_BUFFER_LEN: usize = 8 * 1024;
+
+/// Lazily loads metadata for images contained in files identified by the provided paths.
+///
+/// This works like calling `load_from_file()` for each path, except that a single read buffer
+/// is allocated and reused for all files. Each item of the returned iterator contains a path
+/// and the result of loading metadata from the corresponding file; a failure to open or load
+/// one file does not stop the iteration.
+///
+/// ```no_run
+/// use std::fs;
+///
+/// let paths = fs::read_dir("images").unwrap().map(|e| e.unwrap().path());
+/// for (path, result) in immeta::load_many(paths) {
+///     match result {
+///         Ok(md) => println!("{}: {}", path.display(), md),
+///         Err(e) => println!("{}: {}", path.display(), e)
+///     }
+/// }
+/// ```
+pub fn load_many<I: IntoIterator<Item=PathBuf>>(paths: I) -> LoadMany<I::IntoIter> {
+    LoadMany {
+        paths: paths.into_iter(),
+        buf: vec![0; LOAD_MANY_BUFFER_LEN]
+    }
+}
+
+/// An iterator over metadata of several image files.
+///
+/// Values of this type are obtained via `load_many()` function.
+pub struct LoadMany<I> {
+    paths: I,
+    buf: Vec<u8>
+}
+
+impl<I: Iterator<Item=PathBuf>> Iterator for LoadMany<I> {
+    type Item = (PathBuf, Result<GenericMetadata>);
+
+    fn next(&mut self) -> Option<(PathBuf, Result<GenericMetadata>)> {
+        self.paths.next().map(|path| {
+            let result = File::open(&path)
+                .map_err(Error::from)
+                .and_then(|f| load(&mut BorrowedBufReader::new(f, &mut self.buf)));
+            (path, result)
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.paths.size_hint()
+    }
+}
+
+/// Attempts to load metadata for an image contained in a file identified by the provided path,
+/// using the file extension as a hint about the image format.
+///
+/// The hint only matters for images which do not have a recognizable signature: if the
+/// extension is known (see `ImageFormat::from_extension()`), the decoder for the corresponding
+/// format is tried before the JPEG decoder, which `load()` falls back to. If both of them fail,
+/// `Error::NoFormatMatched` containing both errors is returned. Images with a recognizable
+/// signature are loaded just as with `load_from_file()`, so a file with a wrong extension is
+/// still loaded correctly.
+pub fn load_from_file_with_hint<P: AsRef<Path>>(p: P) -> Result<GenericMetadata> {
+    let p = p.as_ref();
+    let hint = p.extension().and_then(|e| e.to_str()).and_then(ImageFormat::from_extension);
+    let options = LoadOptions::default();
+
+    let mut f = BufReader::new(try!(File::open(p)));
+    let detected = try!(peek_format(&mut f));
+    match (detected, hint) {
+        (None, Some(format)) if format != ImageFormat::Jpeg => match load_format(&mut f, format, &options) {
+            Ok(md) => Ok(md),
+            Err(e) => {
+                try!(f.seek(SeekFrom::Start(0)));
+                load_format(&mut f, ImageFormat::Jpeg, &options)
+                    .map_err(|jpeg_e| Error::NoFormatMatched(vec![(format, e), (ImageFormat::Jpeg, jpeg_e)]))
+            }
+        },
+        _ => load_detected(&mut f, detected, &options)
+    }
+}
+
 /// Attempts to load metadata for an image contained in an in-memory buffer.
 ///
-/// This method delegates to `load()` method and, consequently, also determines the image format
-/// automatically.
+/// This method delegates to `load_from_slice()` method and, consequently, also determines
+/// the image format automatically.
+#[inline]
 pub fn load_from_buf(b: &[u8]) -> Result<GenericMetadata> {
-    load(&mut Cursor::new(b))
+    load_from_slice(b)
+}
+
+/// Attempts to load metadata for an image contained in a borrowed byte slice, e.g. a
+/// memory-mapped file.
+///
+/// The image format is determined directly from the leading bytes of the slice, and then
+/// the corresponding decoder reads the slice in place, so the data is neither copied nor
+/// required to be owned or `'static`. Like `load()`, this method falls back to the JPEG
+/// decoder if the format is not recognized.
+pub fn load_from_slice(b: &[u8]) -> Result<GenericMetadata> {
+    let format = ImageFormat::from_magic(&b[..cmp::min(b.len(), SIGNATURE_LEN)]);
+    load_detected(&mut Cursor::new(b), format, &LoadOptions::default())
+}
+
+/// Attempts to load metadata for an image whose beginning is contained in the provided buffer,
+/// e.g. the part of the image which has been received from a network so far.
+///
+/// Works like `load_from_slice()`, except that if the buffer ends before the metadata is
+/// loaded, `Error::NeedMoreData` is returned instead of `Error::UnexpectedEndOfFile`. It
+/// contains a lower bound of the number of bytes needed, so a streaming client may buffer
+/// at least that many bytes and try again. `Error::NeedMoreData` is also returned if the
+/// buffer is too short to determine the image format.
+///
+/// Some loaders tolerate truncated data after the essential metadata, e.g. the JPEG loader
+/// only counts the scans present in the buffer, so the result may differ from the one
+/// obtained from the complete image.
+pub fn load_from_partial_buf(b: &[u8]) -> Result<GenericMetadata> {
+    let mut r = PrefixCursor::new(b);
+    let format = ImageFormat::from_magic(&b[..cmp::min(b.len(), SIGNATURE_LEN)]);
+    match format {
+        Some(f) => load_detected(&mut r, format, &LoadOptions::default()).map_err(|e| r.map_eof(e, f.min_header_len())),
+        None if b.len() < SIGNATURE_LEN => Err(Error::NeedMoreData { at_least: SIGNATURE_LEN }),
+        None => load_detected(&mut r, None, &LoadOptions::default())
+    }
+}
+
+/// The maximum number of bytes which `load_from_reader()` reads from the input stream.
+const READER_PREFIX_LEN: u64 = 64 * 1024;
+
+/// Attempts to load metadata for an image contained in the provided non-seekable input stream.
+///
+/// Unlike `load()`, this method does not require the stream to be seekable. Instead, it reads
+/// at most the first 64 KiB of the stream into an internal buffer and loads the metadata
+/// from this buffer, determining the image format automatically.
+///
+/// If the metadata does not fit into this prefix, an error is returned. Note that for some
+/// formats the metadata is spread across the whole file (e.g. the information about frames of
+/// GIF images), so this method can't be used to load such images if they are large enough.
+pub fn load_from_reader<R: ?Sized + Read>(r: &mut R) -> Result<GenericMetadata> {
+    let mut buf = Vec::new();
+    try!((&mut *r).take(READER_PREFIX_LEN).read_to_end(&mut buf));
+
+    match load_from_buf(&buf) {
+        Err(Error::UnexpectedEndOfFile(..)) if buf.len() as u64 == READER_PREFIX_LEN =>
+            Err(invalid_format!("image metadata does not fit into the first {} bytes", READER_PREFIX_LEN)),
+        result => result
+    }
 }
@@ -21,6 +21,18 @@
 //!   * JPEG
 //!   * PNG 1.2
 //!   * GIF (both 87a and 89a)
+//!   * BMP
+//!   * TIFF
+//!   * ICO/CUR
+//!   * PNM (PBM, PGM and PPM)
+//!   * OpenEXR
+//!   * QOI
+//!   * HEIF and AVIF (dimensions only)
+//!   * SVG (intrinsic dimensions)
+//!   * DDS
+//!   * PSD and PSB
+//!   * WBMP (not detected automatically)
+//!   * JPEG 2000 (JP2 and raw codestreams)
 //!
 //! Support for more types will come in future versions, as well as support for particular 
 //! metadata kinds (e.g. EXIF tags in JPEG) which are not yet available.
@@ -33,6 +45,8 @@
 extern crate byteorder;
 extern crate num_traits;
 extern crate arrayvec;
+#[cfg(feature = "serde")] #[macro_use] extern crate serde;
+#[cfg(feature = "flate2")] extern crate flate2;
 
 pub use types::*;
 pub use traits::*;
@@ -1,21 +1,21 @@
 macro_rules! invalid_format {
     ($s:expr) => {
-        $crate::types::Error::InvalidFormat($s.into())
+        $crate::types::Error::InvalidFormat($s.into(), None)
     };
     ($fmt:expr, $($args:tt)*) => {
-        $crate::types::Error::InvalidFormat(format!($fmt, $($args)*).into())
+        $crate::types::Error::InvalidFormat(format!($fmt, $($args)*).into(), None)
     }
 }
 
 macro_rules! unexpected_eof {
     () => {
-        $crate::types::Error::UnexpectedEndOfFile(None)
+        $crate::types::Error::UnexpectedEndOfFile(None, None)
     };
     ($s:expr) => {
-        $crate::types::Error::UnexpectedEndOfFile(Some($s.into()))
+        $crate::types::Error::UnexpectedEndOfFile(Some($s.into()), None)
     };
     ($fmt:expr, $($args:tt)*) => {
-        $crate::types::Error::UnexpectedEndOfFile(Some(format!($fmt, $($args)*).into()))
+        $crate::types::Error::UnexpectedEndOfFile(Some(format!($fmt, $($args)*).into()), None)
     }
 }
 
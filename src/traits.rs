@@ -1,11 +1,26 @@
-use std::io::{BufReader, BufRead, Seek, Cursor};
+use std::io::{BufReader, Read, BufRead, Seek, Cursor};
 use std::fs::File;
 use std::path::Path;
 
-use types::Result;
+use types::{Result, LoadOptions};
+use utils::PrefixCursor;
 
 /// Provides several convenience functions for loading metadata from various sources.
 pub trait LoadableMetadata: Sized {
+    /// Returns a lower bound of the number of bytes needed to load metadata of this type.
+    ///
+    /// Metadata can never be loaded from an input which is shorter than this, so this value
+    /// may be used to decide how many bytes to buffer before attempting to parse an image
+    /// received from a stream. The actual number of needed bytes may be much larger, e.g.
+    /// if metadata is located after other data; in that case, loading fails with
+    /// `Error::UnexpectedEndOfFile`, and more bytes should be buffered.
+    ///
+    /// Returns 8 by default.
+    #[inline]
+    fn min_header_len() -> usize {
+        8
+    }
+
     /// Loads the implementing type from the given buffered input stream.
     fn load<R: ?Sized + BufRead>(r: &mut R) -> Result<Self>;
 
@@ -18,6 +33,35 @@ pub trait LoadableMetadata: Sized {
         LoadableMetadata::load(r)
     }
 
+    /// Loads the implementing type from the given buffered input stream with the provided
+    /// options.
+    ///
+    /// Delegates to `LoadableMetadata::load()` method by default, which is appropriate for
+    /// formats whose loaders do not allocate memory based on the image contents.
+    fn load_with_options<R: ?Sized + BufRead>(r: &mut R, options: &LoadOptions) -> Result<Self> {
+        let _ = options;
+        LoadableMetadata::load(r)
+    }
+
+    /// Loads the implementing type from the given buffered and seekable input stream with
+    /// the provided options.
+    ///
+    /// Delegates to `LoadableMetadata::load_with_options()` method by default.
+    /// Implementations which override `load_from_seek()` should override this method too.
+    fn load_from_seek_with_options<R: ?Sized + BufRead + Seek>(r: &mut R, options: &LoadOptions) -> Result<Self> {
+        LoadableMetadata::load_with_options(r, options)
+    }
+
+    /// Loads the implementing type from the given unbuffered input stream, e.g. a decompressor.
+    ///
+    /// Wraps the stream into a `BufReader` and delegates to `LoadableMetadata::load()` method
+    /// by default. Note that the buffered data which is not consumed by the loader is lost,
+    /// so the position of the stream after this method returns is unspecified.
+    #[inline]
+    fn load_from_read<R: ?Sized + Read>(r: &mut R) -> Result<Self> {
+        LoadableMetadata::load(&mut BufReader::new(r))
+    }
+
     /// Loads the implementing type from a file specified by the given path.
     ///
     /// Delegates to `LoadableMetadata::load_from_seek()` method by default.
@@ -34,5 +78,16 @@ pub trait LoadableMetadata: Sized {
     fn load_from_buf(buf: &[u8]) -> Result<Self> {
         LoadableMetadata::load_from_seek(&mut Cursor::new(buf))
     }
-}
 
+    /// Loads the implementing type from an in-memory buffer which contains a prefix of
+    /// the image, e.g. the part of it which has been received from a network so far.
+    ///
+    /// Works like `load_from_buf()`, except that hitting the end of the buffer results in
+    /// `Error::NeedMoreData` instead of `Error::UnexpectedEndOfFile`. The former contains
+    /// a lower bound of the number of bytes to buffer before trying again, which is based
+    /// on the reads performed by the loader and on `min_header_len()`.
+    fn load_from_partial_buf(buf: &[u8]) -> Result<Self> {
+        let mut r = PrefixCursor::new(buf);
+        LoadableMetadata::load_from_seek(&mut r).map_err(|e| r.map_eof(e, Self::min_header_len()))
+    }
+}
@@ -3,54 +3,131 @@ use std::result;
 use std::fmt;
 use std::borrow::Cow;
 use std::error;
+use std::f64;
+use std::cmp;
 
 use num_traits::ToPrimitive;
 
+use generic::ImageFormat;
+
 /// Library-specific error type which is returned by metadata loading operations.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Returned when metadata can't be recovered because image format is invalid.
     ///
     /// This error can be caused by broken file or when trying to load an image with
     /// an incorrect metadata decoder, e.g. trying to load PNG metadata from JPEG.
-    InvalidFormat(Cow<'static, str>),
+    ///
+    /// The second field contains the offset in the input stream where the problem was
+    /// found, if it is known.
+    InvalidFormat(Cow<'static, str>, Option<u64>),
 
     /// Returned when metadata can't be recovered because of the sudden end of the image file.
     ///
     /// Usually this error is caused by broken files, but it may also be cause by applying
     /// loose formats (like JPEG) to a different image type.
-    UnexpectedEndOfFile(Option<Cow<'static, str>>),
+    ///
+    /// The second field contains the offset in the input stream where the problem was
+    /// found, if it is known.
+    UnexpectedEndOfFile(Option<Cow<'static, str>>, Option<u64>),
+
+    /// Returned when metadata can't be recovered from an incomplete image yet.
+    ///
+    /// Unlike `UnexpectedEndOfFile`, this error is only returned by the functions which
+    /// load metadata from a prefix of an image, like `load_from_partial_buf()`. It means
+    /// that the image may be valid, and loading should be retried once at least `at_least`
+    /// bytes of it are available.
+    NeedMoreData {
+        /// A lower bound of the number of bytes needed to load the metadata.
+        at_least: usize
+    },
 
     /// Returned when an I/O error occurs when reading an input stream.
-    Io(io::Error)
+    Io(io::Error),
+
+    /// Returned when the image format could not be determined automatically.
+    ///
+    /// Contains the errors of the decoders which were tried on the image, in order, so
+    /// it is possible to find out why the image was rejected by each of them. The vector
+    /// is empty if the format could not be guessed at all.
+    NoFormatMatched(Vec<(ImageFormat, Error)>)
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::InvalidFormat(ref s) => write!(f, "invalid image format: {}", s),
-            Error::UnexpectedEndOfFile(None) => write!(f, "unexpected end of file"),
-            Error::UnexpectedEndOfFile(Some(ref s)) => write!(f, "unexpected end of file: {}", s),
-            Error::Io(ref e) => write!(f, "I/O error: {}", e)
+            Error::InvalidFormat(ref s, offset) => {
+                try!(write!(f, "invalid image format: {}", s));
+                write_offset(f, offset)
+            }
+            Error::UnexpectedEndOfFile(ref s, offset) => {
+                try!(f.write_str("unexpected end of file"));
+                if let Some(ref s) = *s {
+                    try!(write!(f, ": {}", s));
+                }
+                write_offset(f, offset)
+            }
+            Error::NeedMoreData { at_least } => write!(f, "need more data: at least {} bytes", at_least),
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::NoFormatMatched(ref errors) => {
+                try!(f.write_str("unknown or unsupported image type"));
+                for (i, &(format, ref e)) in errors.iter().enumerate() {
+                    try!(write!(f, "{} {} decoder: {}", if i == 0 { "; tried" } else { ";" }, format, e));
+                }
+                Ok(())
+            }
         }
     }
 }
 
+fn write_offset(f: &mut fmt::Formatter, offset: Option<u64>) -> fmt::Result {
+    match offset {
+        Some(offset) => write!(f, " at offset 0x{:X}", offset),
+        None => Ok(())
+    }
+}
+
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::InvalidFormat(_) => "invalid image format",
-            Error::UnexpectedEndOfFile(_) => "unexpected end of file",
-            Error::Io(_) => "i/o error"
+            Error::InvalidFormat(..) => "invalid image format",
+            Error::UnexpectedEndOfFile(..) => "unexpected end of file",
+            Error::NeedMoreData { .. } => "need more data",
+            Error::Io(_) => "i/o error",
+            Error::NoFormatMatched(_) => "unknown or unsupported image type"
         }
     }
 
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             Error::Io(ref e) => Some(e),
+            Error::NoFormatMatched(ref errors) => errors.first().map(|&(_, ref e)| e as &error::Error),
+            _ => None
+        }
+    }
+}
+
+impl Error {
+    /// Returns the offset in the input stream where the error was found, if it is known.
+    pub fn offset(&self) -> Option<u64> {
+        match *self {
+            Error::InvalidFormat(_, offset) | Error::UnexpectedEndOfFile(_, offset) => offset,
             _ => None
         }
     }
+
+    /// Attaches the given offset to this error, unless it already has one.
+    ///
+    /// Only `InvalidFormat` and `UnexpectedEndOfFile` errors can carry an offset; other
+    /// errors are returned unchanged.
+    pub fn at_offset(self, offset: u64) -> Error {
+        match self {
+            Error::InvalidFormat(s, None) => Error::InvalidFormat(s, Some(offset)),
+            Error::UnexpectedEndOfFile(s, None) => Error::UnexpectedEndOfFile(s, Some(offset)),
+            e => e
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -60,6 +137,26 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Clones the contained I/O error, if any.
+///
+/// `io::Error` does not implement `Clone`, so OS errors are recreated from their error
+/// codes, and all other I/O errors are recreated from their kind and message. The original
+/// inner error of the latter ones, if any, is not preserved.
+impl Clone for Error {
+    fn clone(&self) -> Error {
+        match *self {
+            Error::InvalidFormat(ref s, offset) => Error::InvalidFormat(s.clone(), offset),
+            Error::UnexpectedEndOfFile(ref s, offset) => Error::UnexpectedEndOfFile(s.clone(), offset),
+            Error::NeedMoreData { at_least } => Error::NeedMoreData { at_least: at_least },
+            Error::Io(ref e) => Error::Io(match e.raw_os_error() {
+                Some(code) => io::Error::from_raw_os_error(code),
+                None => io::Error::new(e.kind(), e.to_string())
+            }),
+            Error::NoFormatMatched(ref errors) => Error::NoFormatMatched(errors.clone())
+        }
+    }
+}
+
 /// Library-specific result type.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -70,7 +167,8 @@ pub type Result<T> = result::Result<T, Error>;
 ///
 /// It is possible to convert pairs of type `(T1, T2)`, where `T1` and `T2` are primitive
 /// number types, to this type, however, this is mostly needed for internal usage.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Dimensions {
     /// Image width in pixels.
     pub width: u32,
@@ -78,6 +176,135 @@ pub struct Dimensions {
     pub height: u32
 }
 
+impl Dimensions {
+    /// Creates new dimensions, returning `None` if either the width or the height is zero.
+    #[inline]
+    pub fn new(width: u32, height: u32) -> Option<Dimensions> {
+        if width == 0 || height == 0 {
+            None
+        } else {
+            Some(Dimensions { width: width, height: height })
+        }
+    }
+
+    /// Converts a pair of numbers to dimensions, returning an error if either of them is zero.
+    pub(crate) fn non_zero<T: ToPrimitive, U: ToPrimitive>(dims: (T, U)) -> Result<Dimensions> {
+        let Dimensions { width, height } = dims.into();
+        Dimensions::new(width, height).ok_or(invalid_format!("zero image dimension"))
+    }
+
+    /// Returns the aspect ratio of the image, i.e. its width divided by its height.
+    ///
+    /// If the height is zero, `f64::INFINITY` is returned.
+    pub fn aspect_ratio(&self) -> f64 {
+        if self.height == 0 {
+            f64::INFINITY
+        } else {
+            self.width as f64 / self.height as f64
+        }
+    }
+
+    /// Returns the number of pixels in the image, in millions.
+    #[inline]
+    pub fn megapixels(&self) -> f64 {
+        self.width as f64 * self.height as f64 / 1_000_000.0
+    }
+
+    /// Returns `true` if the image is wider than it is tall.
+    #[inline]
+    pub fn is_landscape(&self) -> bool {
+        self.width > self.height
+    }
+
+    /// Returns `true` if the image is taller than it is wide.
+    #[inline]
+    pub fn is_portrait(&self) -> bool {
+        self.width < self.height
+    }
+
+    /// Returns `true` if the width of the image is equal to its height.
+    #[inline]
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    /// Returns the largest dimensions which fit within `max` and have the same aspect ratio
+    /// as these dimensions.
+    ///
+    /// The dimensions are never scaled up: if they already fit within `max`, they are returned
+    /// intact. Otherwise, they are scaled down with `scale_to_width()` or `scale_to_height()`,
+    /// whichever side is limiting, so the other side of the result is rounded and is at
+    /// least 1.
+    pub fn fit_within(&self, max: Dimensions) -> Dimensions {
+        if self.width <= max.width && self.height <= max.height {
+            *self
+        } else if self.width as u64 * max.height as u64 >= self.height as u64 * max.width as u64 {
+            self.scale_to_width(max.width)
+        } else {
+            self.scale_to_height(max.height)
+        }
+    }
+
+    /// Returns dimensions with the given width and the height scaled proportionally.
+    ///
+    /// The height is rounded to the nearest integer, but it is at least 1, so very wide
+    /// images do not collapse. If the width of these dimensions is zero, the height is left
+    /// intact.
+    #[inline]
+    pub fn scale_to_width(&self, width: u32) -> Dimensions {
+        Dimensions {
+            width: width,
+            height: scale_side(self.height, width, self.width)
+        }
+    }
+
+    /// Returns dimensions with the given height and the width scaled proportionally.
+    ///
+    /// The width is rounded to the nearest integer, but it is at least 1, so very tall
+    /// images do not collapse. If the height of these dimensions is zero, the width is left
+    /// intact.
+    #[inline]
+    pub fn scale_to_height(&self, height: u32) -> Dimensions {
+        Dimensions {
+            width: scale_side(self.width, height, self.height),
+            height: height
+        }
+    }
+
+    /// Returns dimensions adjusted for the given pixel aspect ratio, i.e. the ratio of
+    /// the width of a pixel to its height.
+    ///
+    /// The width is multiplied by the ratio and rounded to the nearest integer, while
+    /// the height is left intact. For square pixels, i.e. when the ratio is 1, the result is
+    /// equal to the original dimensions. Ratios which are not positive finite numbers are
+    /// ignored, and the original dimensions are returned too.
+    pub fn scaled_by_pixel_aspect(&self, par: f64) -> Dimensions {
+        if par > 0.0 && par.is_finite() {
+            Dimensions {
+                width: (self.width as f64 * par).round() as u32,
+                height: self.height
+            }
+        } else {
+            *self
+        }
+    }
+}
+
+/// Computes `side * num / den`, rounded to the nearest integer and clamped to `1..=u32::MAX`.
+fn scale_side(side: u32, num: u32, den: u32) -> u32 {
+    if den == 0 {
+        return side;
+    }
+    let scaled = (side as u64 * num as u64 + den as u64 / 2) / den as u64;
+    cmp::max(1, cmp::min(scaled, u32::MAX as u64)) as u32
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
 impl<T: ToPrimitive, U: ToPrimitive> From<(T, U)> for Dimensions {
     fn from((w, h): (T, U)) -> Dimensions {
         Dimensions {
@@ -86,3 +313,153 @@ impl<T: ToPrimitive, U: ToPrimitive> From<(T, U)> for Dimensions {
         }
     }
 }
+
+/// Represents image resolution in dots per inch.
+///
+/// Values of this type are obtained via `GenericMetadata::resolution()`, which converts
+/// format-specific units to dots per inch.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Resolution {
+    /// Horizontal resolution in dots per inch.
+    pub x_dpi: f64,
+    /// Vertical resolution in dots per inch.
+    pub y_dpi: f64
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{} dpi", self.x_dpi, self.y_dpi)
+    }
+}
+
+impl From<(f64, f64)> for Resolution {
+    #[inline]
+    fn from((x_dpi, y_dpi): (f64, f64)) -> Resolution {
+        Resolution { x_dpi: x_dpi, y_dpi: y_dpi }
+    }
+}
+
+/// Options which control how metadata is loaded.
+///
+/// These are passed to `load_with_options()` function and to `LoadableMetadata` methods
+/// with the `_with_options` suffix; all other loading functions use the default options.
+///
+/// New options may be added in future versions, so values of this type can't be created
+/// with a struct literal. Use the setter methods on the default value instead:
+///
+/// ```
+/// use immeta::LoadOptions;
+///
+/// let options = LoadOptions::default().max_alloc(1024 * 1024).load_color_tables(true);
+/// assert_eq!(options.max_alloc, 1024 * 1024);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct LoadOptions {
+    /// The maximum size of a single buffer, in bytes, which may be allocated based on
+    /// sizes and counts read from the image.
+    ///
+    /// Exceeding this limit, e.g. with a huge embedded ICC profile or TIFF entry, causes
+    /// `Error::InvalidFormat` to be returned. This prevents crafted images from causing
+    /// huge allocations. For data which is split into many segments, like JPEG comments or
+    /// ICC profile chunks, the limit applies to their total size. The default value is
+    /// `LoadOptions::DEFAULT_MAX_ALLOC`.
+    pub max_alloc: usize,
+    /// Whether the colors of color tables (palettes) should be loaded.
+    ///
+    /// This is currently supported for GIF images, whose color tables are returned in
+    /// `gif::ColorTable::colors`. It is disabled by default, so the color data is skipped.
+    pub load_color_tables: bool,
+    /// The maximum number of frames of a GIF image whose blocks are loaded.
+    ///
+    /// When the limit is reached, loading stops at the beginning of the next frame and
+    /// `gif::Metadata::has_more_frames` is set, so `is_animated()` remains accurate while
+    /// the rest of the stream is not parsed. A limit of zero is treated as one. There is no
+    /// limit by default.
+    pub max_gif_frames: Option<usize>,
+    /// Whether only the header of a GIF image should be loaded.
+    ///
+    /// When enabled, loading stops right after the logical screen descriptor and the global
+    /// color table, so `gif::Metadata::blocks` is empty. This is much faster for large
+    /// animations when only dimensions or the version are needed, but information derived
+    /// from blocks, like `gif::Metadata::frames_number()` or `is_animated()`, is unavailable
+    /// then. It is disabled by default.
+    pub gif_header_only: bool,
+    /// Whether metadata should be returned for images which end after their header.
+    ///
+    /// This is useful when only the beginning of a large image is available, e.g. to
+    /// a thumbnail scanner. When enabled, loaders stop at the end of the stream and return
+    /// the metadata read so far, provided that the header with essential metadata like
+    /// dimensions is complete: the `IHDR` chunk of PNG images, the logical screen descriptor
+    /// of GIF images, and the first chunk of WebP images. Optional metadata located after
+    /// the end of the stream, e.g. ancillary PNG chunks or GIF frames, is then missing.
+    /// An incomplete header is still reported as `Error::UnexpectedEndOfFile`.
+    ///
+    /// JPEG images are always loaded this way once their frame header is read. Loaders of
+    /// other formats only read their headers anyway. It is disabled by default.
+    pub allow_truncated: bool
+}
+
+impl LoadOptions {
+    /// The default allocation limit, 64 MiB.
+    pub const DEFAULT_MAX_ALLOC: usize = 64 * 1024 * 1024;
+
+    /// Sets `max_alloc` option.
+    #[inline]
+    pub fn max_alloc(mut self, max_alloc: usize) -> LoadOptions {
+        self.max_alloc = max_alloc;
+        self
+    }
+
+    /// Sets `load_color_tables` option.
+    #[inline]
+    pub fn load_color_tables(mut self, load_color_tables: bool) -> LoadOptions {
+        self.load_color_tables = load_color_tables;
+        self
+    }
+
+    /// Sets `max_gif_frames` option.
+    #[inline]
+    pub fn max_gif_frames(mut self, max_gif_frames: Option<usize>) -> LoadOptions {
+        self.max_gif_frames = max_gif_frames;
+        self
+    }
+
+    /// Sets `gif_header_only` option.
+    #[inline]
+    pub fn gif_header_only(mut self, gif_header_only: bool) -> LoadOptions {
+        self.gif_header_only = gif_header_only;
+        self
+    }
+
+    /// Sets `allow_truncated` option.
+    #[inline]
+    pub fn allow_truncated(mut self, allow_truncated: bool) -> LoadOptions {
+        self.allow_truncated = allow_truncated;
+        self
+    }
+
+    /// Returns an error if `size` exceeds the allocation limit.
+    pub(crate) fn check_alloc(&self, size: u64, what: &str) -> Result<()> {
+        if size > self.max_alloc as u64 {
+            Err(invalid_format!("size of {} ({} bytes) exceeds the allocation limit of {} bytes",
+                                what, size, self.max_alloc))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions {
+            max_alloc: LoadOptions::DEFAULT_MAX_ALLOC,
+            load_color_tables: false,
+            max_gif_frames: None,
+            gif_header_only: false,
+            allow_truncated: false
+        }
+    }
+}
@@ -1,8 +1,13 @@
-use std::io::{self, Read, BufRead, ErrorKind};
+use std::io::{self, Read, BufRead, Seek, SeekFrom, ErrorKind, Cursor};
+use std::cmp;
+use std::i64;
+use std::hash::{Hash, Hasher};
 
 use byteorder::{ReadBytesExt, LittleEndian, BigEndian};
 use byteorder::ByteOrder as ByteOrderTrait;
 
+use types::Error;
+
 pub trait ReadExt: Read {
     fn read_exact_0(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
         let orig_len = buf.len() as u64;
@@ -18,6 +23,22 @@ pub trait ReadExt: Read {
         try!(self.read_to_end(&mut buf));
         Ok(buf)
     }
+
+    /// Reads all data from the stream, failing with `ErrorKind::InvalidData` if there are
+    /// more than `max` bytes.
+    ///
+    /// Unlike `read_to_vec()`, this method never allocates more than `max` bytes (plus one byte
+    /// which is needed to detect the overflow), so it is safe to use with untrusted streams.
+    /// The buffer grows with the data actually read rather than being preallocated.
+    fn read_to_vec_capped(&mut self, max: usize) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        try!(self.take(max as u64 + 1).read_to_end(&mut buf));
+        if buf.len() > max {
+            Err(io::Error::new(ErrorKind::InvalidData, format!("data size exceeds {} bytes", max)))
+        } else {
+            Ok(buf)
+        }
+    }
 }
 
 impl<R: ?Sized + Read> ReadExt for R {}
@@ -73,7 +94,88 @@ pub trait BufReadExt: BufRead {
 
 impl<R: ?Sized + BufRead> BufReadExt for R {}
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub trait SeekSkipExt: BufRead + Seek {
+    /// Skips `n` bytes, seeking in the underlying stream if they are not buffered.
+    ///
+    /// Unlike `BufReadExt::skip_exact()`, this always returns `n`, because seeking past
+    /// the end of the stream is not an error. Skipping past the end is therefore detected
+    /// by the next read, which reports the end of the stream.
+    fn seek_skip_exact(&mut self, n: u64) -> io::Result<u64> {
+        // small skips within the buffer are cheaper than seeking, which discards the buffer
+        let available = loop {
+            match self.fill_buf() {
+                Ok(buf) => break buf.len() as u64,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e)
+            }
+        };
+        if n <= available {
+            self.consume(n as usize);
+            return Ok(n);
+        }
+
+        let n = cmp::min(n, i64::MAX as u64);
+        try!(self.seek(SeekFrom::Current(n as i64)));
+        Ok(n)
+    }
+}
+
+impl<R: ?Sized + BufRead + Seek> SeekSkipExt for R {}
+
+/// A buffered reader which can skip bytes.
+///
+/// This trait allows loaders to use the same code for seekable and non-seekable streams,
+/// wrapped into `Streaming` and `Seekable` respectively, while skipping data in the most
+/// efficient way for each of them.
+pub trait Skip: BufRead {
+    /// Skips `n` bytes, returning the number of bytes actually skipped.
+    ///
+    /// Seekable streams may report that all bytes were skipped even if the end of the stream
+    /// was reached; the next read then reports the end of the stream.
+    fn skip(&mut self, n: u64) -> io::Result<u64>;
+}
+
+/// Wraps a non-seekable stream; skipping reads and discards the data.
+pub struct Streaming<'a, R: ?Sized + 'a>(pub &'a mut R);
+
+/// Wraps a seekable stream; skipping seeks over the data which is not buffered.
+pub struct Seekable<'a, R: ?Sized + 'a>(pub &'a mut R);
+
+macro_rules! impl_skip_wrapper {
+    ($name:ident, $($bound:ident)+; $skip:ident) => {
+        impl<'a, R: ?Sized + $($bound+)+> Read for $name<'a, R> {
+            #[inline]
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl<'a, R: ?Sized + $($bound+)+> BufRead for $name<'a, R> {
+            #[inline]
+            fn fill_buf(&mut self) -> io::Result<&[u8]> {
+                self.0.fill_buf()
+            }
+
+            #[inline]
+            fn consume(&mut self, amt: usize) {
+                self.0.consume(amt)
+            }
+        }
+
+        impl<'a, R: ?Sized + $($bound+)+> Skip for $name<'a, R> {
+            #[inline]
+            fn skip(&mut self, n: u64) -> io::Result<u64> {
+                self.0.$skip(n)
+            }
+        }
+    }
+}
+
+impl_skip_wrapper! { Streaming, BufRead; skip_exact }
+impl_skip_wrapper! { Seekable, BufRead Seek; seek_skip_exact }
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ByteOrder {
     Little,
     Big,
@@ -143,3 +245,357 @@ gen_read_byte_order_ext! {
 }
 
 impl<R: Read> ByteOrderReadExt for R {}
+
+/// Order in which `BitReader` takes bits from each byte.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+    /// The least significant bit of a byte is read first, and the first bit read becomes
+    /// the least significant bit of the value, like in VP8 and VP8L.
+    LsbFirst,
+    /// The most significant bit of a byte is read first, and the first bit read becomes
+    /// the most significant bit of the value.
+    // kept for formats with big-endian bit fields, e.g. AV1 or JPEG 2000 codestream headers,
+    // and covered by unit tests until a loader needs it
+    #[allow(dead_code)]
+    MsbFirst,
+}
+
+/// Reads bit fields which may cross byte boundaries from a byte buffer.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    order: BitOrder,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8], order: BitOrder) -> BitReader<'a> {
+        BitReader {
+            data: data,
+            pos: 0,
+            order: order,
+        }
+    }
+
+    /// Returns the number of bits which are left in the buffer.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    /// Reads an `n`-bit value, where `n` is at most 32.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if there are less than `n` bits left; no bits
+    /// are consumed in this case.
+    pub fn read_bits(&mut self, n: u32) -> io::Result<u32> {
+        assert!(n <= 32, "cannot read more than 32 bits at once");
+        if self.remaining() < n as usize {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "not enough bits left"));
+        }
+
+        let mut result = 0u32;
+        for i in 0..n {
+            let byte = self.data[self.pos / 8];
+            let offset = (self.pos % 8) as u32;
+            self.pos += 1;
+            match self.order {
+                BitOrder::LsbFirst => result |= (((byte >> offset) & 1) as u32) << i,
+                BitOrder::MsbFirst => result = (result << 1) | ((byte >> (7 - offset)) & 1) as u32,
+            }
+        }
+        Ok(result)
+    }
+
+    /// Reads a single bit as a flag.
+    #[inline]
+    pub fn read_flag(&mut self) -> io::Result<bool> {
+        self.read_bits(1).map(|b| b == 1)
+    }
+}
+
+/// A cursor over a prefix of an input stream which keeps track of how many bytes the reader
+/// attempted to read beyond the end of the available data.
+///
+/// This is used to tell a truncated image from one which has not been received completely yet.
+pub struct PrefixCursor<'a> {
+    inner: Cursor<&'a [u8]>,
+    needed: u64
+}
+
+impl<'a> PrefixCursor<'a> {
+    pub fn new(data: &'a [u8]) -> PrefixCursor<'a> {
+        PrefixCursor {
+            inner: Cursor::new(data),
+            needed: 0
+        }
+    }
+
+    /// Returns a lower bound of the length of the stream which is needed for all of the reads
+    /// performed so far to succeed. It is never less than the length of the prefix plus one.
+    pub fn needed(&self) -> u64 {
+        cmp::max(self.needed, self.inner.get_ref().len() as u64 + 1)
+    }
+
+    /// Replaces an `UnexpectedEndOfFile` error with a `NeedMoreData` one, as the end of
+    /// the prefix is not necessarily the end of the stream.
+    pub fn map_eof(&self, e: Error, min_len: usize) -> Error {
+        match e {
+            Error::UnexpectedEndOfFile(..) => Error::NeedMoreData {
+                at_least: cmp::max(self.needed() as usize, min_len)
+            },
+            e => e
+        }
+    }
+
+    #[inline]
+    fn demand(&mut self, n: usize) {
+        self.needed = cmp::max(self.needed, self.inner.position().saturating_add(n as u64));
+    }
+}
+
+impl<'a> Read for PrefixCursor<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.inner.read(buf));
+        if n == 0 && !buf.is_empty() {
+            self.demand(1);
+        }
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.inner.position() + buf.len() as u64 > self.inner.get_ref().len() as u64 {
+            self.demand(buf.len());
+        }
+        self.inner.read_exact(buf)
+    }
+}
+
+impl<'a> BufRead for PrefixCursor<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.inner.position() >= self.inner.get_ref().len() as u64 {
+            self.demand(1);
+        }
+        self.inner.fill_buf()
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<'a> Seek for PrefixCursor<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // seeking past the end of the prefix means that the data there is needed
+        let n = try!(self.inner.seek(pos));
+        self.needed = cmp::max(self.needed, n);
+        Ok(n)
+    }
+}
+
+/// A buffered reader which uses a borrowed buffer, so the buffer can be reused for several
+/// input streams.
+///
+/// It works like `std::io::BufReader`, including discarding the buffered data on seeking.
+pub struct BorrowedBufReader<'a, R> {
+    inner: R,
+    buf: &'a mut [u8],
+    pos: usize,
+    cap: usize
+}
+
+impl<'a, R: Read> BorrowedBufReader<'a, R> {
+    /// Wraps the given reader. The buffer must not be empty.
+    pub fn new(inner: R, buf: &'a mut [u8]) -> BorrowedBufReader<'a, R> {
+        debug_assert!(!buf.is_empty());
+        BorrowedBufReader {
+            inner: inner,
+            buf: buf,
+            pos: 0,
+            cap: 0
+        }
+    }
+}
+
+impl<'a, R: Read> Read for BorrowedBufReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // large reads bypass the buffer if it is empty
+        if self.pos == self.cap && buf.len() >= self.buf.len() {
+            return self.inner.read(buf);
+        }
+        let n = {
+            let mut available = try!(self.fill_buf());
+            try!(available.read(buf))
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<'a, R: Read> BufRead for BorrowedBufReader<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.cap {
+            self.cap = try!(self.inner.read(self.buf));
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.cap])
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.cap);
+    }
+}
+
+impl<'a, R: Read + Seek> Seek for BorrowedBufReader<'a, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let result = match pos {
+            // the underlying reader is ahead by the number of buffered bytes
+            SeekFrom::Current(n) => {
+                let remainder = (self.cap - self.pos) as i64;
+                match n.checked_sub(remainder) {
+                    Some(n) => try!(self.inner.seek(SeekFrom::Current(n))),
+                    None => {
+                        try!(self.inner.seek(SeekFrom::Current(-remainder)));
+                        self.pos = self.cap;
+                        try!(self.inner.seek(SeekFrom::Current(n)))
+                    }
+                }
+            }
+            pos => try!(self.inner.seek(pos))
+        };
+        self.pos = 0;
+        self.cap = 0;
+        Ok(result)
+    }
+}
+
+/// Returns the bit pattern of a floating-point number, with negative zero mapped to
+/// positive zero.
+#[inline]
+fn f64_bits(value: f64) -> u64 {
+    if value == 0.0 { 0 } else { value.to_bits() }
+}
+
+/// Compares floating-point numbers by their bit patterns, so that equality is reflexive
+/// even for NaNs. Positive and negative zeros are equal.
+#[inline]
+pub fn f64_eq(a: f64, b: f64) -> bool {
+    f64_bits(a) == f64_bits(b)
+}
+
+/// Feeds a floating-point number to the hasher by its bit pattern, consistently with `f64_eq()`.
+pub fn hash_f64<H: Hasher>(value: f64, state: &mut H) {
+    f64_bits(value).hash(state);
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Computes the 64-bit FNV-1a hash of the given data.
+///
+/// Unlike `DefaultHasher`, this algorithm is fixed, so the results may be persisted.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET_BASIS, |h, &b| (h ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::{BitReader, BitOrder, PrefixCursor, BorrowedBufReader, fnv1a};
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_bit_reader_lsb_first() {
+        let data = [0b1010_1101, 0b0000_0011, 0b1111_0000];
+        let mut r = BitReader::new(&data, BitOrder::LsbFirst);
+        assert_eq!(r.read_flag().unwrap(), true);
+        assert_eq!(r.read_bits(3).unwrap(), 0b110);
+        // crosses the byte boundary
+        assert_eq!(r.read_bits(6).unwrap(), 0b11_1010);
+        assert_eq!(r.read_bits(0).unwrap(), 0);
+        assert_eq!(r.remaining(), 14);
+        assert_eq!(r.read_bits(14).unwrap(), 0b1111_0000_0000_00);
+        assert_eq!(r.remaining(), 0);
+        assert_eq!(r.read_flag().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_bit_reader_msb_first() {
+        let data = [0b1010_1101, 0b0000_0011, 0b1111_0000];
+        let mut r = BitReader::new(&data, BitOrder::MsbFirst);
+        assert_eq!(r.read_flag().unwrap(), true);
+        assert_eq!(r.read_bits(3).unwrap(), 0b010);
+        assert_eq!(r.read_bits(6).unwrap(), 0b1101_00);
+        // fewer than 8 bits remain in the current byte
+        assert_eq!(r.read_bits(7).unwrap(), 0b00_0011_1);
+        // not enough bits left; nothing is consumed
+        assert_eq!(r.read_bits(8).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(r.read_bits(7).unwrap(), 0b111_0000);
+    }
+
+    #[test]
+    fn test_bit_reader_full_width() {
+        let data = [0x78, 0x56, 0x34, 0x12, 0xff];
+        assert_eq!(BitReader::new(&data, BitOrder::LsbFirst).read_bits(32).unwrap(), 0x12345678);
+        assert_eq!(BitReader::new(&data, BitOrder::MsbFirst).read_bits(32).unwrap(), 0x78563412);
+
+        let mut r = BitReader::new(&data, BitOrder::LsbFirst);
+        r.read_bits(4).unwrap();
+        assert_eq!(r.read_bits(32).unwrap(), 0xf1234567);
+    }
+
+    #[test]
+    fn test_prefix_cursor() {
+        use std::io::{Read, BufRead};
+        use byteorder::{ReadBytesExt, BigEndian};
+
+        let data = [1, 2, 3, 4, 5, 6];
+        let mut r = PrefixCursor::new(&data);
+        assert_eq!(r.read_u32::<BigEndian>().unwrap(), 0x01020304);
+        assert_eq!(r.needed(), 7);
+        assert_eq!(r.read_u32::<BigEndian>().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(r.needed(), 8);
+
+        let mut r = PrefixCursor::new(&data);
+        let mut buf = [0u8; 16];
+        assert_eq!(r.read(&mut buf).unwrap(), 6);
+        assert_eq!(r.needed(), 7);
+        assert_eq!(r.fill_buf().unwrap().len(), 0);
+        assert_eq!(r.needed(), 7);
+    }
+
+    #[test]
+    fn test_borrowed_buf_reader() {
+        use std::io::{Read, BufRead, Seek, SeekFrom, Cursor};
+
+        let data: Vec<u8> = (0..32).collect();
+        let mut buf = [0u8; 8];
+
+        let mut r = BorrowedBufReader::new(Cursor::new(&data), &mut buf);
+        assert_eq!(r.fill_buf().unwrap(), &data[..8]);
+        r.consume(3);
+        assert_eq!(r.seek(SeekFrom::Current(2)).unwrap(), 5);
+        assert_eq!(r.fill_buf().unwrap(), &data[5..13]);
+        assert_eq!(r.seek(SeekFrom::Current(-5)).unwrap(), 0);
+
+        let mut out = [0u8; 16];
+        r.read_exact(&mut out).unwrap();
+        assert_eq!(&out, &data[..16]);
+        assert_eq!(r.seek(SeekFrom::End(-4)).unwrap(), 28);
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, &data[28..]);
+        drop(r);
+
+        // the buffer is reused with another reader
+        let mut r = BorrowedBufReader::new(Cursor::new(&data[16..]), &mut buf);
+        assert_eq!(r.fill_buf().unwrap(), &data[16..24]);
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "serde")]
+
+extern crate immeta;
+extern crate serde_json;
+
+use immeta::GenericMetadata;
+
+fn check_round_trip(path: &str) {
+    let md = immeta::load_from_file(path).unwrap();
+    let json = serde_json::to_string(&md).unwrap();
+    let decoded: GenericMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, md, "{}", path);
+}
+
+#[test]
+fn test_round_trip() {
+    for path in &[
+        "tests/images/owlet.jpg",
+        "tests/images/exif.jpg",
+        "tests/images/owlet.png",
+        "tests/images/animated.png",
+        "tests/images/drop.gif",
+        "tests/images/cherry.webp",
+        "tests/images/animated.webp",
+        "tests/images/squares.bmp",
+        "tests/images/squares.tif",
+    ] {
+        check_round_trip(path);
+    }
+}
+
+#[test]
+fn test_common_metadata() {
+    let md = immeta::load_from_file("tests/images/squares.png").unwrap();
+    let json = serde_json::to_value(&md.common()).unwrap();
+    assert_eq!(json, serde_json::json!({
+        "format": "Png",
+        "dimensions": { "width": 8, "height": 6 },
+        "color_depth": 24,
+        "mime_type": "image/png",
+        "is_animated": false,
+        "frame_count": 1
+    }));
+}
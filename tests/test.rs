@@ -1,8 +1,15 @@
 extern crate immeta;
 
-use immeta::Dimensions;
-use immeta::formats::{png, gif, jpeg};
-use immeta::markers::{Png, Gif, Jpeg, Webp};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
+use immeta::{Dimensions, ImageFormat, LoadableMetadata, Resolution, GenericMetadata};
+use immeta::formats::{png, gif, jpeg, bmp, tiff, webp, ico, pnm, exr, qoi, heif, svg, dds, psd, wbmp, jp2};
+use immeta::common::tiff::{TiffReader, Tag, EntryType, entry_types};
+use immeta::common::exif::{Orientation, EntryValue};
+use immeta::common::riff::ChunkId;
+use immeta::markers::{MetadataMarker, Png, Gif, Jpeg, Webp, Bmp, Tiff, Ico, Pnm, Exr, Qoi, Heif, Svg, Dds, Psd, Wbmp, Jp2};
 
 const OWLET_DIM: Dimensions = Dimensions {
     width: 1280,
@@ -19,12 +26,88 @@ const CHERRY_DIM: Dimensions = Dimensions {
     height: 772
 };
 
+const SQUARES_DIM: Dimensions = Dimensions {
+    width: 8,
+    height: 6
+};
+
+#[test]
+fn test_dimensions() {
+    assert_eq!(OWLET_DIM.aspect_ratio(), 1280.0 / 857.0);
+    assert!(OWLET_DIM.is_landscape());
+    assert!(!OWLET_DIM.is_portrait());
+    assert!(!OWLET_DIM.is_square());
+    assert_eq!(OWLET_DIM.megapixels(), 1.09696);
+
+    let portrait = Dimensions { width: 6, height: 8 };
+    assert_eq!(portrait.aspect_ratio(), 0.75);
+    assert!(portrait.is_portrait());
+    assert!(!portrait.is_landscape());
+
+    let square = Dimensions { width: 16, height: 16 };
+    assert_eq!(square.aspect_ratio(), 1.0);
+    assert!(square.is_square());
+
+    let empty = Dimensions { width: 16, height: 0 };
+    assert_eq!(empty.aspect_ratio(), f64::INFINITY);
+    assert_eq!(empty.megapixels(), 0.0);
+
+    assert_eq!(OWLET_DIM.scaled_by_pixel_aspect(1.0), OWLET_DIM);
+    assert_eq!(OWLET_DIM.scaled_by_pixel_aspect(0.5), Dimensions { width: 640, height: 857 });
+    assert_eq!(portrait.scaled_by_pixel_aspect(1.1), Dimensions { width: 7, height: 8 });
+    assert_eq!(portrait.scaled_by_pixel_aspect(0.0), portrait);
+    assert_eq!(portrait.scaled_by_pixel_aspect(f64::NAN), portrait);
+}
+
+#[test]
+fn test_dimensions_scaling() {
+    let max = Dimensions { width: 200, height: 200 };
+    assert_eq!(OWLET_DIM.fit_within(max), Dimensions { width: 200, height: 134 });
+    assert_eq!(OWLET_DIM.fit_within(Dimensions { width: 2000, height: 100 }),
+               Dimensions { width: 149, height: 100 });
+    // images are never scaled up
+    assert_eq!(SQUARES_DIM.fit_within(max), SQUARES_DIM);
+    assert_eq!(OWLET_DIM.fit_within(OWLET_DIM), OWLET_DIM);
+
+    // very wide and very tall images keep at least one pixel
+    let wide = Dimensions { width: 100000, height: 10 };
+    assert_eq!(wide.fit_within(max), Dimensions { width: 200, height: 1 });
+    let tall = Dimensions { width: 3, height: 100000 };
+    assert_eq!(tall.fit_within(max), Dimensions { width: 1, height: 200 });
+
+    assert_eq!(OWLET_DIM.scale_to_width(640), Dimensions { width: 640, height: 429 });
+    assert_eq!(OWLET_DIM.scale_to_height(100), Dimensions { width: 149, height: 100 });
+    assert_eq!(Dimensions { width: 1, height: 2 }.scale_to_width(u32::MAX),
+               Dimensions { width: u32::MAX, height: u32::MAX });
+    assert_eq!(Dimensions { width: 0, height: 8 }.scale_to_width(16), Dimensions { width: 16, height: 8 });
+}
+
+#[test]
+fn test_zero_dimensions() {
+    assert_eq!(Dimensions::new(6, 8), Some(Dimensions { width: 6, height: 8 }));
+    assert_eq!(Dimensions::new(0, 8), None);
+    assert_eq!(Dimensions::new(6, 0), None);
+
+    // PNG width and QOI height are set to zero
+    let mut png = fs::read("tests/images/squares.png").unwrap();
+    png[16..20].copy_from_slice(&[0; 4]);
+    let mut qoi = fs::read("tests/images/squares.qoi").unwrap();
+    qoi[8..12].copy_from_slice(&[0; 4]);
+    for data in &[png, qoi] {
+        match immeta::load_from_buf(data) {
+            Err(immeta::Error::InvalidFormat(ref msg, _)) => assert_eq!(msg, "zero image dimension"),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+}
+
 #[test]
 fn test_jpeg() {
     let md = immeta::load_from_file("tests/images/owlet.jpg").unwrap();
 
     assert_eq!(md.mime_type(), "image/jpeg");
     assert_eq!(md.dimensions(), OWLET_DIM);
+    assert_eq!(md.display_dimensions(), OWLET_DIM);
 
     // let md = Jpeg::from(md).ok()
     let md = md.into::<Jpeg>().ok().expect("not JPEG metadata");
@@ -34,6 +117,353 @@ fn test_jpeg() {
     assert_eq!(md.entropy_coding, jpeg::EntropyCoding::Huffman);
     assert!(md.baseline);
     assert!(!md.differential);
+    assert_eq!(md.num_components, 3);
+    assert_eq!(md.subsampling, Some(jpeg::Subsampling::Ratio420));
+    assert_eq!(md.scan_count, 1);
+    assert_eq!(md.quantization_tables, 2);
+    assert_eq!(md.huffman_tables, 4);
+    assert!(md.exif.is_none());
+    assert_eq!(md.orientation(), None);
+    assert!(md.comments.is_empty());
+    assert_eq!(md.icc_profile, None);
+
+    let density = md.density.expect("no JFIF density");
+    assert_eq!(density.units, jpeg::DensityUnit::None);
+    assert_eq!((density.x_density, density.y_density), (1, 1));
+    assert_eq!(density.dpi(), None);
+}
+
+#[test]
+fn test_jpeg_comments() {
+    let md = Jpeg::load_from_file("tests/images/comment.jpg").unwrap();
+
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.comments, vec!["Created by immeta".to_owned(), "caf\u{fffd}".to_owned()]);
+}
+
+#[test]
+fn test_jpeg_dnl() {
+    let md = Jpeg::load_from_file("tests/images/dnl.jpg").unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.num_components, 1);
+    assert_eq!(md.scan_count, 1);
+
+    // remove the DNL marker
+    let data = fs::read("tests/images/dnl.jpg").unwrap();
+    let mut broken = data[..data.len() - 8].to_vec();
+    broken.extend_from_slice(b"\xff\xd9");
+    let err = Jpeg::load_from_buf(&broken).unwrap_err();
+    assert!(err.to_string().contains("DNL marker is missing"), "{}", err);
+}
+
+#[test]
+fn test_jpeg_progressive() {
+    let md = Jpeg::load_from_file("tests/images/progressive.jpg").unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.coding_process, jpeg::CodingProcess::DctProgressive);
+    assert!(!md.baseline);
+    assert_eq!(md.scan_count, 3);
+    // each scan has its own Huffman tables
+    assert_eq!(md.huffman_tables, 3);
+    assert_eq!(md.quantization_tables, 0);
+}
+
+#[test]
+fn test_jpeg_tables() {
+    let data = fs::read("tests/images/owlet.jpg").unwrap();
+    let dqt = data.windows(2).position(|w| w == b"\xff\xdb").unwrap();
+
+    // merge the first two DQT segments into one defining both tables
+    let mut merged = data[..dqt].to_vec();
+    merged.extend_from_slice(&[0xff, 0xdb, 0x00, 0x84]);
+    merged.extend_from_slice(&data[dqt + 4..dqt + 69]);
+    merged.extend_from_slice(&data[dqt + 73..dqt + 138]);
+    merged.extend_from_slice(&data[dqt + 138..]);
+    let md = Jpeg::load_from_buf(&merged).unwrap();
+    assert_eq!(md.quantization_tables, 2);
+
+    // a 16-bit table does not fit into the segment
+    let mut broken = data.clone();
+    broken[dqt + 4] |= 0x10;
+    let err = Jpeg::load_from_buf(&broken).unwrap_err();
+    assert!(err.to_string().contains("truncated table"), "{}", err);
+}
+
+#[test]
+fn test_jpeg_icc_profile() {
+    let md = Jpeg::load_from_file("tests/images/icc.jpg").unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.icc_profile, Some(b"first part, second part".to_vec()));
+
+    // remove the second chunk, the profile is incomplete now
+    let data = fs::read("tests/images/icc.jpg").unwrap();
+    let mut broken = data[..2].to_vec();
+    broken.extend_from_slice(&data[2 + 29..]);
+    let md = Jpeg::load_from_buf(&broken).unwrap();
+    assert_eq!(md.icc_profile, None);
+}
+
+#[test]
+fn test_jpeg_xmp() {
+    let md = Jpeg::load_from_file("tests/images/xmp.jpg").unwrap();
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert!(md.exif.is_none());
+
+    let xmp = md.xmp.expect("no XMP packet");
+    assert!(xmp.starts_with("<x:xmpmeta"));
+    assert!(xmp.contains(r#"xmpNote:HasExtendedXMP="0123456789ABCDEF0123456789ABCDEF""#));
+    let extended_xmp = md.extended_xmp.expect("no extended XMP packet");
+    assert!(extended_xmp.starts_with("<x:xmpmeta"));
+    assert!(extended_xmp.contains(r#"dc:format="image/jpeg""#));
+    assert!(extended_xmp.ends_with("</x:xmpmeta>"));
+
+    // change the GUID of the first extended XMP chunk, the packet is incomplete now
+    let mut data = fs::read("tests/images/xmp.jpg").unwrap();
+    let guid_offset = data.windows(32).position(|w| w == b"0123456789ABCDEF0123456789ABCDEF").unwrap();
+    let guid_offset = guid_offset + 32 +
+        data[guid_offset + 32..].windows(32).position(|w| w == b"0123456789ABCDEF0123456789ABCDEF").unwrap();
+    data[guid_offset] = b'F';
+    let md = Jpeg::load_from_buf(&data).unwrap();
+    assert!(md.xmp.is_some());
+    assert_eq!(md.extended_xmp, None);
+
+    let md = Jpeg::load_from_file("tests/images/owlet.jpg").unwrap();
+    assert_eq!(md.xmp, None);
+    assert_eq!(md.extended_xmp, None);
+}
+
+#[test]
+fn test_jpeg_large_segment_skip() {
+    use immeta::LoadableMetadata;
+
+    // insert a large APP15 segment after SOI, it is skipped by seeking when possible
+    let data = fs::read("tests/images/owlet.jpg").unwrap();
+    let mut large = data[..2].to_vec();
+    large.extend_from_slice(&[0xff, 0xef, 0xff, 0xff]);
+    large.extend(vec![0xaa; 0xfffd]);
+    large.extend_from_slice(&data[2..]);
+
+    let streamed = jpeg::Metadata::load(&mut &large[..]).unwrap();
+    let seeked = Jpeg::load_from_buf(&large).unwrap();
+    assert_eq!(streamed.dimensions, OWLET_DIM);
+    assert_eq!(streamed, seeked);
+
+    // truncated segments must be detected in both cases
+    let truncated = &large[..0x8000];
+    assert!(jpeg::Metadata::load(&mut &truncated[..]).is_err());
+    assert!(Jpeg::load_from_buf(truncated).is_err());
+    let md = jpeg::Metadata::load_from_seek(&mut io::BufReader::with_capacity(16, io::Cursor::new(truncated)));
+    assert!(md.is_err());
+}
+
+#[test]
+fn test_jpeg_adobe_transform() {
+    let md = Jpeg::load_from_file("tests/images/adobe.jpg").unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.num_components, 4);
+    assert_eq!(md.adobe_transform, Some(jpeg::AdobeTransform::Ycck));
+
+    let mut data = include_bytes!("images/adobe.jpg").to_vec();
+    data[17] = 0;
+    assert_eq!(Jpeg::load_from_buf(&data).unwrap().adobe_transform, Some(jpeg::AdobeTransform::Unknown));
+    // unknown transform values are ignored
+    data[17] = 7;
+    assert_eq!(Jpeg::load_from_buf(&data).unwrap().adobe_transform, None);
+
+    assert_eq!(Jpeg::load_from_file("tests/images/owlet.jpg").unwrap().adobe_transform, None);
+}
+
+#[test]
+fn test_jpeg_restart_interval() {
+    let md = Jpeg::load_from_file("tests/images/dri.jpg").unwrap();
+    assert_eq!(md.scan_count, 2);
+    assert_eq!(md.restart_interval, Some(2));
+
+    // only the DRI segment before the frame header remains
+    let data = include_bytes!("images/dri.jpg");
+    let mut data = data.to_vec();
+    data.drain(21..27);
+    assert_eq!(Jpeg::load_from_buf(&data).unwrap().restart_interval, Some(4));
+
+    assert_eq!(Jpeg::load_from_file("tests/images/owlet.jpg").unwrap().restart_interval, None);
+}
+
+#[test]
+fn test_jpeg_density() {
+    let md = Jpeg::load_from_file("tests/images/density.jpg").unwrap();
+
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.density, Some(jpeg::JfifDensity {
+        units: jpeg::DensityUnit::DotsPerCm,
+        x_density: 100,
+        y_density: 50
+    }));
+    assert_eq!(md.density.unwrap().dpi(), Some((254.0, 127.0)));
+}
+
+#[test]
+fn test_jpeg_exif() {
+    let md = immeta::load_from_file("tests/images/exif.jpg").unwrap();
+    assert_eq!(md.dimensions(), Dimensions { width: 16, height: 8 });
+    assert_eq!(md.display_dimensions(), Dimensions { width: 8, height: 16 });
+
+    let md = md.into::<Jpeg>().ok().expect("not JPEG metadata");
+
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+    assert_eq!(md.orientation(), Some(Orientation::Rotate90));
+
+    assert_eq!(md.density, None);
+
+    let exif = md.exif.expect("no EXIF metadata");
+    assert_eq!(exif.ifd0.len(), 3);
+
+    let make = exif.ifd0.get(&0x010f).unwrap();
+    assert_eq!(make.entry_type(), EntryType::Ascii);
+    assert_eq!(make.values::<entry_types::Ascii>().unwrap().unwrap(), vec!["immeta"]);
+
+    let orientation = exif.get(0x0112).unwrap();
+    assert_eq!(orientation.values::<entry_types::Short>().unwrap().unwrap(), vec![6]);
+    assert!(orientation.values::<entry_types::Long>().is_none());
+
+    let exif_ifd = exif.exif_ifd.as_ref().expect("no EXIF sub-IFD");
+    assert_eq!(exif_ifd.len(), 4);
+    assert_eq!(exif.get(0x829a).unwrap().rational_values().unwrap().unwrap(), vec![1.0 / 250.0]);
+    assert!(exif.get(0xa002).unwrap().rational_values().is_none());
+    assert_eq!(exif_ifd.get(&0x9000).unwrap().data(), b"0230");
+    assert_eq!(exif.get(0xa002).unwrap().values::<entry_types::Long>().unwrap().unwrap(), vec![16]);
+    assert_eq!(exif.get(0xa003).unwrap().values::<entry_types::Long>().unwrap().unwrap(), vec![8]);
+
+    assert!(exif.gps_ifd.is_none());
+    assert_eq!(exif.gps(), None);
+
+    assert!(exif.ifd1.is_none());
+    assert_eq!(exif.thumbnail(), None);
+
+    let tags = exif.tags();
+    assert_eq!(tags.len(), 7);
+    assert_eq!(tags[&Tag::Make], EntryValue::Ascii(vec!["immeta".to_owned()]));
+    assert_eq!(tags[&Tag::Orientation], EntryValue::Shorts(vec![6]));
+    assert_eq!(tags[&Tag::ExposureTime], EntryValue::Rationals(vec![(1, 250)]));
+    assert_eq!(tags[&Tag::ExifVersion], EntryValue::Undefined(b"0230".to_vec()));
+    assert_eq!(tags[&Tag::PixelXDimension], EntryValue::Longs(vec![16]));
+    assert_eq!(exif.get(0xa003).unwrap().value().unwrap(), EntryValue::Longs(vec![8]));
+}
+
+#[test]
+fn test_jpeg_exif_broken_sub_ifds() {
+    // the EXIF sub-IFD pointer of IFD0 is a big-endian Long at offset 0x2e
+    let data = fs::read("tests/images/exif.jpg").unwrap();
+    let mut dangling = data.clone();
+    dangling[0x36..0x3a].copy_from_slice(&[0x7f, 0xff, 0xff, 0xff]);
+    let mut ifd_type = data.clone();
+    ifd_type[0x30..0x32].copy_from_slice(&[0, 13]);
+    for data in &[dangling, ifd_type] {
+        let md = Jpeg::load_from_buf(data).unwrap();
+        assert_eq!(md.orientation(), Some(Orientation::Rotate90));
+        let exif = md.exif.expect("no EXIF metadata");
+        assert!(exif.exif_ifd.is_none());
+        assert_eq!(exif.orientation(), Some(Orientation::Rotate90));
+    }
+
+    // the GPS sub-IFD pointer of IFD0 is a little-endian Long at offset 22
+    let mut data = fs::read("tests/images/gps.jpg").unwrap();
+    data[30..34].copy_from_slice(&[0xff, 0xff, 0xff, 0x7f]);
+    let exif = Jpeg::load_from_buf(&data).unwrap().exif.expect("no EXIF metadata");
+    assert!(exif.gps_ifd.is_none());
+    assert_eq!(exif.gps(), None);
+    assert!(exif.ifd0.contains_key(&0x8825));
+}
+
+#[test]
+fn test_jpeg_exif_maker_note() {
+    let mut data = fs::read("tests/images/makernote.jpg").unwrap();
+    let md = Jpeg::load_from_buf(&data).unwrap();
+    let exif = md.exif.expect("no EXIF metadata");
+    let note = exif.maker_note().expect("no maker note");
+    assert_eq!(note.len(), 24);
+    assert!(note.starts_with(b"Nikon\0\x02\x10"));
+    assert_eq!(exif.maker_note_vendor(), Some("Nikon"));
+
+    // Canon maker notes have no signature, so they are detected by the camera make
+    let make = data.windows(18).position(|w| w == b"NIKON CORPORATION\0").unwrap();
+    data[make..make + 18].copy_from_slice(b"Canon\0\0\0\0\0\0\0\0\0\0\0\0\0");
+    let note = data.windows(6).position(|w| w == b"Nikon\0").unwrap();
+    data[note..note + 6].copy_from_slice(&[0; 6]);
+    let exif = Jpeg::load_from_buf(&data).unwrap().exif.unwrap();
+    assert_eq!(exif.maker_note_vendor(), Some("Canon"));
+
+    let md = Jpeg::load_from_file("tests/images/exif.jpg").unwrap();
+    let exif = md.exif.unwrap();
+    assert_eq!(exif.maker_note(), None);
+    assert_eq!(exif.maker_note_vendor(), None);
+}
+
+#[test]
+fn test_jpeg_exif_thumbnail() {
+    let md = Jpeg::load_from_file("tests/images/exif_thumbnail.jpg").unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 16, height: 8 });
+
+    let exif = md.exif.expect("no EXIF metadata");
+    assert_eq!(exif.ifd1.as_ref().expect("no IFD1").len(), 3);
+
+    let thumbnail = exif.thumbnail().expect("no thumbnail");
+    assert_eq!(thumbnail.dimensions, Dimensions { width: 4, height: 2 });
+    assert_eq!(&thumbnail.data[..2], b"\xff\xd8");
+    assert_eq!(Some(&thumbnail.data), exif.thumbnail_data.as_ref());
+
+    // thumbnail data which is not a JPEG image is ignored
+    let mut exif = exif;
+    exif.thumbnail_data = Some(vec![0; 16]);
+    assert_eq!(exif.thumbnail(), None);
+}
+
+#[test]
+fn test_jpeg_exif_gps() {
+    let md = Jpeg::load_from_file("tests/images/gps.jpg").unwrap();
+    assert_eq!(md.dimensions, SQUARES_DIM);
+
+    let exif = md.exif.expect("no EXIF metadata");
+    assert!(exif.exif_ifd.is_none());
+    assert_eq!(exif.gps_ifd.as_ref().expect("no GPS sub-IFD").len(), 7);
+
+    let gps = exif.gps().expect("no GPS location");
+    assert!((gps.latitude - 48.858216).abs() < 1e-6, "{}", gps.latitude);
+    assert!((gps.longitude + 2.2945).abs() < 1e-6, "{}", gps.longitude);
+    assert_eq!(gps.altitude, Some(-35.0));
+
+    // altitude is optional, but coordinate references are not
+    let mut exif = exif;
+    exif.gps_ifd.as_mut().unwrap().remove(&0x0006);
+    assert_eq!(exif.gps().unwrap().altitude, None);
+    exif.gps_ifd.as_mut().unwrap().remove(&0x0003);
+    assert_eq!(exif.gps(), None);
+}
+
+#[test]
+fn test_resolution() {
+    let md = immeta::load_from_file("tests/images/resolution.jpg").unwrap();
+    assert_eq!(md.resolution(), Some(Resolution { x_dpi: 300.0, y_dpi: 150.0 }));
+
+    // centimeters are converted to inches, and the absolute unit is not a resolution
+    // (the value of ResolutionUnit entry, the last one in IFD0)
+    let mut data = fs::read("tests/images/resolution.jpg").unwrap();
+    assert_eq!(data[54], 2);
+    data[54] = 3;
+    assert_eq!(Jpeg::load_from_buf(&data).unwrap().exif.unwrap().dpi(), Some((762.0, 381.0)));
+    data[54] = 1;
+    assert_eq!(immeta::load_from_buf(&data).unwrap().resolution(), None);
+
+    // JFIF density takes precedence over EXIF
+    let md = immeta::load_from_file("tests/images/density.jpg").unwrap();
+    assert_eq!(md.resolution(), Some(Resolution { x_dpi: 254.0, y_dpi: 127.0 }));
+
+    let md = immeta::load_from_file("tests/images/squares.png").unwrap();
+    let (x, y) = md.as_ref::<Png>().unwrap().physical_dimensions.unwrap().dpi().unwrap();
+    assert_eq!(md.resolution(), Some(Resolution { x_dpi: x, y_dpi: y }));
+
+    assert_eq!(immeta::load_from_file("tests/images/owlet.png").unwrap().resolution(), None);
+    assert_eq!(immeta::load_from_file("tests/images/owlet.jpg").unwrap().resolution(), None);
+    assert_eq!(immeta::load_from_file("tests/images/drop.gif").unwrap().resolution(), None);
 }
 
 #[test]
@@ -47,9 +477,184 @@ fn test_png() {
     assert_eq!(md.dimensions, OWLET_DIM);
     assert_eq!(md.color_type, png::ColorType::Rgb);
     assert_eq!(md.color_depth, 24);
+    assert_eq!(md.bit_depth, 8);
     assert_eq!(md.compression_method, png::CompressionMethod::DeflateInflate);
     assert_eq!(md.filter_method, png::FilterMethod::AdaptiveFiltering);
     assert_eq!(md.interlace_method, png::InterlaceMethod::Disabled);
+    assert_eq!(md.physical_dimensions, None);
+    assert_eq!(md.palette_entries, None);
+    assert!(!md.is_transparent());
+    assert_eq!(md.animation, None);
+    assert!(!md.is_animated());
+    assert_eq!(md.icc_profile, None);
+    assert_eq!(md.gamma, Some(0.45455));
+    assert_eq!(md.rendering_intent, None);
+    assert_eq!(md.chromaticities, Some(png::Chromaticities {
+        white_x: 0.3127,
+        white_y: 0.329,
+        red_x: 0.64,
+        red_y: 0.33,
+        green_x: 0.3,
+        green_y: 0.6,
+        blue_x: 0.15,
+        blue_y: 0.06
+    }));
+}
+
+#[test]
+fn test_png_load_basic() {
+    let data = fs::read("tests/images/palette.png").unwrap();
+    let full = Png::load_from_buf(&data).unwrap();
+
+    // signature and IHDR chunk without its CRC
+    let mut r = &data[..29];
+    let md = png::Metadata::load_basic(&mut r).unwrap();
+    assert!(r.is_empty());
+    assert_eq!(md, png::BasicMetadata {
+        dimensions: full.dimensions,
+        color_type: full.color_type,
+        color_depth: full.color_depth,
+        bit_depth: full.bit_depth,
+        compression_method: full.compression_method,
+        filter_method: full.filter_method,
+        interlace_method: full.interlace_method
+    });
+
+    assert!(png::Metadata::load_basic(&mut &data[..28]).is_err());
+    assert!(png::Metadata::load_basic(&mut &b"GIF89a"[..]).is_err());
+}
+
+#[test]
+fn test_png_chunk_reader() {
+    let data: &[u8] = include_bytes!("images/palette.png");
+    let mut r = png::ChunkReader::new(data).unwrap();
+
+    let mut chunks = Vec::new();
+    while let Some(chunk) = r.next() {
+        let chunk = chunk.unwrap();
+        chunks.push((chunk.chunk_type(), chunk.length(), chunk.offset(), chunk.is_critical()));
+        chunk.verify_crc().unwrap();
+    }
+    assert_eq!(chunks, vec![
+        (*b"IHDR", 13, 8, true),
+        (*b"PLTE", 15, 33, true),
+        (*b"tRNS", 4, 60, false),
+        (*b"IDAT", 31, 76, true),
+        (*b"IEND", 0, 119, true),
+    ]);
+
+    // chunk data can be read directly, and unread data is skipped
+    let mut r = png::ChunkReader::new(data).unwrap();
+    r.next().unwrap().unwrap();
+    let mut plte = r.next().unwrap().unwrap();
+    let mut palette = Vec::new();
+    plte.read_to_end(&mut palette).unwrap();
+    assert_eq!(palette.len(), 15);
+    assert_eq!(&palette[..3], &[0xff, 0, 0]);
+    plte.verify_crc().unwrap();
+    assert_eq!(&r.next().unwrap().unwrap().chunk_type(), b"tRNS");
+
+    // break the PLTE chunk data
+    let mut data = data.to_vec();
+    data[41] ^= 0xff;
+    let mut r = png::ChunkReader::new(&data[..]).unwrap();
+    r.next().unwrap().unwrap().verify_crc().unwrap();
+    let err = r.next().unwrap().unwrap().verify_crc().unwrap_err();
+    assert_eq!(err.offset(), Some(33));
+
+    assert!(png::ChunkReader::new(&b"GIF89a"[..]).is_err());
+}
+
+#[test]
+fn test_png_srgb() {
+    // insert an sRGB chunk right after IHDR; CRC is not checked
+    let mut data = include_bytes!("images/squares.png").to_vec();
+    let tail = data.split_off(33);
+    data.extend_from_slice(b"\0\0\0\x01sRGB\x01\0\0\0\0");
+    data.extend_from_slice(&tail);
+
+    let md = Png::load_from_buf(&data).unwrap();
+    assert_eq!(md.rendering_intent, Some(png::RenderingIntent::RelativeColorimetric));
+    assert_eq!(md.gamma, None);
+    assert_eq!(md.chromaticities, None);
+
+    data[41] = 4;
+    assert!(Png::load_from_buf(&data).is_err());
+}
+
+#[test]
+fn test_png_significant_bits() {
+    let md = Png::load_from_file("tests/images/sbit.png").unwrap();
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.color_type, png::ColorType::Rgb);
+    assert_eq!(md.color_depth, 48);
+    assert_eq!(md.bit_depth, 16);
+    assert_eq!(md.significant_bits, Some(vec![12, 12, 12]));
+
+    // sBIT chunk of RGB images must contain three values
+    let mut data = fs::read("tests/images/sbit.png").unwrap();
+    data[36] = 2;
+    data.remove(43);
+    let err = Png::load_from_buf(&data).unwrap_err();
+    assert!(err.to_string().contains("invalid sBIT chunk length"), "{}", err);
+
+    assert_eq!(Png::load_from_file("tests/images/owlet.png").unwrap().significant_bits, None);
+}
+
+#[test]
+fn test_png_icc_profile() {
+    let md = Png::load_from_file("tests/images/icc.png").unwrap();
+
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    let profile = md.icc_profile.expect("no ICC profile");
+    assert_eq!(profile.name, "Test profile");
+    if cfg!(feature = "flate2") {
+        assert!(!profile.compressed);
+        assert_eq!(profile.data, b"not really an ICC profile".repeat(4));
+    } else {
+        assert!(profile.compressed);
+        assert_eq!(&profile.data[..2], b"\x78\x9c");  // zlib header
+    }
+}
+
+#[test]
+fn test_png_palette() {
+    let md = Png::load_from_file("tests/images/palette.png").unwrap();
+
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.color_type, png::ColorType::Indexed);
+    assert_eq!(md.color_depth, 8);
+    assert_eq!(md.bit_depth, 8);
+    assert_eq!(md.palette_entries, Some(5));
+    assert!(md.has_transparency);
+    assert!(md.is_transparent());
+}
+
+#[test]
+fn test_png_animated() {
+    let md = Png::load_from_file("tests/images/animated.png").unwrap();
+
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.color_type, png::ColorType::RgbAlpha);
+    assert_eq!(md.animation, Some(png::ApngMetadata { num_frames: 3, num_plays: 0 }));
+    assert!(md.is_animated());
+    assert!(md.is_transparent());
+}
+
+#[test]
+fn test_png_physical_dimensions() {
+    let md = Png::load_from_file("tests/images/squares.png").unwrap();
+
+    assert_eq!(md.dimensions, SQUARES_DIM);
+
+    let pd = md.physical_dimensions.expect("no physical dimensions");
+    assert_eq!(pd.pixels_per_unit_x, 2835);
+    assert_eq!(pd.pixels_per_unit_y, 3780);
+    assert_eq!(pd.unit, png::PhysicalUnit::Meter);
+
+    let (x, y) = pd.dpi().unwrap();
+    assert_eq!(x.round(), 72.0);
+    assert_eq!(y.round(), 96.0);
 }
 
 #[test]
@@ -64,13 +669,24 @@ fn test_gif_plain() {
     assert_eq!(md.dimensions, OWLET_DIM);
     assert_eq!(md.global_color_table, Some(gif::ColorTable {
         size: 256,
-        sorted: false
+        sorted: false,
+        colors: None
     }));
     assert_eq!(md.color_resolution, 256);
     assert_eq!(md.background_color_index, 0);
     assert_eq!(md.pixel_aspect_ratio, 0);
     assert_eq!(md.frames_number(), 1);
     assert_eq!(md.is_animated(), false);
+    assert_eq!(md.loop_count(), None);
+    assert_eq!(md.total_duration_ms(), 0);
+    assert_eq!(md.frames(), vec![gif::FrameInfo {
+        dimensions: OWLET_DIM,
+        offset: (0, 0),
+        delay_ms: 0,
+        disposal_method: gif::DisposalMethod::None,
+        transparent_color_index: None,
+        interlace: false
+    }]);
     assert_eq!(md.blocks, vec![
         gif::Block::GraphicControlExtension(gif::GraphicControlExtension {
             disposal_method: gif::DisposalMethod::None,
@@ -80,7 +696,8 @@ fn test_gif_plain() {
         }),
         gif::Block::ApplicationExtension(gif::ApplicationExtension {
             application_identifier: *b"ImageMag",
-            authentication_code: *b"ick"
+            authentication_code: *b"ick",
+            data: b"gamma=0.45455".to_vec()
         }),
         gif::Block::ImageDescriptor(gif::ImageDescriptor {
             left: 0, top: 0,
@@ -88,7 +705,25 @@ fn test_gif_plain() {
             local_color_table: None,
             interlace: false
         })
-    ])
+    ]);
+    assert_eq!(md.display_dimensions(), OWLET_DIM);
+    match md.blocks[1] {
+        gif::Block::ApplicationExtension(ref ext) => {
+            assert_eq!(ext.kind(), gif::ApplicationKind::Unknown);
+            assert!(!ext.is_netscape());
+        }
+        ref b => panic!("unexpected block: {:?}", b)
+    }
+
+    // pixel aspect ratio is stored as (par * 64 - 15)
+    let mut data = fs::read("tests/images/owlet.gif").unwrap();
+    data[12] = 49;
+    let md = immeta::load_from_buf(&data).unwrap();
+    assert_eq!(md.display_dimensions(), OWLET_DIM);
+    data[12] = 113;
+    let md = immeta::load_from_buf(&data).unwrap();
+    assert_eq!(md.display_dimensions(), Dimensions { width: 2560, height: 857 });
+    assert_eq!(md.into::<Gif>().unwrap().display_dimensions(), Dimensions { width: 2560, height: 857 });
 }
 
 #[test]
@@ -103,13 +738,24 @@ fn test_gif_animated() {
     assert_eq!(md.dimensions, DROP_DIM);
     assert_eq!(md.global_color_table, Some(gif::ColorTable {
         size: 256,
-        sorted: false
+        sorted: false,
+        colors: None
     }));
     assert_eq!(md.color_resolution, 128);
     assert_eq!(md.background_color_index, 255);
     assert_eq!(md.pixel_aspect_ratio, 0);
     assert_eq!(md.frames_number(), 30);
     assert_eq!(md.is_animated(), true);
+    assert_eq!(md.loop_count(), Some(0));
+    assert_eq!(md.total_duration_ms(), 30 * 70);
+
+    match md.blocks[0] {
+        gif::Block::ApplicationExtension(ref ext) => {
+            assert_eq!(ext.kind(), gif::ApplicationKind::Netscape);
+            assert!(ext.is_netscape());
+        }
+        ref b => panic!("unexpected block: {:?}", b)
+    }
 
     let mut blocks = md.blocks.iter();
 
@@ -117,13 +763,16 @@ fn test_gif_animated() {
         blocks.next().unwrap(),
         &gif::Block::ApplicationExtension(gif::ApplicationExtension {
             application_identifier: *b"NETSCAPE",
-            authentication_code: *b"2.0"
+            authentication_code: *b"2.0",
+            data: vec![1, 0, 0]
         })
     );
 
     assert_eq!(
         blocks.next().unwrap(),
-        &gif::Block::CommentExtension(gif::CommentExtension)
+        &gif::Block::CommentExtension(gif::CommentExtension {
+            text: "Created with GIMP on a Mac".into()
+        })
     );
 
     for i in 0..30 {
@@ -160,6 +809,210 @@ fn test_gif_animated() {
     }
 
     assert!(blocks.next().is_none());
+
+    let frames = md.frames();
+    assert_eq!(frames.len(), 30);
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame, &gif::FrameInfo {
+            dimensions: DROP_DIM,
+            offset: (0, 0),
+            delay_ms: 70,
+            disposal_method: if i == 29 {
+                gif::DisposalMethod::None
+            } else {
+                gif::DisposalMethod::DoNotDispose
+            },
+            transparent_color_index: Some(255),
+            interlace: false
+        });
+    }
+}
+
+#[test]
+fn test_gif_transparent_color() {
+    use immeta::LoadOptions;
+    use std::io::Cursor;
+
+    let data = fs::read("tests/images/drop.gif").unwrap();
+    let options = LoadOptions::default().load_color_tables(true);
+    let md = gif::Metadata::load_from_seek_with_options(&mut Cursor::new(&data), &options).unwrap();
+    let colors = md.global_color_table.as_ref().unwrap().colors.clone().unwrap();
+    assert_eq!(md.transparent_color(0), Some(colors[255]));
+    assert_eq!(md.transparent_color(29), Some(colors[255]));
+    assert_eq!(md.transparent_color(30), None);
+
+    // color tables are not loaded by default
+    let md = Gif::load_from_buf(&data).unwrap();
+    assert_eq!(md.transparent_color(0), None);
+
+    let mut data = b"GIF89a\x02\0\x02\0\x80\0\0".to_vec();
+    data.extend_from_slice(&[0, 0, 0, 255, 255, 255]);  // global color table
+    // a frame with a local color table
+    data.extend_from_slice(&[0x21, 0xf9, 4, 1, 0, 0, 1, 0]);
+    data.extend_from_slice(&[0x2c, 0, 0, 0, 0, 2, 0, 2, 0, 0x80, 10, 20, 30, 40, 50, 60, 2, 1, 0, 0]);
+    // a frame which uses the global color table
+    data.extend_from_slice(&[0x21, 0xf9, 4, 1, 0, 0, 0, 0]);
+    data.extend_from_slice(&[0x2c, 0, 0, 0, 0, 2, 0, 2, 0, 0, 2, 1, 0, 0]);
+    // a frame without transparency
+    data.extend_from_slice(&[0x2c, 0, 0, 0, 0, 2, 0, 2, 0, 0, 2, 1, 0, 0, 0x3b]);
+    let md = gif::Metadata::load_with_options(&mut &data[..], &options).unwrap();
+    assert_eq!(md.frames_number(), 3);
+    assert_eq!(md.transparent_color(0), Some([40, 50, 60]));
+    assert_eq!(md.transparent_color(1), Some([0, 0, 0]));
+    assert_eq!(md.transparent_color(2), None);
+}
+
+#[test]
+fn test_gif_frame_dimensions() {
+    let md = Gif::load_from_file("tests/images/owlet.gif").unwrap();
+    assert_eq!(md.canvas_dimensions(), OWLET_DIM);
+    assert_eq!(md.first_frame_dimensions(), Some(OWLET_DIM));
+
+    // a single frame which is smaller than the logical screen
+    let mut data = b"GIF89a\x04\0\x03\0\0\0\0".to_vec();
+    data.extend_from_slice(&[0x21, 0xfe, 2, b'h', b'i', 0]);
+    data.extend_from_slice(&[0x2c, 1, 0, 1, 0, 2, 0, 1, 0, 0, 2, 1, 0, 0, 0x3b]);
+    let md = Gif::load_from_buf(&data).unwrap();
+    assert_eq!(md.canvas_dimensions(), Dimensions { width: 4, height: 3 });
+    assert_eq!(md.first_frame_dimensions(), Some(Dimensions { width: 2, height: 1 }));
+
+    let md = Gif::load_from_buf(b"GIF89a\x04\0\x03\0\0\0\0\x3b").unwrap();
+    assert_eq!(md.first_frame_dimensions(), None);
+}
+
+#[test]
+fn test_structural_hash() {
+    use std::collections::HashSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |path| immeta::load_from_file(path).unwrap().structural_hash();
+    assert_eq!(hash("tests/images/owlet.png"), hash("tests/images/owlet.png"));
+    assert!(hash("tests/images/owlet.png") != hash("tests/images/owlet.gif"));
+    assert!(hash("tests/images/owlet.png") != hash("tests/images/squares.png"));
+    assert!(hash("tests/images/owlet.gif") != hash("tests/images/drop.gif"));
+    // the hash is stable: FNV-1a of "PNG\0", 1280, 857, Some(24) and 1 frame
+    assert_eq!(hash("tests/images/owlet.png"), 0xd9d06ff022c62c47);
+
+    // other metadata is not taken into account
+    let mut data = fs::read("tests/images/squares.png").unwrap();
+    let md = immeta::load_from_buf(&data).unwrap();
+    let tail = data.split_off(33);
+    data.extend_from_slice(b"\0\0\0\x01sRGB\x01\0\0\0\0");
+    data.extend_from_slice(&tail);
+    let srgb = immeta::load_from_buf(&data).unwrap();
+    assert!(md != srgb);
+    assert_eq!(md.structural_hash(), srgb.structural_hash());
+
+    let mut set = HashSet::new();
+    set.insert(Gif::load_from_file("tests/images/drop.gif").unwrap());
+    set.insert(Gif::load_from_file("tests/images/drop.gif").unwrap());
+    set.insert(Gif::load_from_file("tests/images/owlet.gif").unwrap());
+    assert_eq!(set.len(), 2);
+
+    // metadata containing floating-point values is hashable too
+    fn std_hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+    for path in &["tests/images/owlet.png", "tests/images/exif.jpg", "tests/images/squares.svg"] {
+        let md = immeta::load_from_file(path).unwrap();
+        assert_eq!(std_hash(&md), std_hash(&immeta::load_from_file(path).unwrap()));
+    }
+    let md = Png::load_from_file("tests/images/owlet.png").unwrap();
+    let mut other = md.clone();
+    other.chromaticities.as_mut().unwrap().white_x = 0.3;
+    assert!(std_hash(&md) != std_hash(&other));
+    let md = svg::Metadata { dimensions: None, view_box: Some((0.0, 0.0, 1.0, 1.0)) };
+    let other = svg::Metadata { dimensions: None, view_box: Some((-0.0, 0.0, 1.0, 1.0)) };
+    assert_eq!(md, other);
+    assert_eq!(std_hash(&md), std_hash(&other));
+
+    // floating-point values are compared bitwise, so all metadata types implement `Eq`
+    let mut set = HashSet::new();
+    for path in &["tests/images/owlet.png", "tests/images/owlet.png", "tests/images/exif.jpg",
+                  "tests/images/exif.jpg", "tests/images/squares.svg", "tests/images/owlet.gif"] {
+        set.insert(immeta::load_from_file(path).unwrap());
+    }
+    assert_eq!(set.len(), 4);
+    let mut md = Png::load_from_file("tests/images/owlet.png").unwrap();
+    md.chromaticities.as_mut().unwrap().white_x = f64::NAN;
+    md.gamma = Some(f64::NAN);
+    assert_eq!(md, md.clone());
+    assert_eq!(std_hash(&md), std_hash(&md.clone()));
+    let mut other = md.clone();
+    other.gamma = Some(0.5);
+    assert!(md != other);
+    other.gamma = None;
+    assert!(md != other);
+}
+
+#[test]
+fn test_validate() {
+    for path in &["tests/images/owlet.png", "tests/images/palette.png", "tests/images/sbit.png",
+                  "tests/images/animated.png", "tests/images/owlet.gif", "tests/images/drop.gif",
+                  "tests/images/owlet.jpg", "tests/images/squares.bmp"] {
+        immeta::load_from_file(path).unwrap().validate().unwrap();
+    }
+
+    let png = Png::load_from_file("tests/images/owlet.png").unwrap();
+    let mut md = png.clone();
+    md.color_depth = 12;
+    assert!(immeta::GenericMetadata::Png(md).validate().is_err());
+    let mut md = png.clone();
+    md.color_type = png::ColorType::Indexed;
+    md.color_depth = 8;
+    assert!(md.validate().is_err());
+    md.palette_entries = Some(256);
+    assert!(md.validate().is_ok());
+    md.color_depth = 4;
+    assert!(md.validate().is_err());
+    let mut md = png.clone();
+    md.color_type = png::ColorType::Grayscale;
+    md.color_depth = 8;
+    md.palette_entries = Some(16);
+    assert!(md.validate().is_err());
+    let mut md = png.clone();
+    md.significant_bits = Some(vec![8, 9, 8]);
+    assert!(md.validate().is_err());
+    let mut md = png.clone();
+    md.bit_depth = 16;
+    assert!(md.validate().is_err());
+    md.color_depth = 48;
+    assert!(md.validate().is_ok());
+    md.bit_depth = 4;
+    md.color_depth = 12;
+    assert!(md.validate().is_err());
+
+    let gif = Gif::load_from_file("tests/images/drop.gif").unwrap();
+    let mut md = gif.clone();
+    md.dimensions.width -= 1;
+    let err = immeta::GenericMetadata::Gif(md).validate().unwrap_err();
+    assert!(err.to_string().contains("logical screen"), "{}", err);
+    let mut md = gif.clone();
+    md.global_color_table.as_mut().unwrap().size = 128;
+    assert!(md.validate().is_err());
+}
+
+#[test]
+fn test_gif_exceeds_canvas() {
+    let md = Gif::load_from_file("tests/images/drop.gif").unwrap();
+    let id = md.blocks.iter().filter_map(|b| match *b {
+        gif::Block::ImageDescriptor(ref id) => Some(id),
+        _ => None
+    }).next().unwrap();
+    assert!(!id.exceeds_canvas(md.dimensions));
+    assert!(id.exceeds_canvas(Dimensions { width: md.dimensions.width - 1, ..md.dimensions }));
+    assert!(id.exceeds_canvas(Dimensions { height: md.dimensions.height - 1, ..md.dimensions }));
+
+    let mut id = id.clone();
+    id.left = 1;
+    assert!(id.exceeds_canvas(md.dimensions));
+    id.left = u16::max_value();
+    id.width = u16::max_value();
+    assert!(!id.exceeds_canvas(Dimensions { width: 131070, height: u32::max_value() }));
+    assert!(id.exceeds_canvas(Dimensions { width: 131069, height: u32::max_value() }));
 }
 
 #[test]
@@ -173,3 +1026,1311 @@ fn test_webp() {
 
     println!("{:?}", md);
 }
+
+#[test]
+fn test_webp_lossless() {
+    let md = immeta::load_from_file("tests/images/lossless.webp").unwrap();
+
+    assert_eq!(md.mime_type(), "image/webp");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+
+    let md = md.into::<Webp>().ok().expect("not WEBP metadata");
+    assert_eq!(md, webp::Metadata::VP8L(webp::VP8LMetadata {
+        dimensions: SQUARES_DIM,
+        has_alpha: true,
+        version: 0,
+        chunks: vec![(ChunkId(*b"VP8L"), 8)]
+    }));
+    assert!(md.animation().is_none());
+}
+
+#[test]
+fn test_webp_vp8_interframe() {
+    let mut data = b"RIFF\x16\0\0\0WEBPVP8 \x0a\0\0\0".to_vec();
+    data.extend_from_slice(&[0x01, 0, 0, 0x9d, 0x01, 0x2a, 8, 0, 8, 0]);
+    assert_eq!(data.len(), 30);
+    match immeta::load_from_buf(&data) {
+        Err(immeta::Error::InvalidFormat(ref msg, _)) => assert!(msg.contains("key frame"), "{}", msg),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    data[20] = 0;
+    assert_eq!(Webp::load_from_buf(&data).unwrap().dimensions(), Dimensions { width: 8, height: 8 });
+}
+
+#[test]
+fn test_webp_chunks() {
+    let md = Webp::load_from_file("tests/images/metadata.webp").unwrap();
+    assert_eq!(md.chunks(), &[
+        (ChunkId(*b"VP8X"), 10), (ChunkId(*b"VP8L"), 8), (ChunkId(*b"EXIF"), 26), (ChunkId(*b"XMP "), 58)
+    ][..]);
+
+    let md = Webp::load_from_file("tests/images/cherry.webp").unwrap();
+    assert_eq!(md.chunks(), &[(ChunkId(*b"VP8 "), 176952)][..]);
+
+    // unknown chunks are listed too
+    let mut data = include_bytes!("images/metadata.webp").to_vec();
+    data.extend_from_slice(b"ABCD\x03\0\0\0xyz\0");
+    let riff_len = data.len() as u32 - 8;
+    data[4..8].copy_from_slice(&[riff_len as u8, (riff_len >> 8) as u8, 0, 0]);
+    let md = Webp::load_from_buf(&data).unwrap();
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.chunks().len(), 5);
+    assert_eq!(md.chunks()[4], (ChunkId(*b"ABCD"), 3));
+}
+
+#[test]
+fn test_bmp() {
+    let md = immeta::load_from_file("tests/images/squares.bmp").unwrap();
+
+    assert_eq!(md.mime_type(), "image/bmp");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+
+    let md = md.into::<Bmp>().ok().expect("not BMP metadata");
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.header_version, bmp::HeaderVersion::Info);
+    assert_eq!(md.bit_depth, 24);
+    assert_eq!(md.compression, bmp::Compression::Rgb);
+    assert!(!md.top_down);
+}
+
+#[test]
+fn test_bmp_top_down() {
+    let mut data = std::fs::read("tests/images/squares.bmp").unwrap();
+    // height is stored at offset 22, make it negative
+    data[22..26].copy_from_slice(&(-6i32).to_le_bytes());
+
+    let md = Bmp::load_from_buf(&data).unwrap();
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert!(md.top_down);
+
+    // unknown DIB header size
+    data[14] = 12;
+    assert!(Bmp::load_from_buf(&data).is_err());
+}
+
+#[test]
+fn test_tiff() {
+    for &path in &["tests/images/squares.tif", "tests/images/squares_be.tif"] {
+        let md = immeta::load_from_file(path).unwrap();
+
+        assert_eq!(md.mime_type(), "image/tiff");
+        assert_eq!(md.dimensions(), SQUARES_DIM);
+
+        let md = md.into::<Tiff>().ok().expect("not TIFF metadata");
+        assert_eq!(md.dimensions, SQUARES_DIM);
+        assert_eq!(md.bits_per_sample, vec![8, 8, 8]);
+        assert_eq!(md.samples_per_pixel, 3);
+        assert_eq!(md.photometric_interpretation, tiff::PhotometricInterpretation::Rgb);
+        assert_eq!(md.compression, tiff::Compression::None);
+        assert_eq!(md.pages.len(), 1);
+        assert_eq!(md.pages[0].dimensions, SQUARES_DIM);
+    }
+}
+
+#[test]
+fn test_tiff_multipage() {
+    let md = immeta::load_from_file("tests/images/multipage.tif").unwrap();
+
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(24));
+
+    let md = md.into::<Tiff>().ok().expect("not TIFF metadata");
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.pages, vec![
+        tiff::Page {
+            dimensions: SQUARES_DIM,
+            bits_per_sample: vec![8, 8, 8],
+            samples_per_pixel: 3,
+            photometric_interpretation: tiff::PhotometricInterpretation::Rgb,
+            compression: tiff::Compression::None
+        },
+        tiff::Page {
+            dimensions: Dimensions { width: 4, height: 3 },
+            bits_per_sample: vec![1],
+            samples_per_pixel: 1,
+            photometric_interpretation: tiff::PhotometricInterpretation::WhiteIsZero,
+            compression: tiff::Compression::CcittGroup4
+        },
+    ]);
+
+    // the last IFD points back to the first one
+    let mut data = include_bytes!("images/multipage.tif").to_vec();
+    let n = data.len();
+    data[n - 4] = 14;
+    assert!(Tiff::load_from_buf(&data).is_err());
+}
+
+#[test]
+fn test_ico() {
+    let md = immeta::load_from_file("tests/images/icon.ico").unwrap();
+
+    assert_eq!(md.mime_type(), "image/x-icon");
+    assert_eq!(md.dimensions(), Dimensions { width: 256, height: 256 });
+    assert_eq!(md.color_depth(), Some(32));
+
+    let md = md.into::<Ico>().ok().expect("not ICO metadata");
+    assert_eq!(md.icon_type, ico::IconType::Icon);
+    assert_eq!(md.entries, vec![
+        ico::IconEntry {
+            dimensions: Dimensions { width: 16, height: 16 },
+            color_count: 0,
+            bit_count: Some(8),
+            hotspot: None,
+            size: 40,
+            offset: 38
+        },
+        ico::IconEntry {
+            dimensions: Dimensions { width: 256, height: 256 },
+            color_count: 0,
+            bit_count: Some(32),
+            hotspot: None,
+            size: 24,
+            offset: 78
+        }
+    ]);
+    assert_eq!(md.largest(), Dimensions { width: 256, height: 256 });
+}
+
+#[test]
+fn test_ico_cursor() {
+    let mut data = include_bytes!("images/icon.ico").to_vec();
+    data[2] = 2;
+
+    let md = Ico::load_from_buf(&data).unwrap();
+    assert_eq!(md.icon_type, ico::IconType::Cursor);
+    assert_eq!(md.entries[0].bit_count, None);
+    assert_eq!(md.entries[0].hotspot, Some((1, 8)));
+
+    // unknown resource type
+    data[2] = 3;
+    assert!(Ico::load_from_buf(&data).is_err());
+
+    // no images
+    data[2] = 1;
+    data[4] = 0;
+    assert!(Ico::load_from_buf(&data).is_err());
+}
+
+#[test]
+fn test_pnm() {
+    let md = immeta::load_from_file("tests/images/squares.ppm").unwrap();
+
+    assert_eq!(md.mime_type(), "image/x-portable-anymap");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(24));
+
+    let md = md.into::<Pnm>().ok().expect("not PNM metadata");
+    assert_eq!(md, pnm::Metadata {
+        dimensions: SQUARES_DIM,
+        format: pnm::Format::Pixmap,
+        binary: true,
+        max_value: Some(255)
+    });
+}
+
+#[test]
+fn test_pnm_header_whitespace() {
+    let md = Pnm::load_from_buf(b"P2#comment\r\t8 # width\n\n 6#height\n#\n65535\n0 0").unwrap();
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.format, pnm::Format::Graymap);
+    assert!(!md.binary);
+    assert_eq!(md.max_value, Some(65535));
+    assert_eq!(md.bits_per_sample(), 16);
+
+    let md = Pnm::load_from_buf(b"P4 8 6\n\x00\xff").unwrap();
+    assert_eq!(md.format, pnm::Format::Bitmap);
+    assert!(md.binary);
+    assert_eq!(md.max_value, None);
+    assert_eq!(md.bits_per_sample(), 1);
+
+    // a comment directly after the last header value would be a part of the raster
+    assert!(Pnm::load_from_buf(b"P5 8 6 255#c\n").is_err());
+    // missing whitespace after the magic number
+    assert!(Pnm::load_from_buf(b"P58 6 255\n").is_err());
+    // invalid maximum color values
+    assert!(Pnm::load_from_buf(b"P5 8 6 0\n").is_err());
+    assert!(Pnm::load_from_buf(b"P5 8 6 65536\n").is_err());
+    // non-numeric values and truncated headers
+    assert!(Pnm::load_from_buf(b"P3 8 x 255\n").is_err());
+    assert!(Pnm::load_from_buf(b"P3 8 6").is_err());
+    assert!(Pnm::load_from_buf(b"P7 8 6 255\n").is_err());
+}
+
+#[test]
+fn test_exr() {
+    let md = immeta::load_from_file("tests/images/squares.exr").unwrap();
+
+    assert_eq!(md.mime_type(), "image/x-exr");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(16 * 3 + 32));
+
+    let md = md.into::<Exr>().ok().expect("not OpenEXR metadata");
+    assert_eq!(md.dimensions, SQUARES_DIM);
+    assert_eq!(md.version, 2);
+    assert!(!md.tiled);
+    assert!(!md.deep);
+    assert!(!md.multipart);
+    assert_eq!(md.compression, exr::Compression::Zip);
+    let channels: Vec<_> = md.channels.iter().map(|c| (&*c.name, c.pixel_type)).collect();
+    assert_eq!(channels, vec![
+        ("B", exr::PixelType::Half),
+        ("G", exr::PixelType::Half),
+        ("R", exr::PixelType::Half),
+        ("Z", exr::PixelType::Float),
+    ]);
+    assert_eq!(md.channels[0].sampling, (1, 1));
+    assert!(!md.channels[0].linear);
+
+    let data = include_bytes!("images/squares.exr");
+    // truncated header
+    assert!(Exr::load_from_buf(&data[..100]).is_err());
+    // invalid magic number
+    let mut data = data.to_vec();
+    data[0] = 0;
+    assert!(Exr::load_from_buf(&data).is_err());
+    // data window from i32::MIN to i32::MAX, 2^32 pixels wide
+    data[0] = 0x76;
+    data[0x97..0x9b].copy_from_slice(&[0, 0, 0, 0x80]);
+    data[0x9f..0xa3].copy_from_slice(&[0xff, 0xff, 0xff, 0x7f]);
+    assert!(Exr::load_from_buf(&data).is_err());
+    data[0x97..0x9b].copy_from_slice(&[1, 0, 0, 0x80]);
+    assert_eq!(Exr::load_from_buf(&data).unwrap().dimensions.width, u32::max_value());
+}
+
+#[test]
+fn test_qoi() {
+    let md = immeta::load_from_file("tests/images/squares.qoi").unwrap();
+
+    assert_eq!(md.mime_type(), "image/qoi");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(32));
+
+    let md = md.into::<Qoi>().ok().expect("not QOI metadata");
+    assert_eq!(md, qoi::Metadata {
+        dimensions: SQUARES_DIM,
+        channels: 4,
+        colorspace: qoi::Colorspace::SrgbLinearAlpha
+    });
+
+    let mut data = include_bytes!("images/squares.qoi").to_vec();
+    data[12] = 5;
+    assert!(Qoi::load_from_buf(&data).is_err());
+    data[12] = 3;
+    data[13] = 2;
+    assert!(Qoi::load_from_buf(&data).is_err());
+    assert!(Qoi::load_from_buf(&data[..10]).is_err());
+}
+
+#[test]
+fn test_avif() {
+    let md = immeta::load_from_file("tests/images/squares.avif").unwrap();
+
+    assert_eq!(md.format(), ImageFormat::Avif);
+    assert_eq!(md.mime_type(), "image/avif");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), None);
+
+    // dimensions of the primary item are used, not those of the thumbnail
+    let md = md.into::<Heif>().ok().expect("not HEIF metadata");
+    assert_eq!(md, heif::Metadata {
+        dimensions: SQUARES_DIM,
+        major_brand: "avif".into(),
+        minor_version: 0,
+        compatible_brands: vec!["avif".into(), "mif1".into(), "miaf".into()]
+    });
+    assert!(md.is_avif());
+}
+
+#[test]
+fn test_heif() {
+    let data = include_bytes!("images/squares.avif");
+
+    let mut heic = data.to_vec();
+    heic[8..12].copy_from_slice(b"heic");
+    heic[16..20].copy_from_slice(b"heic");
+    assert_eq!(immeta::detect_format_from_buf(&heic).unwrap(), ImageFormat::Heif);
+    let md = immeta::load_from_buf(&heic).unwrap();
+    assert_eq!(md.format(), ImageFormat::Heif);
+    assert_eq!(md.mime_type(), "image/heif");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+
+    // generic major brand, AVIF is only mentioned among compatible brands
+    let mut mif1 = data.to_vec();
+    mif1[8..12].copy_from_slice(b"mif1");
+    assert_eq!(immeta::detect_format_from_buf(&mif1).unwrap(), ImageFormat::Heif);
+    assert_eq!(immeta::load_from_buf(&mif1).unwrap().format(), ImageFormat::Avif);
+
+    let mut mp4 = data.to_vec();
+    mp4[8..12].copy_from_slice(b"isom");
+    mp4[16..28].copy_from_slice(b"isomiso2mp41");
+    assert!(Heif::load_from_buf(&mp4).is_err());
+
+    assert!(Heif::load_from_buf(&data[..40]).is_err());
+    assert!(Heif::load_from_buf(&data[28..]).is_err());
+}
+
+#[test]
+fn test_svg() {
+    let md = immeta::load_from_file("tests/images/squares.svg").unwrap();
+
+    assert_eq!(md.mime_type(), "image/svg+xml");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), None);
+
+    let md = md.into::<Svg>().ok().expect("not SVG metadata");
+    assert_eq!(md, svg::Metadata {
+        dimensions: Some(SQUARES_DIM),
+        view_box: Some((0.0, 0.0, 16.0, 12.0))
+    });
+
+    let load = |s: &str| Svg::load_from_buf(s.as_bytes());
+
+    let md = load("<svg viewBox='0,0 40.5,30'/>").unwrap();
+    assert_eq!(md.dimensions, Some((41u32, 30u32).into()));
+    assert_eq!(md.view_box, Some((0.0, 0.0, 40.5, 30.0)));
+
+    let md = load("<svg:svg width='30pt' height='1in'>").unwrap();
+    assert_eq!(md.dimensions, Some((40u32, 96u32).into()));
+
+    // relative sizes are resolved through the view box aspect ratio
+    let md = load(r#"<svg width="100%" height="20mm" viewBox="0 0 200 100">"#).unwrap();
+    assert_eq!(md.dimensions, Some((151u32, 76u32).into()));
+    let md = load(r#"<svg width="1e2" viewBox="0 0 200 100">"#).unwrap();
+    assert_eq!(md.dimensions, Some((100u32, 50u32).into()));
+
+    let md = load(r#"<svg width="100%" height="2em"><rect/></svg>"#).unwrap();
+    assert_eq!(md, svg::Metadata { dimensions: None, view_box: None });
+    assert!(immeta::load_from_buf(b"<svg width='50%'/>").is_err());
+    let md = load("<svg width='0.4' height='2.6'/>").unwrap();
+    assert_eq!(md.dimensions, Some(Dimensions { width: 1, height: 3 }));
+    assert_eq!(load("<svg viewBox='0 0 0 0'/>").unwrap().dimensions, None);
+    assert_eq!(load("<svg width='0' height='10'/>").unwrap().dimensions, None);
+
+    assert!(load("<?xml version='1.0'?><html><svg width='1' height='1'/></html>").is_err());
+    assert!(load("<svg width='1' height='1'").is_err());
+    assert!(load("").is_err());
+
+    // the root element must be found near the beginning of the document
+    let mut long = String::from("<?xml version='1.0'?>");
+    for _ in 0..10000 {
+        long.push_str("<!-- padding -->");
+    }
+    long.push_str("<svg width='1' height='1'/>");
+    assert!(load(&long).is_err());
+}
+
+#[test]
+fn test_dds() {
+    let md = immeta::load_from_file("tests/images/squares.dds").unwrap();
+
+    assert_eq!(md.mime_type(), "image/vnd-ms.dds");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), None);
+
+    let md = md.into::<Dds>().ok().expect("not DDS metadata");
+    assert_eq!(md, dds::Metadata {
+        dimensions: SQUARES_DIM,
+        mipmap_count: 4,
+        format: dds::PixelFormat::Dx10,
+        bit_count: None,
+        dxgi_format: Some(98),
+        array_size: 1,
+        is_cubemap: false
+    });
+
+    let data = fs::read("tests/images/squares.dds").unwrap();
+
+    // a cube map declared through the caps
+    let mut cube = data.clone();
+    cube[4 + 108 + 1] = 0xfe;
+    let md = Dds::load_from_buf(&cube).unwrap();
+    assert!(md.is_cubemap);
+
+    // a DXT1 image without the DX10 header and the mipmap count flag
+    let mut dxt1 = data[..128].to_vec();
+    dxt1[4 + 80..4 + 84].copy_from_slice(b"DXT1");
+    dxt1[4 + 6] = 0;
+    let md = Dds::load_from_buf(&dxt1).unwrap();
+    assert_eq!(md.format, dds::PixelFormat::Dxt1);
+    assert_eq!(md.mipmap_count, 1);
+    assert_eq!(md.dxgi_format, None);
+
+    // an uncompressed 32-bit RGBA image
+    let mut rgba = data[..128].to_vec();
+    rgba[4 + 76..4 + 80].copy_from_slice(&[0x41, 0, 0, 0]);
+    rgba[4 + 84] = 32;
+    let md = Dds::load_from_buf(&rgba).unwrap();
+    assert_eq!(md.format, dds::PixelFormat::Uncompressed);
+    assert_eq!(md.bit_count, Some(32));
+    assert_eq!(immeta::load_from_buf(&rgba).unwrap().color_depth(), Some(32));
+
+    let mut other = dxt1.clone();
+    other[4 + 80..4 + 84].copy_from_slice(b"ETC2");
+    assert_eq!(Dds::load_from_buf(&other).unwrap().format, dds::PixelFormat::Other(*b"ETC2"));
+
+    let mut bad = data.clone();
+    bad[4] = 100;
+    assert!(Dds::load_from_buf(&bad).is_err());
+    assert!(Dds::load_from_buf(&data[..140]).is_err());
+    assert!(Dds::load_from_buf(&data[..100]).is_err());
+}
+
+#[test]
+fn test_psd() {
+    let md = immeta::load_from_file("tests/images/squares.psd").unwrap();
+
+    assert_eq!(md.mime_type(), "image/vnd.adobe.photoshop");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(32));
+
+    let md = md.into::<Psd>().ok().expect("not PSD metadata");
+    assert_eq!(md, psd::Metadata {
+        dimensions: SQUARES_DIM,
+        version: 1,
+        channels: 4,
+        depth: 8,
+        color_mode: psd::ColorMode::Rgb
+    });
+    assert!(!md.is_psb());
+
+    let data = include_bytes!("images/squares.psd").to_vec();
+
+    let mut psb = data.clone();
+    psb[5] = 2;
+    psb[25] = 9;
+    let md = Psd::load_from_buf(&psb).unwrap();
+    assert!(md.is_psb());
+    assert_eq!(md.color_mode, psd::ColorMode::Lab);
+
+    let mut bad = data.clone();
+    bad[5] = 3;
+    assert!(Psd::load_from_buf(&bad).is_err());
+    let mut bad = data.clone();
+    bad[23] = 7;
+    assert!(Psd::load_from_buf(&bad).is_err());
+    let mut bad = data.clone();
+    bad[25] = 5;
+    assert!(Psd::load_from_buf(&bad).is_err());
+    assert!(Psd::load_from_buf(&data[..20]).is_err());
+}
+
+#[test]
+fn test_wbmp() {
+    let md = Wbmp::load_from_file("tests/images/squares.wbmp").unwrap();
+    assert_eq!(md, wbmp::Metadata { dimensions: SQUARES_DIM });
+
+    // WBMP images are only loaded by extension, as they have no signature
+    let md = immeta::load_from_file_with_hint("tests/images/squares.wbmp").unwrap();
+    assert_eq!(md.format(), ImageFormat::Wbmp);
+    assert_eq!(md.mime_type(), "image/vnd.wap.wbmp");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), Some(1));
+    assert!(immeta::detect_format_from_file("tests/images/squares.wbmp").is_err());
+
+    // multi-byte dimensions
+    let md = Wbmp::load_from_buf(&[0x00, 0x00, 0x82, 0x00, 0x81, 0x00]).unwrap();
+    assert_eq!(md.dimensions, Dimensions { width: 256, height: 128 });
+
+    assert!(Wbmp::load_from_buf(&[0x01, 0x00, 0x08, 0x06]).is_err());
+    assert!(Wbmp::load_from_buf(&[0x00, 0x80, 0x08, 0x06]).is_err());
+    assert!(Wbmp::load_from_buf(&[0x00, 0x00, 0x08, 0x86]).is_err());
+}
+
+#[test]
+fn test_jp2() {
+    let md = Jp2::load_from_file("tests/images/squares.jp2").unwrap();
+    assert_eq!(md, jp2::Metadata { dimensions: SQUARES_DIM, components: 3, jp2_container: true });
+
+    // the image area is offset on the reference grid
+    let md = Jp2::load_from_file("tests/images/squares.j2k").unwrap();
+    assert_eq!(md, jp2::Metadata { dimensions: SQUARES_DIM, components: 3, jp2_container: false });
+
+    let md = immeta::load_from_file("tests/images/squares.jp2").unwrap();
+    assert_eq!(md.format(), ImageFormat::Jp2);
+    assert_eq!(md.mime_type(), "image/jp2");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert_eq!(md.color_depth(), None);
+
+    let data = include_bytes!("images/squares.j2k");
+    // the image offset exceeds the reference grid size
+    let mut bad = data.to_vec();
+    bad[19] = 10;
+    assert!(Jp2::load_from_buf(&bad).is_err());
+    assert!(Jp2::load_from_buf(&data[..20]).is_err());
+
+    let data = include_bytes!("images/squares.jp2");
+    // the file type box must follow the signature box
+    let mut bad = data.to_vec();
+    bad[16..20].copy_from_slice(b"free");
+    assert!(Jp2::load_from_buf(&bad).is_err());
+    let mut bad = data.to_vec();
+    bad[8] = b'\n';
+    assert!(Jp2::load_from_buf(&bad).is_err());
+    // the header box lacks the image header
+    let mut bad = data.to_vec();
+    bad[0x2c..0x30].copy_from_slice(b"ihdx");
+    assert!(Jp2::load_from_buf(&bad).is_err());
+    assert!(Jp2::load_from_buf(&data[..0x30]).is_err());
+}
+
+#[test]
+fn test_webp_metadata() {
+    let md = immeta::load_from_file("tests/images/metadata.webp").unwrap();
+    let md = md.into::<Webp>().ok().expect("not WEBP metadata");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+    assert!(md.animation().is_none());
+
+    assert_eq!(md.xmp(), Some(r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF/></x:xmpmeta>"#));
+
+    let exif = md.exif().expect("no EXIF metadata");
+    let ifds = TiffReader::new(io::Cursor::new(exif)).ifds().unwrap();
+    let ifd = (&ifds).into_iter().next().unwrap().unwrap();
+    let entry = ifd.into_iter().next().unwrap().unwrap();
+    assert_eq!(entry.tag_name(), Tag::Orientation);
+    assert_eq!(entry.values::<entry_types::Short>().unwrap().next().unwrap().unwrap(), 6);
+    assert_eq!(entry.raw_bytes().unwrap(), vec![6, 0]);
+
+    // metadata is only read if the corresponding flags are set
+    let mut data = include_bytes!("images/metadata.webp").to_vec();
+    data[20] = 0x10;
+    let md = Webp::load_from_buf(&data).unwrap();
+    assert_eq!(md.exif(), None);
+    assert_eq!(md.xmp(), None);
+
+    assert!(immeta::load_from_file("tests/images/lossless.webp").unwrap()
+        .into::<Webp>().unwrap().exif().is_none());
+}
+
+#[test]
+fn test_webp_icc_profile() {
+    let md = immeta::load_from_file("tests/images/icc.webp").unwrap();
+    let md = md.into::<Webp>().ok().expect("not WEBP metadata");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+
+    let profile = md.icc_profile().expect("no ICC profile");
+    assert_eq!(profile.len(), 132);
+    assert_eq!(&profile[..4], &[0, 0, 0, 132]);
+    assert_eq!(&profile[36..40], b"acsp");
+    assert_eq!(md.exif(), None);
+
+    let data = include_bytes!("images/icc.webp");
+    let options = immeta::LoadOptions::default().max_alloc(131);
+    assert!(immeta::load_with_options(&mut io::Cursor::new(&data[..]), &options).is_err());
+    // the ICCP chunk is truncated
+    assert!(Webp::load_from_buf(&data[..100]).is_err());
+
+    assert!(immeta::load_from_file("tests/images/metadata.webp").unwrap()
+        .into::<Webp>().unwrap().icc_profile().is_none());
+}
+
+#[test]
+fn test_webp_animated() {
+    let md = immeta::load_from_file("tests/images/animated.webp").unwrap();
+
+    assert_eq!(md.mime_type(), "image/webp");
+    assert_eq!(md.dimensions(), SQUARES_DIM);
+
+    let md = md.into::<Webp>().ok().expect("not WEBP metadata");
+    match md {
+        webp::Metadata::VP8X(ref md) => {
+            assert_eq!(md.dimensions, SQUARES_DIM);
+            assert!(!md.has_icc_profile);
+            assert!(md.has_alpha);
+            assert!(!md.has_exif);
+            assert!(!md.has_xmp);
+            assert!(md.has_animation);
+        }
+        _ => panic!("not VP8X metadata")
+    }
+
+    let animation = md.animation().expect("no animation metadata");
+    assert_eq!(animation.background_color, 0xff00ff00);
+    assert_eq!(animation.loop_count, 3);
+    assert_eq!(animation.frames, vec![
+        webp::Frame {
+            left: 0, top: 0,
+            dimensions: SQUARES_DIM,
+            duration: 100,
+            blending_method: webp::BlendingMethod::AlphaBlending,
+            disposal_method: webp::DisposalMethod::None
+        },
+        webp::Frame {
+            left: 2, top: 4,
+            dimensions: Dimensions { width: 4, height: 2 },
+            duration: 250,
+            blending_method: webp::BlendingMethod::NoBlending,
+            disposal_method: webp::DisposalMethod::Background
+        }
+    ]);
+}
+
+#[test]
+fn test_detect_format() {
+    let cases = [
+        ("tests/images/owlet.jpg", ImageFormat::Jpeg),
+        ("tests/images/exif.jpg", ImageFormat::Jpeg),
+        ("tests/images/owlet.png", ImageFormat::Png),
+        ("tests/images/animated.png", ImageFormat::Png),
+        ("tests/images/owlet.gif", ImageFormat::Gif),
+        ("tests/images/drop.gif", ImageFormat::Gif),
+        ("tests/images/cherry.webp", ImageFormat::Webp),
+        ("tests/images/lossless.webp", ImageFormat::Webp),
+        ("tests/images/squares.bmp", ImageFormat::Bmp),
+        ("tests/images/squares.tif", ImageFormat::Tiff),
+        ("tests/images/squares_be.tif", ImageFormat::Tiff),
+        ("tests/images/icon.ico", ImageFormat::Ico),
+        ("tests/images/squares.ppm", ImageFormat::Pnm),
+        ("tests/images/squares.exr", ImageFormat::Exr),
+        ("tests/images/squares.qoi", ImageFormat::Qoi),
+        ("tests/images/squares.avif", ImageFormat::Avif),
+        ("tests/images/squares.svg", ImageFormat::Svg),
+        ("tests/images/squares.jp2", ImageFormat::Jp2),
+        ("tests/images/squares.j2k", ImageFormat::Jp2),
+        ("tests/images/squares.dds", ImageFormat::Dds),
+        ("tests/images/squares.psd", ImageFormat::Psd),
+    ];
+    for &(path, format) in &cases {
+        assert_eq!(immeta::detect_format_from_file(path).unwrap(), format, "{}", path);
+    }
+
+    assert!(immeta::detect_format_from_buf(b"").is_err());
+    assert!(immeta::detect_format_from_buf(b"not an image").is_err());
+}
+
+#[test]
+fn test_load_many() {
+    let mut paths: Vec<_> = fs::read_dir("tests/images").unwrap().map(|e| e.unwrap().path()).collect();
+    paths.sort();
+    // a missing file does not stop the iteration
+    paths.insert(1, "tests/images/nonexistent.png".into());
+
+    let results: Vec<_> = immeta::load_many(paths.clone()).collect();
+    assert_eq!(results.len(), paths.len());
+    for (path, &(ref result_path, ref result)) in paths.iter().zip(&results) {
+        assert_eq!(path, result_path);
+        match (result, immeta::load_from_file(path)) {
+            (&Ok(ref md), Ok(expected)) => assert_eq!(md, &expected, "{}", path.display()),
+            (&Err(_), Err(_)) => {}
+            (result, expected) => panic!("{}: {:?} != {:?}", path.display(), result, expected)
+        }
+    }
+    assert!(results[1].1.is_err());
+    assert!(results.iter().filter(|r| r.1.is_ok()).count() > paths.len() / 2);
+}
+
+#[test]
+fn test_load_from_file_with_hint() {
+    let md = immeta::load_from_file_with_hint("tests/images/squares.psd").unwrap();
+    assert_eq!(md.format(), ImageFormat::Psd);
+
+    // a wrong extension does not prevent loading the image
+    let path = env::temp_dir().join("immeta-hint-test.JPG");
+    fs::copy("tests/images/squares.png", &path).unwrap();
+    let md = immeta::load_from_file_with_hint(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(md.unwrap().format(), ImageFormat::Png);
+
+    // a broken image with a known signature is decoded only once
+    let path = env::temp_dir().join("immeta-hint-test.png");
+    fs::write(&path, &include_bytes!("images/squares.png")[..20]).unwrap();
+    let result = immeta::load_from_file_with_hint(&path);
+    fs::remove_file(&path).unwrap();
+    match result {
+        Err(immeta::Error::UnexpectedEndOfFile(..)) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+    // both the hinted and the JPEG decoders are tried for images without a signature
+    let path = env::temp_dir().join("immeta-hint-test.wbmp");
+    fs::write(&path, b"\x00\x00").unwrap();
+    let result = immeta::load_from_file_with_hint(&path);
+    fs::remove_file(&path).unwrap();
+    match result {
+        Err(immeta::Error::NoFormatMatched(ref errors)) => {
+            let formats: Vec<_> = errors.iter().map(|&(f, _)| f).collect();
+            assert_eq!(formats, vec![ImageFormat::Wbmp, ImageFormat::Jpeg]);
+        }
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    assert_eq!(ImageFormat::from_extension("JPG"), Some(ImageFormat::Jpeg));
+    assert_eq!(ImageFormat::from_extension("heic"), Some(ImageFormat::Heif));
+    assert_eq!(ImageFormat::from_extension("txt"), None);
+
+    assert_eq!(ImageFormat::Jpeg.extensions()[0], "jpg");
+    assert_eq!(ImageFormat::Tiff.as_str(), "TIFF");
+    assert_eq!(ImageFormat::Exr.to_string(), ImageFormat::Exr.as_str());
+    for &format in &[ImageFormat::Png, ImageFormat::Heif, ImageFormat::Avif, ImageFormat::Psd] {
+        for ext in format.extensions() {
+            assert_eq!(ImageFormat::from_extension(ext), Some(format), "{}", ext);
+        }
+    }
+
+    assert!(immeta::load_from_file_with_hint("tests/images/nonexistent.png").is_err());
+}
+
+#[test]
+fn test_image_format_mime_type() {
+    for &format in &[ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Bmp, ImageFormat::Tiff,
+                     ImageFormat::Webp, ImageFormat::Heif, ImageFormat::Avif, ImageFormat::Wbmp] {
+        assert_eq!(ImageFormat::from_mime_type(format.mime_type()), Some(format), "{}", format);
+    }
+
+    assert_eq!(ImageFormat::from_mime_type("IMAGE/PNG"), Some(ImageFormat::Png));
+    assert_eq!(ImageFormat::from_mime_type("image/svg+xml; charset=utf-8"), Some(ImageFormat::Svg));
+    assert_eq!(ImageFormat::from_mime_type("image/jpg"), Some(ImageFormat::Jpeg));
+    assert_eq!(ImageFormat::from_mime_type("image/x-ms-bmp"), Some(ImageFormat::Bmp));
+    assert_eq!(ImageFormat::from_mime_type("image/heic"), Some(ImageFormat::Heif));
+    assert_eq!(ImageFormat::from_mime_type("text/plain"), None);
+    assert_eq!(ImageFormat::from_mime_type(""), None);
+
+    let md = immeta::load_from_file("tests/images/squares.bmp").unwrap();
+    assert_eq!(md.mime_type(), md.format().mime_type());
+}
+
+#[test]
+fn test_signatures() {
+    for &(format, magic) in immeta::signatures() {
+        assert_eq!(ImageFormat::from_magic(magic), Some(format), "{:?}", magic);
+    }
+    assert!(immeta::signatures().iter().any(|&(f, m)| f == ImageFormat::Png && m == b"\x89PNG\r\n\x1a\n"));
+
+    assert_eq!(ImageFormat::from_magic(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::Webp));
+    assert_eq!(ImageFormat::from_magic(b"RIFF\0\0\0\0WAVE"), None);
+    assert_eq!(ImageFormat::from_magic(b"P6\n8 6\n"), Some(ImageFormat::Pnm));
+    assert_eq!(ImageFormat::from_magic(b"\0\0\0\x1cftypavif"), Some(ImageFormat::Avif));
+    assert_eq!(ImageFormat::from_magic(b"\xff"), None);
+    assert_eq!(ImageFormat::from_magic(b""), None);
+}
+
+#[test]
+fn test_common_metadata() {
+    let md = immeta::load_from_file("tests/images/drop.gif").unwrap();
+    assert_eq!(md.common(), immeta::CommonMetadata {
+        format: ImageFormat::Gif,
+        dimensions: DROP_DIM,
+        color_depth: Some(7),
+        mime_type: "image/gif",
+        is_animated: true,
+        frame_count: 30
+    });
+
+    let md = immeta::load_from_file("tests/images/cherry.webp").unwrap();
+    assert_eq!(md.common(), immeta::CommonMetadata {
+        format: ImageFormat::Webp,
+        dimensions: CHERRY_DIM,
+        color_depth: None,
+        mime_type: "image/webp",
+        is_animated: false,
+        frame_count: 1
+    });
+}
+
+#[test]
+fn test_load_generic() {
+    let mut f = fs::File::open("tests/images/owlet.jpg").unwrap();
+    let md = Jpeg::load_generic(&mut f).unwrap();
+    assert_eq!(md.format(), ImageFormat::Jpeg);
+    assert_eq!(md.dimensions(), OWLET_DIM);
+
+    let mut f = fs::File::open("tests/images/squares.ppm").unwrap();
+    let md = Pnm::load_generic(&mut f).unwrap();
+    assert_eq!(md.as_ref::<Pnm>().unwrap().format, pnm::Format::Pixmap);
+
+    // the format is not detected, so loading an image of another type fails
+    let mut f = fs::File::open("tests/images/owlet.jpg").unwrap();
+    assert!(Png::load_generic(&mut f).is_err());
+
+    // SVG images without an intrinsic size have no generic dimensions
+    assert!(Svg::load_generic(&mut io::Cursor::new(&b"<svg width='50%'/>"[..])).is_err());
+}
+
+#[test]
+fn test_load_from_read() {
+    // a reader which does not implement BufRead, like a decompressor
+    struct Unbuffered<R>(R);
+    impl<R: Read> Read for Unbuffered<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    let data = fs::read("tests/images/owlet.png").unwrap();
+    let md = png::Metadata::load_from_read(&mut Unbuffered(&data[..])).unwrap();
+    assert_eq!(md, Png::load_from_buf(&data).unwrap());
+    assert_eq!(Png::load_from_read(&mut Unbuffered(&data[..])).unwrap(), md);
+
+    let mut f = fs::File::open("tests/images/drop.gif").unwrap();
+    assert_eq!(Gif::load_from_read(&mut f).unwrap().frames_number(), 30);
+    assert!(Gif::load_from_read(&mut Unbuffered(&data[..])).is_err());
+}
+
+#[test]
+fn test_generic_from_metadata() {
+    for path in &["tests/images/owlet.jpg", "tests/images/owlet.png", "tests/images/owlet.gif",
+                  "tests/images/squares.avif", "tests/images/squares.jp2"] {
+        let md = immeta::load_from_file(path).unwrap();
+        let round_trip: GenericMetadata = match md.clone() {
+            GenericMetadata::Jpeg(md) => md.into(),
+            GenericMetadata::Png(md) => md.into(),
+            GenericMetadata::Gif(md) => md.into(),
+            GenericMetadata::Heif(md) => md.into(),
+            GenericMetadata::Jp2(md) => md.into(),
+            md => panic!("unexpected metadata: {:?}", md)
+        };
+        assert_eq!(round_trip, md);
+    }
+
+    let md = Qoi::load_from_file("tests/images/squares.qoi").unwrap();
+    let generic = GenericMetadata::from(md.clone());
+    assert_eq!(generic.format(), ImageFormat::Qoi);
+    assert_eq!(generic.into::<Qoi>().unwrap(), md);
+}
+
+#[test]
+fn test_load_options() {
+    use immeta::{LoadOptions, LoadableMetadata};
+    use std::io::Cursor;
+
+    let limited = |max_alloc| LoadOptions::default().max_alloc(max_alloc);
+    let frames_limited = |max| LoadOptions::default().max_gif_frames(Some(max));
+
+    let data = fs::read("tests/images/icc.png").unwrap();
+    assert!(immeta::load_with_options(&mut Cursor::new(&data), &LoadOptions::default()).is_ok());
+    // iCCP chunk is 50 bytes long, and the decompressed profile is 100 bytes long
+    assert!(png::Metadata::load_with_options(&mut Cursor::new(&data), &limited(49)).is_err());
+    let md = png::Metadata::load_with_options(&mut Cursor::new(&data), &limited(50));
+    if cfg!(feature = "flate2") {
+        assert!(md.is_err());
+        assert!(png::Metadata::load_with_options(&mut Cursor::new(&data), &limited(100)).is_ok());
+    } else {
+        assert!(md.is_ok());
+    }
+
+    // bits per sample entry data of 6 bytes is stored out of line
+    let data = fs::read("tests/images/squares.tif").unwrap();
+    assert!(immeta::load_with_options(&mut Cursor::new(&data), &limited(6)).is_ok());
+    match immeta::load_with_options(&mut Cursor::new(&data), &limited(5)) {
+        Err(immeta::Error::InvalidFormat(_, Some(_))) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+    assert!(tiff::Metadata::load_with_options(&mut Cursor::new(&data), &limited(5)).is_err());
+
+    // non-seekable TIFF streams are buffered in memory, which is subject to the limit too
+    assert!(tiff::Metadata::load(&mut &data[..]).is_ok());
+    match tiff::Metadata::load_with_options(&mut &data[..], &limited(data.len() - 1)) {
+        Err(immeta::Error::InvalidFormat(..)) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+    assert!(tiff::Metadata::load_with_options(&mut &data[..], &limited(data.len())).is_ok());
+
+    // the total sizes of JPEG comments, ICC profile chunks and extended XMP chunks are limited
+    let data = fs::read("tests/images/comment.jpg").unwrap();
+    assert!(jpeg::Metadata::load_with_options(&mut &data[..], &limited(20)).is_err());
+    assert!(jpeg::Metadata::load_from_seek_with_options(&mut Cursor::new(&data), &limited(20)).is_err());
+    assert_eq!(jpeg::Metadata::load_with_options(&mut &data[..], &limited(21)).unwrap().comments.len(), 2);
+    let data = fs::read("tests/images/icc.jpg").unwrap();
+    assert!(immeta::load_with_options(&mut Cursor::new(&data), &limited(22)).is_err());
+    assert!(immeta::load_with_options(&mut Cursor::new(&data), &limited(23)).is_ok());
+    let data = fs::read("tests/images/xmp.jpg").unwrap();
+    let xmp_len = Jpeg::load_from_buf(&data).unwrap().extended_xmp.unwrap().len();
+    assert!(jpeg::Metadata::load_with_options(&mut &data[..], &limited(xmp_len - 1)).is_err());
+    assert!(jpeg::Metadata::load_with_options(&mut &data[..], &limited(xmp_len)).is_ok());
+
+    // EXIF entry data is limited too, but broken EXIF metadata does not prevent loading the image
+    let data = fs::read("tests/images/exif.jpg").unwrap();
+    let md = jpeg::Metadata::load_with_options(&mut &data[..], &limited(6)).unwrap();
+    assert!(md.exif.is_none());
+    let md = jpeg::Metadata::load_with_options(&mut &data[..], &limited(7)).unwrap();
+    assert!(md.exif.is_some());
+
+    // GIF color tables are only loaded on request, both from seekable and streaming sources
+    let data = fs::read("tests/images/owlet.gif").unwrap();
+    let options = LoadOptions::default().load_color_tables(true);
+    let md = gif::Metadata::load_from_seek_with_options(&mut Cursor::new(&data), &options).unwrap();
+    let colors = md.global_color_table.unwrap().colors.unwrap();
+    assert_eq!(colors.len(), 256);
+    assert_eq!(colors[0], [2, 2, 1]);
+    assert_eq!(colors[255], [125, 138, 131]);
+    let md = gif::Metadata::load_with_options(&mut &data[..], &options).unwrap();
+    assert_eq!(md.global_color_table.unwrap().colors.map(|c| c.len()), Some(256));
+    assert!(immeta::load_from_buf(&data).unwrap().into::<Gif>().unwrap().global_color_table.unwrap().colors.is_none());
+    assert!(gif::Metadata::load_with_options(&mut &data[..500], &options).is_err());
+    let md = gif::Metadata::load_with_options(&mut &data[..], &frames_limited(1)).unwrap();
+    assert_eq!(md.frames_number(), 1);
+    assert!(!md.has_more_frames);
+    assert!(!md.is_animated());
+
+    // loading of GIF frames stops at the limit, but animation is still detected
+    let data = fs::read("tests/images/drop.gif").unwrap();
+    let md = gif::Metadata::load_with_options(&mut &data[..], &frames_limited(3)).unwrap();
+    assert_eq!(md.frames_number(), 3);
+    assert!(md.has_more_frames);
+    assert!(md.is_animated());
+    assert!(md.to_string().contains("at least 3 frame(s)"));
+    let md = gif::Metadata::load_from_seek_with_options(&mut Cursor::new(&data), &frames_limited(0)).unwrap();
+    assert_eq!(md.frames_number(), 1);
+    assert!(md.is_animated());
+    let md = gif::Metadata::load_with_options(&mut &data[..], &frames_limited(30)).unwrap();
+    assert_eq!(md.frames_number(), 30);
+    assert!(!md.has_more_frames);
+    let md = immeta::load_with_options(&mut Cursor::new(&data), &frames_limited(1)).unwrap();
+    assert_eq!(md.frame_count(), 1);
+    assert!(md.is_animated());
+    assert!(md.into::<Gif>().unwrap().is_animated());
+
+    // only the logical screen descriptor and the global color table are read in header-only mode
+    let options = LoadOptions::default().gif_header_only(true).load_color_tables(true);
+    let header = &data[..13 + 256 * 3];
+    let md = gif::Metadata::load_with_options(&mut &header[..], &options).unwrap();
+    assert_eq!(md.dimensions, DROP_DIM);
+    assert_eq!(md.version, gif::Version::V89a);
+    assert_eq!(md.global_color_table.as_ref().and_then(|t| t.colors.as_ref()).map(|c| c.len()), Some(256));
+    assert!(md.blocks.is_empty());
+    assert_eq!(md.frames_number(), 0);
+    assert!(!md.is_animated());
+    let md = immeta::load_with_options(&mut Cursor::new(&data), &options).unwrap();
+    assert_eq!(md.dimensions(), DROP_DIM);
+    assert!(gif::Metadata::load_with_options(&mut &header[..header.len() - 1], &options).is_err());
+}
+
+#[test]
+fn test_load_unknown_format() {
+    match immeta::load_from_buf(b"not an image at all") {
+        Err(immeta::Error::NoFormatMatched(ref errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].0, ImageFormat::Jpeg);
+        }
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let err = immeta::load_from_buf(b"not an image at all").unwrap_err();
+    assert!(err.to_string().starts_with("unknown or unsupported image type; tried JPEG decoder: "), "{}", err);
+
+    match immeta::detect_format_from_buf(b"not an image") {
+        Err(immeta::Error::NoFormatMatched(ref errors)) => assert!(errors.is_empty()),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn test_error_clone() {
+    let err = immeta::load_from_buf(b"not an image at all").unwrap_err();
+    assert_eq!(err.clone().to_string(), err.to_string());
+
+    let err = immeta::load_from_file("tests/images/does-not-exist.png").unwrap_err();
+    match (&err, &err.clone()) {
+        (&immeta::Error::Io(ref e1), &immeta::Error::Io(ref e2)) => {
+            assert_eq!(e1.kind(), e2.kind());
+            assert_eq!(e1.raw_os_error(), e2.raw_os_error());
+            assert_eq!(e1.to_string(), e2.to_string());
+        }
+        other => panic!("unexpected errors: {:?}", other)
+    }
+
+    let err = immeta::Error::from(io::Error::new(io::ErrorKind::Other, "custom error"));
+    assert_eq!(err.clone().to_string(), "I/O error: custom error");
+}
+
+#[test]
+fn test_error_offset() {
+    let mut data = include_bytes!("images/owlet.png").to_vec();
+    data[12..16].copy_from_slice(b"XXXX");
+    let err = Png::load_from_buf(&data).unwrap_err();
+    assert_eq!(err.offset(), Some(12));
+    assert!(err.to_string().ends_with(" at offset 0xC"), "{}", err);
+
+    // break the PLTE chunk length
+    let mut data = include_bytes!("images/palette.png").to_vec();
+    data[36] = 16;
+    let err = Png::load_from_buf(&data).unwrap_err();
+    assert_eq!(err.offset(), Some(33));
+    assert!(err.to_string().ends_with("invalid PLTE chunk length: 16 at offset 0x21"), "{}", err);
+
+    // the data ends in the middle of the tRNS chunk
+    let data = &include_bytes!("images/palette.png")[..66];
+    assert_eq!(Png::load_from_buf(data).unwrap_err().offset(), Some(60));
+
+    // the data ends in the middle of the first IFD
+    let data = &include_bytes!("images/squares.tif")[..20];
+    assert_eq!(Tiff::load_from_buf(data).unwrap_err().offset(), Some(8));
+
+    assert_eq!(immeta::load_from_file("tests/images/does-not-exist.png").unwrap_err().offset(), None);
+}
+
+#[test]
+fn test_load_corrupt_png() {
+    let mut data = include_bytes!("images/owlet.png").to_vec();
+    // break the IHDR chunk type
+    data[12..16].copy_from_slice(b"XXXX");
+
+    let err = immeta::load_from_buf(&data).unwrap_err();
+    assert!(err.to_string().contains("invalid PNG chunk"), "{}", err);
+}
+
+#[test]
+fn test_display() {
+    let md = immeta::load_from_file("tests/images/squares.png").unwrap();
+    assert_eq!(md.to_string(), "PNG image, 8x6 (image/png)");
+    assert_eq!(md.as_ref::<Png>().unwrap().to_string(), "PNG image, 8x6, RGB, 24 bpp");
+
+    let md = Png::load_from_file("tests/images/animated.png").unwrap();
+    assert_eq!(md.to_string(), "PNG image, 8x6, RGB with alpha channel, 32 bpp, animated");
+
+    let md = immeta::load_from_file("tests/images/exif.jpg").unwrap();
+    assert_eq!(md.to_string(), "JPEG image, 16x8 (image/jpeg)");
+    assert_eq!(md.as_ref::<Jpeg>().unwrap().to_string(),
+               "JPEG image, 16x8, 8-bit samples, Sequential DCT, Huffman coding");
+
+    let md = Gif::load_from_file("tests/images/owlet.gif").unwrap();
+    assert_eq!(md.to_string(), "GIF image, version 89a, 1280x857, 1 frame(s)");
+}
+
+#[test]
+fn test_color_depth() {
+    let cases = [
+        ("tests/images/owlet.jpg", Some(8)),
+        ("tests/images/squares.png", Some(24)),
+        ("tests/images/palette.png", Some(8)),
+        ("tests/images/owlet.gif", Some(8)),
+        ("tests/images/cherry.webp", None),
+        ("tests/images/squares.bmp", Some(24)),
+        ("tests/images/squares.tif", Some(24)),
+    ];
+    for &(path, depth) in &cases {
+        assert_eq!(immeta::load_from_file(path).unwrap().color_depth(), depth, "{}", path);
+    }
+}
+
+#[test]
+fn test_animation() {
+    let cases = [
+        ("tests/images/owlet.jpg", 1),
+        ("tests/images/owlet.png", 1),
+        ("tests/images/animated.png", 3),
+        ("tests/images/owlet.gif", 1),
+        ("tests/images/cherry.webp", 1),
+        ("tests/images/squares.bmp", 1),
+    ];
+    for &(path, frames) in &cases {
+        let md = immeta::load_from_file(path).unwrap();
+        assert_eq!(md.frame_count(), frames, "{}", path);
+        assert_eq!(md.is_animated(), frames > 1, "{}", path);
+    }
+
+    let md = immeta::load_from_file("tests/images/drop.gif").unwrap();
+    assert!(md.is_animated());
+    assert_eq!(md.frame_count(), md.as_ref::<Gif>().unwrap().frames_number());
+
+    let md = immeta::load_from_file("tests/images/animated.webp").unwrap();
+    assert!(md.is_animated());
+    assert_eq!(md.frame_count(), md.as_ref::<Webp>().unwrap().animation().unwrap().frames.len());
+}
+
+#[test]
+fn test_has_alpha() {
+    let cases = [
+        ("tests/images/owlet.jpg", false),
+        ("tests/images/squares.png", false),
+        ("tests/images/palette.png", true),
+        ("tests/images/animated.png", true),
+        ("tests/images/owlet.gif", false),
+        ("tests/images/drop.gif", true),
+        ("tests/images/cherry.webp", false),
+        ("tests/images/lossless.webp", true),
+        ("tests/images/animated.webp", true),
+        ("tests/images/icc.webp", false),
+        ("tests/images/squares.bmp", false),
+    ];
+    for &(path, alpha) in &cases {
+        assert_eq!(immeta::load_from_file(path).unwrap().has_alpha(), alpha, "{}", path);
+    }
+}
+
+#[test]
+fn test_load_from_reader() {
+    for path in &["tests/images/owlet.jpg", "tests/images/owlet.png", "tests/images/cherry.webp",
+                  "tests/images/squares.bmp", "tests/images/squares.tif"] {
+        let data = fs::read(path).unwrap();
+        let expected = immeta::load_from_buf(&data).unwrap();
+        // a byte slice is not seekable
+        let md = immeta::load_from_reader(&mut &data[..]).unwrap();
+        assert_eq!(md, expected, "{}", path);
+    }
+}
+
+#[test]
+fn test_load_from_slice() {
+    for path in &["tests/images/owlet.jpg", "tests/images/drop.gif", "tests/images/squares.tif",
+                  "tests/images/squares.svg", "tests/images/squares.psd"] {
+        // the slice borrows a local buffer, like a memory-mapped file
+        let data = fs::read(path).unwrap();
+        let md = immeta::load_from_slice(&data[..]).unwrap();
+        assert_eq!(md, immeta::load_from_buf(&data).unwrap(), "{}", path);
+    }
+
+    match immeta::load_from_slice(b"") {
+        Err(immeta::Error::NoFormatMatched(ref errors)) => assert_eq!(errors[0].0, ImageFormat::Jpeg),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn test_min_header_len() {
+    fn check<M: LoadableMetadata>(path: &str, format: ImageFormat) {
+        let data = fs::read(path).unwrap();
+        let len = M::min_header_len();
+        assert_eq!(format.min_header_len(), len, "{}", path);
+        assert!(data.len() >= len, "{}", path);
+        for n in 0..len {
+            assert!(M::load_from_buf(&data[..n]).is_err(), "{}: {} bytes", path, n);
+        }
+    }
+
+    check::<jpeg::Metadata>("tests/images/owlet.jpg", ImageFormat::Jpeg);
+    check::<png::Metadata>("tests/images/squares.png", ImageFormat::Png);
+    check::<gif::Metadata>("tests/images/drop.gif", ImageFormat::Gif);
+    check::<webp::Metadata>("tests/images/lossless.webp", ImageFormat::Webp);
+    check::<bmp::Metadata>("tests/images/squares.bmp", ImageFormat::Bmp);
+    check::<tiff::Metadata>("tests/images/squares.tif", ImageFormat::Tiff);
+    check::<ico::Metadata>("tests/images/icon.ico", ImageFormat::Ico);
+    check::<pnm::Metadata>("tests/images/squares.ppm", ImageFormat::Pnm);
+    check::<exr::Metadata>("tests/images/squares.exr", ImageFormat::Exr);
+    check::<qoi::Metadata>("tests/images/squares.qoi", ImageFormat::Qoi);
+    check::<heif::Metadata>("tests/images/squares.avif", ImageFormat::Avif);
+    check::<svg::Metadata>("tests/images/squares.svg", ImageFormat::Svg);
+    check::<dds::Metadata>("tests/images/squares.dds", ImageFormat::Dds);
+    check::<psd::Metadata>("tests/images/squares.psd", ImageFormat::Psd);
+    check::<wbmp::Metadata>("tests/images/squares.wbmp", ImageFormat::Wbmp);
+    check::<jp2::Metadata>("tests/images/squares.j2k", ImageFormat::Jp2);
+
+    // some headers can be parsed from exactly this number of bytes
+    assert!(Wbmp::load_from_buf(&[0x00, 0x00, 0x08, 0x06]).is_ok());
+    let data = fs::read("tests/images/squares.j2k").unwrap();
+    assert!(Jp2::load_from_buf(&data[..jp2::Metadata::min_header_len()]).is_ok());
+    let data = fs::read("tests/images/squares.qoi").unwrap();
+    assert!(Qoi::load_from_buf(&data[..qoi::Metadata::min_header_len()]).is_ok());
+}
+
+#[test]
+fn test_load_from_partial_buf() {
+    for path in &["tests/images/owlet.jpg", "tests/images/owlet.png", "tests/images/cherry.webp", "tests/images/squares.bmp", "tests/images/squares.tif",
+                  "tests/images/squares.avif", "tests/images/squares.jp2"] {
+        let data = fs::read(path).unwrap();
+        let expected = immeta::load_from_buf(&data).unwrap();
+
+        // emulate a client which buffers as many bytes as requested
+        let mut len = 0;
+        let md = loop {
+            match immeta::load_from_partial_buf(&data[..len]) {
+                Ok(md) => break md,
+                Err(immeta::Error::NeedMoreData { at_least }) => {
+                    assert!(at_least > len && at_least <= data.len(), "{}: {} -> {}", path, len, at_least);
+                    len = at_least;
+                }
+                Err(e) => panic!("{}: {} bytes: {}", path, len, e)
+            }
+        };
+        // JPEG scans are counted only in the available data, so other fields may differ
+        assert_eq!(md.common(), expected.common(), "{}", path);
+    }
+
+    // all frames of GIF images are needed
+    let data = fs::read("tests/images/drop.gif").unwrap();
+    match immeta::load_from_partial_buf(&data[..data.len() - 1]) {
+        Err(immeta::Error::NeedMoreData { at_least }) => assert_eq!(at_least, data.len()),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let data = fs::read("tests/images/squares.png").unwrap();
+    match png::Metadata::load_from_partial_buf(&data[..20]) {
+        Err(immeta::Error::NeedMoreData { at_least }) => assert_eq!(at_least, 33),
+        other => panic!("unexpected result: {:?}", other)
+    }
+    // the length of IHDR chunk data is known when its header has been read
+    match png::Metadata::load_from_partial_buf(&data[..40]) {
+        Err(immeta::Error::NeedMoreData { at_least }) => assert!(at_least > 40),
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    // loading from a complete buffer still reports truncation as the end of file
+    match png::Metadata::load_from_buf(&data[..20]) {
+        Err(immeta::Error::UnexpectedEndOfFile(..)) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+    // invalid images are reported as such, regardless of their length
+    let mut bad = data[..16].to_vec();
+    bad[12..16].copy_from_slice(b"IDAT");
+    match immeta::load_from_partial_buf(&bad) {
+        Err(immeta::Error::InvalidFormat(..)) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn test_load_truncated() {
+    use immeta::LoadOptions;
+    use std::io::Cursor;
+
+    let options = LoadOptions::default().allow_truncated(true);
+    let load = |data: &[u8]| immeta::load_with_options(&mut Cursor::new(data), &options);
+
+    // each prefix contains the header and a part of the following data
+    for &(path, len) in &[("tests/images/owlet.png", 40), ("tests/images/owlet.gif", 1000),
+                          ("tests/images/drop.gif", 2000), ("tests/images/animated.webp", 100),
+                          ("tests/images/owlet.jpg", 4096), ("tests/images/squares.bmp", 54),
+                          ("tests/images/squares.qoi", 14), ("tests/images/squares.psd", 26),
+                          ("tests/images/squares.j2k", 42)] {
+        let data = fs::read(path).unwrap();
+        let expected = immeta::load_from_buf(&data).unwrap();
+        let md = load(&data[..len]).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(md.format(), expected.format(), "{}", path);
+        assert_eq!(md.dimensions(), expected.dimensions(), "{}", path);
+    }
+
+    // optional metadata after the end of the stream is missing
+    let data = fs::read("tests/images/drop.gif").unwrap();
+    let md = load(&data[..data.len() / 2]).unwrap().into::<Gif>().unwrap();
+    assert!(md.frames_number() > 0 && md.frames_number() < 30);
+    assert_eq!(md.loop_count(), Some(0));
+    assert_eq!(load(&data).unwrap().into::<Gif>().unwrap().frames_number(), 30);
+    assert!(immeta::load_from_buf(&data[..data.len() / 2]).is_err());
+
+    let data = fs::read("tests/images/animated.webp").unwrap();
+    match load(&data[..30]).unwrap().into::<Webp>().unwrap() {
+        webp::Metadata::VP8X(md) => {
+            assert!(md.has_animation);
+            assert_eq!(md.animation, None);
+        }
+        md => panic!("unexpected metadata: {:?}", md)
+    }
+
+    let data = fs::read("tests/images/owlet.png").unwrap();
+    let md = load(&data[..33]).unwrap().into::<Png>().unwrap();
+    assert_eq!(md.dimensions, OWLET_DIM);
+    assert_eq!(md.gamma, None);
+
+    // incomplete headers are still reported
+    for &(path, len) in &[("tests/images/owlet.png", 28), ("tests/images/drop.gif", 12),
+                          ("tests/images/animated.webp", 25)] {
+        let data = fs::read(path).unwrap();
+        match load(&data[..len]) {
+            Err(immeta::Error::UnexpectedEndOfFile(..)) => {}
+            other => panic!("{}: unexpected result: {:?}", path, other)
+        }
+    }
+}
+
+#[test]
+fn test_load_from_reader_large_header() {
+    let data = fs::read("tests/images/squares.png").unwrap();
+    let mut large = data[..33].to_vec();  // signature and IHDR
+    large.extend_from_slice(&[0, 1, 0, 0]);  // 64 KiB chunk
+    large.extend_from_slice(b"tEXt");
+    large.extend(std::iter::repeat(0).take(64 * 1024 + 4));
+    large.extend_from_slice(&data[33..]);
+
+    assert!(immeta::load_from_buf(&large).is_ok());
+    let err = immeta::load_from_reader(&mut &large[..]).unwrap_err();
+    assert!(err.to_string().contains("does not fit"), "{}", err);
+}